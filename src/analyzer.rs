@@ -0,0 +1,1283 @@
+use realfft::num_complex::Complex32;
+use realfft::{RealFftPlanner, RealToComplex};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::averaging::{Averaging, BandAverager};
+use crate::bands::{BandLayout, BandScale};
+use crate::chroma::{ChromaAnalyzer, PITCH_CLASSES, estimate_key};
+use crate::features::Features;
+use crate::loudness::LoudnessMeter;
+use crate::pitch::{DEFAULT_A4_HZ, PitchDetector};
+use crate::tempo::{SpectralFlux, TempoTracker};
+use crate::types::{AnalyzerConfig, Channel, ChannelSpectrum, MIN_DBFS, Meter, Spectrum};
+use crate::weighting::{Weighting, WeightingFilter};
+use crate::window::{WindowFunction, enbw};
+
+/// Turns a stream of samples into smoothed spectrum frames.
+///
+/// Samples are pushed in chunks of any size; every time a full FFT window is
+/// available a [`Spectrum`] is queued, then the window advances by the hop.
+/// Multi-channel input is interleaved; each frame then carries the spectrum of
+/// the mix of all channels and, in [`Spectrum::channels`], one per channel plus
+/// the Mid and Side of the first two, each with its own FFT. Frames also
+/// carry the loudness of everything pushed up to their end and the pitch of
+/// the mix, along with its chroma and key, its onsets, beats and tempo, and
+/// its spectral [`Features`].
+///
+/// ```
+/// use selara::analyzer::SpectrumAnalyzer;
+/// use selara::types::AnalyzerConfig;
+///
+/// let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), 48_000.0)?;
+/// analyzer.push(&vec![0.0; 4096]);
+/// while let Some(spectrum) = analyzer.pop() {
+///     println!("{:.3}s: {} bands", spectrum.time, spectrum.bands.len());
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct SpectrumAnalyzer {
+    config: AnalyzerConfig,
+    sample_rate: f32,
+
+    // caches plans, so switching back to an FFT size is cheap
+    planner: RealFftPlanner<f32>,
+    r2c: Arc<dyn RealToComplex<f32>>,
+    input: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+    // power of each bin in the current frame
+    power: Vec<f32>,
+    // power gain of the weighting at each bin, empty when unweighted
+    bin_weights: Vec<f32>,
+    // power of each band in the current frame
+    band_power: Vec<f32>,
+    // smoothed chroma of the mix, relative to its strongest class
+    chroma: [f32; PITCH_CLASSES],
+    // long-term mean chroma behind the key estimate, and the frames in it
+    key_chroma: [f32; PITCH_CLASSES],
+    key_frames: u64,
+    // onset strength of the mix and the tempo tracked from it
+    flux: SpectralFlux,
+    tempo: TempoTracker,
+    // descriptors of the mix in the current frame
+    features: Features,
+    window: Vec<f32>,
+    window_sum: f32,
+    // equivalent noise bandwidth of the window, in bins
+    window_enbw: f32,
+
+    // analyzed range in Hz
+    f_lo: f32,
+    f_hi: f32,
+    frequencies: Arc<[f32]>,
+    nominal_frequencies: Arc<[f32]>,
+    edges: Arc<[f32]>,
+    band_bins: Vec<BandBins>,
+
+    channels: usize,
+    // analyzed signals, the mix first
+    signals: Vec<Channel>,
+    // smoothing buffers, one per signal
+    smooth: Vec<Smoothed>,
+    // weighting in front of each signal's meter, and the weighted samples
+    meter_weighting: Vec<WeightingFilter>,
+    meter_samples: Vec<f32>,
+
+    // rolling buffer of interleaved samples
+    ring: Vec<f32>,
+    // frames drained from the ring so far, for frame timestamps
+    consumed: u64,
+
+    loudness: LoudnessMeter,
+    pitch: PitchDetector,
+    chroma_analyzer: ChromaAnalyzer,
+    // samples at the start of the ring already measured for loudness, pitch
+    // and chroma
+    measured: usize,
+
+    ready: VecDeque<Spectrum>,
+}
+
+/// Averaged and smoothed band levels of one signal.
+#[derive(Clone)]
+struct Smoothed {
+    average: BandAverager,
+    db: Vec<f32>,
+    linear: Vec<f32>,
+}
+
+/// The FFT bins that make up one band.
+struct BandBins {
+    /// First bin overlapping the band.
+    first: usize,
+    /// Fraction of each overlapping bin's width that lies inside the band, from `first` on.
+    weights: Vec<f32>,
+    /// Bins whose center lies inside the band.
+    inner: Range<usize>,
+    /// Band center in bins, where bands too narrow to contain a bin center are
+    /// interpolated.
+    center: f32,
+}
+
+impl SpectrumAnalyzer {
+    /// An analyzer for a single channel.
+    pub fn new(
+        config: AnalyzerConfig,
+        sample_rate: f32,
+    ) -> Result<SpectrumAnalyzer, anyhow::Error> {
+        SpectrumAnalyzer::with_channels(config, sample_rate, 1)
+    }
+
+    /// An analyzer for `channels` interleaved channels.
+    pub fn with_channels(
+        config: AnalyzerConfig,
+        sample_rate: f32,
+        channels: usize,
+    ) -> Result<SpectrumAnalyzer, anyhow::Error> {
+        config.validate()?;
+        anyhow::ensure!(channels > 0, "at least one channel is required");
+        let mut signals = vec![Channel::Mix];
+        if channels > 1 {
+            signals.extend((0..channels).map(Channel::Input));
+            signals.extend([Channel::Mid, Channel::Side]);
+        }
+
+        let f_hi = (sample_rate / 2.0).min(config.max_freq);
+        let f_lo = config.min_freq.min(f_hi / 2.0);
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let r2c = planner.plan_fft_forward(config.fft_size);
+
+        let mut analyzer = SpectrumAnalyzer {
+            sample_rate,
+            planner,
+            r2c,
+            input: Vec::new(),
+            spectrum: Vec::new(),
+            scratch: Vec::new(),
+            power: Vec::new(),
+            bin_weights: Vec::new(),
+            band_power: Vec::new(),
+            chroma: [0.0; PITCH_CLASSES],
+            key_chroma: [0.0; PITCH_CLASSES],
+            key_frames: 0,
+            flux: SpectralFlux::default(),
+            tempo: TempoTracker::new(sample_rate / config.hop as f32),
+            features: Features::default(),
+            window: Vec::new(),
+            window_sum: 0.0,
+            window_enbw: 0.0,
+            f_lo,
+            f_hi,
+            frequencies: Arc::from([]),
+            nominal_frequencies: Arc::from([]),
+            edges: Arc::from([]),
+            band_bins: Vec::new(),
+            channels,
+            signals,
+            smooth: Vec::new(),
+            meter_weighting: Vec::new(),
+            meter_samples: Vec::new(),
+            ring: Vec::with_capacity(config.fft_size * 2 * channels),
+            consumed: 0,
+            loudness: LoudnessMeter::new(sample_rate, channels),
+            pitch: PitchDetector::new(sample_rate),
+            chroma_analyzer: ChromaAnalyzer::new(sample_rate, DEFAULT_A4_HZ),
+            measured: 0,
+            ready: VecDeque::new(),
+            config,
+        };
+        analyzer.layout_bands();
+        analyzer.plan();
+        analyzer.reset_meter_weighting();
+        Ok(analyzer)
+    }
+
+    pub fn config(&self) -> &AnalyzerConfig {
+        &self.config
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved input channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Center frequency of each band in Hz.
+    pub fn frequencies(&self) -> &[f32] {
+        &self.frequencies
+    }
+
+    /// Nominal center frequency of each band in Hz, for labels.
+    pub fn nominal_frequencies(&self) -> &[f32] {
+        &self.nominal_frequencies
+    }
+
+    /// Band edges in Hz; band `i` spans `edges()[i]..edges()[i + 1]`.
+    pub fn edges(&self) -> &[f32] {
+        &self.edges
+    }
+
+    /// Switches the window applied to subsequent frames.
+    pub fn set_window(&mut self, window: WindowFunction) {
+        self.config.window = window;
+        self.window = window.coefficients(self.config.fft_size, self.config.kaiser_beta);
+        self.window_sum = self.window.iter().sum();
+        self.window_enbw = enbw(&self.window);
+    }
+
+    /// Changes the FFT size and hop for subsequent frames.
+    ///
+    /// Buffered samples and band smoothing carry over, so the output continues
+    /// without a gap; a larger FFT starts producing frames once enough samples
+    /// have arrived.
+    pub fn set_resolution(&mut self, fft_size: usize, hop: usize) -> Result<(), anyhow::Error> {
+        let config = AnalyzerConfig {
+            fft_size,
+            hop,
+            ..self.config.clone()
+        };
+        config.validate()?;
+        self.config = config;
+        self.plan();
+        Ok(())
+    }
+
+    /// Switches to another band scale. Smoothing restarts since the bands change.
+    pub fn set_scale(&mut self, scale: BandScale) {
+        self.config.scale = scale;
+        self.layout_bands();
+        self.map_bins();
+    }
+
+    /// Switches the frequency weighting of subsequent frames.
+    pub fn set_weighting(&mut self, weighting: Weighting) {
+        self.config.weighting = weighting;
+        self.weigh_bins();
+        self.reset_meter_weighting();
+    }
+
+    /// Switches the averaging of band powers, starting the averages over.
+    pub fn set_averaging(&mut self, averaging: Averaging) {
+        self.config.averaging = averaging;
+        self.reset_average();
+    }
+
+    /// Starts the band power averages over, e.g. for a new infinite average.
+    pub fn reset_average(&mut self) {
+        for smoothed in &mut self.smooth {
+            smoothed.average.reset();
+        }
+    }
+
+    /// Frames in the current average of the mix.
+    pub fn averaged_frames(&self) -> u64 {
+        self.smooth[0].average.frames()
+    }
+
+    /// Restarts the integrated loudness and loudness range.
+    pub fn reset_loudness(&mut self) {
+        self.loudness.reset();
+    }
+
+    /// Continues from `frame` frames into the source after a jump, such as a
+    /// seek in a file: the partial window is dropped, frames are timed from
+    /// there, and the loudness, onsets and tempo start over.
+    pub fn seek(&mut self, frame: u64) {
+        self.ring.clear();
+        self.measured = 0;
+        self.consumed = frame;
+        self.loudness.reset();
+        self.flux = SpectralFlux::default();
+        self.tempo = TempoTracker::new(self.sample_rate / self.config.hop as f32);
+        self.reset_meter_weighting();
+    }
+
+    /// Rebuilds the bands for the configured scale.
+    fn layout_bands(&mut self) {
+        let layout = BandLayout::new(self.config.scale, self.config.bands, self.f_lo, self.f_hi);
+        self.frequencies = layout.centers.into();
+        self.nominal_frequencies = layout.nominal.into();
+        self.edges = layout.edges.into();
+        let bands = self.frequencies.len();
+        let empty = Smoothed {
+            average: BandAverager::new(bands),
+            db: vec![MIN_DBFS; bands],
+            linear: vec![0.0; bands],
+        };
+        self.smooth = vec![empty; self.signals.len()];
+    }
+
+    /// Rebuilds everything that depends on the FFT size.
+    fn plan(&mut self) {
+        let fft_size = self.config.fft_size;
+
+        self.r2c = self.planner.plan_fft_forward(fft_size);
+        self.input = self.r2c.make_input_vec();
+        self.spectrum = self.r2c.make_output_vec();
+        self.scratch = self.r2c.make_scratch_vec();
+        self.power = vec![0.0; self.spectrum.len()];
+        // Onsets and tempo are counted in frames, so they start over
+        self.flux = SpectralFlux::default();
+        self.tempo = TempoTracker::new(self.sample_rate / self.config.hop as f32);
+        self.set_window(self.config.window);
+        self.map_bins();
+        self.weigh_bins();
+    }
+
+    /// Works out the weighting's power gain at every bin.
+    fn weigh_bins(&mut self) {
+        self.bin_weights.clear();
+        if self.config.weighting == Weighting::Z {
+            return;
+        }
+        let bin_hz = self.sample_rate / self.config.fft_size as f32;
+        self.bin_weights.extend(
+            (0..self.power.len())
+                .map(|k| 10f32.powf(self.config.weighting.gain_db(k as f32 * bin_hz) / 10.0)),
+        );
+    }
+
+    /// Starts a filter for the configured weighting in front of every meter.
+    fn reset_meter_weighting(&mut self) {
+        self.meter_weighting = self
+            .signals
+            .iter()
+            .map(|_| WeightingFilter::new(self.config.weighting, self.sample_rate))
+            .collect();
+    }
+
+    /// Works out which bins, and how much of each, fall into every band.
+    fn map_bins(&mut self) {
+        let num_bins = self.spectrum.len(); // == fft_size/2 + 1
+        let bin_hz = self.sample_rate / self.config.fft_size as f32;
+
+        self.band_bins = self
+            .edges
+            .windows(2)
+            .zip(self.frequencies.iter())
+            .map(|(edge, &center)| {
+                // Bin k covers (k - 0.5)..(k + 0.5) bins
+                let (lo, hi) = (edge[0] / bin_hz, edge[1] / bin_hz);
+                let last = num_bins - 1;
+                let first = ((lo + 0.5).floor() as usize).min(last);
+                let end = ((hi + 0.5).ceil() as usize).min(num_bins);
+                let weights = (first..end)
+                    .map(|k| {
+                        let k = k as f32;
+                        ((k + 0.5).min(hi) - (k - 0.5).max(lo)).clamp(0.0, 1.0)
+                    })
+                    .collect();
+                let inner_first = (lo.ceil() as usize).min(num_bins);
+                let inner_end = (hi.ceil() as usize).clamp(inner_first, num_bins);
+                BandBins {
+                    first,
+                    weights,
+                    inner: inner_first..inner_end,
+                    center: (center / bin_hz).min(last as f32),
+                }
+            })
+            .collect();
+    }
+
+    /// Appends samples, interleaved by channel in whole frames, and analyzes
+    /// every FFT frame they complete.
+    pub fn push(&mut self, samples: &[f32]) {
+        self.ring.extend_from_slice(samples);
+
+        // process as long as we have one full FFT frame
+        while self.ring.len() >= self.config.fft_size * self.channels {
+            let spectrum = self.process_frame();
+            self.ready.push_back(spectrum);
+
+            // advance by hop
+            self.ring.drain(0..self.config.hop * self.channels);
+            self.measured = self
+                .measured
+                .saturating_sub(self.config.hop * self.channels);
+            self.consumed += self.config.hop as u64;
+        }
+    }
+
+    /// Takes the oldest analyzed frame, if any.
+    pub fn pop(&mut self) -> Option<Spectrum> {
+        self.ready.pop_front()
+    }
+
+    fn process_frame(&mut self) -> Spectrum {
+        let fft_size = self.config.fft_size;
+        let hop = self.config.hop;
+        let channels = self.channels;
+
+        // Loudness, pitch and chroma run up to the end of the window, each sample
+        // once, even after a smaller FFT size leaves some measured beyond it
+        let end = fft_size * channels;
+        if end > self.measured {
+            let fresh = &self.ring[self.measured..end];
+            self.loudness.push(fresh);
+            let mix = fresh
+                .chunks_exact(channels)
+                .map(|frame| signal_sample(Channel::Mix, frame));
+            self.pitch.push(mix.clone());
+            self.chroma_analyzer.push(mix);
+            self.measured = end;
+        }
+        self.update_chroma();
+
+        let mut meters = Vec::with_capacity(self.signals.len());
+        for index in 0..self.signals.len() {
+            let signal = self.signals[index];
+            let frames = self.ring.chunks_exact(channels);
+
+            // copy + window (no alloc inside the loop)
+            for ((x, frame), w) in self.input.iter_mut().zip(frames).zip(&self.window) {
+                *x = signal_sample(signal, frame) * w;
+            }
+            self.analyze(index);
+
+            // weighted level of the samples that entered the window since the
+            // last frame
+            let fresh = self.ring[(fft_size - hop) * channels..fft_size * channels]
+                .chunks_exact(channels)
+                .map(|frame| signal_sample(signal, frame));
+            self.meter_samples.clear();
+            self.meter_samples.extend(fresh);
+            self.meter_weighting[index].process(&mut self.meter_samples);
+            let (mut peak, mut sum_sq) = (0.0f32, 0.0f32);
+            for &s in &self.meter_samples {
+                peak = peak.max(s.abs());
+                sum_sq += s * s;
+            }
+            let rms = (sum_sq / hop as f32).sqrt();
+            meters.push(Meter { rms, peak });
+        }
+
+        self.features.measure_samples(
+            self.ring[..fft_size * channels]
+                .chunks_exact(channels)
+                .map(|frame| signal_sample(Channel::Mix, frame)),
+        );
+
+        Spectrum {
+            time: (self.consumed + fft_size as u64) as f64 / self.sample_rate as f64,
+            frequencies: self.frequencies.clone(),
+            nominal_frequencies: self.nominal_frequencies.clone(),
+            edges: self.edges.clone(),
+            bands: self.smooth[0].db.clone(),
+            bands_linear: self.smooth[0].linear.clone(),
+            meter: meters[0],
+            loudness: self.loudness.loudness(),
+            pitch: self.pitch.detect(),
+            chroma: self.chroma,
+            key: estimate_key(&self.key_chroma),
+            onset: self.tempo.onset(),
+            beat: self.tempo.beat(),
+            tempo: self.tempo.tempo(),
+            features: self.features,
+            channels: self.signals[1..]
+                .iter()
+                .zip(&self.smooth[1..])
+                .zip(&meters[1..])
+                .map(|((&channel, smoothed), &meter)| ChannelSpectrum {
+                    channel,
+                    bands: smoothed.db.clone(),
+                    bands_linear: smoothed.linear.clone(),
+                    meter,
+                })
+                .collect(),
+        }
+    }
+
+    /// Transforms the windowed input and folds it into the smoothed bands of
+    /// signal `index`.
+    fn analyze(&mut self, index: usize) {
+        // FFT
+        self.r2c
+            .process_with_scratch(&mut self.input, &mut self.spectrum, &mut self.scratch)
+            .expect("FFT failed");
+
+        for (p, c) in self.power.iter_mut().zip(&self.spectrum) {
+            *p = c.re * c.re + c.im * c.im;
+        }
+        if index == 0 {
+            // Onsets are found before weighting, which would hide low hits
+            let scale = 2.0 / self.window_sum;
+            let flux = self.flux.push(self.power.iter().map(|p| p.sqrt() * scale));
+            self.tempo.push(flux);
+            let bin_hz = self.sample_rate / self.config.fft_size as f32;
+            self.features.flux = flux;
+            self.features.measure_spectrum(&self.power, bin_hz);
+        }
+        for (p, w) in self.power.iter_mut().zip(&self.bin_weights) {
+            *p *= w;
+        }
+
+        // Scaling by the window sum (N × coherent gain) makes a bin-centered
+        // full-scale sine read 1.0 whichever window is used. In bands wider than
+        // the window's main lobe, the summed power divided by the ENBW recovers
+        // the sine's level wherever it falls between bins; narrow bands fall back
+        // to their strongest bin, and bands narrower than a bin to the power
+        // interpolated at their center.
+        let amplitude_scale = 2.0 / self.window_sum;
+        self.band_power.clear();
+        for band in &self.band_bins {
+            let sum: f32 = band
+                .weights
+                .iter()
+                .zip(&self.power[band.first..])
+                .map(|(w, p)| w * p)
+                .sum();
+            let peak = if band.inner.is_empty() {
+                let k = band.center.floor() as usize;
+                let frac = band.center - k as f32;
+                let next = (k + 1).min(self.power.len() - 1);
+                self.power[k] + frac * (self.power[next] - self.power[k])
+            } else {
+                self.power[band.inner.clone()]
+                    .iter()
+                    .copied()
+                    .fold(0.0, f32::max)
+            };
+            self.band_power.push((sum / self.window_enbw).max(peak));
+        }
+
+        let smooth = &mut self.smooth[index];
+        smooth.average.push(
+            self.config.averaging,
+            self.config.average_frames,
+            &mut self.band_power,
+        );
+
+        // Attack and release act per frame, so the coefficients follow the hop
+        let frame_secs = self.config.hop as f32 / self.sample_rate;
+        let attack = smoothing_coefficient(self.config.attack_ms, frame_secs);
+        let release = smoothing_coefficient(self.config.release_ms, frame_secs);
+        for (b, &power) in self.band_power.iter().enumerate() {
+            let amplitude = power.sqrt() * amplitude_scale;
+
+            // Linear magnitude for linear mode
+            let linear_level = amplitude.clamp(0.0, 1.0);
+
+            // Convert to dBFS
+            let db = if amplitude > 0.0 {
+                (20.0 * amplitude.log10()).max(MIN_DBFS)
+            } else {
+                MIN_DBFS
+            };
+
+            let alpha = if db > smooth.db[b] { attack } else { release };
+            smooth.db[b] += alpha * (db - smooth.db[b]);
+            let alpha = if linear_level > smooth.linear[b] {
+                attack
+            } else {
+                release
+            };
+            smooth.linear[b] += alpha * (linear_level - smooth.linear[b]);
+        }
+    }
+
+    /// Folds the chroma of the latest mix samples into the displayed chroma
+    /// and the key's long-term chroma.
+    fn update_chroma(&mut self) {
+        let Some(mut frame) = self.chroma_analyzer.chroma() else {
+            return;
+        };
+        let strongest = frame.iter().copied().fold(0.0, f32::max);
+        if strongest < CHROMA_FLOOR {
+            frame = [0.0; PITCH_CLASSES];
+        } else {
+            for class in &mut frame {
+                *class /= strongest;
+            }
+            // Equal weights while the mean settles, then an exponential mean
+            self.key_frames += 1;
+            let frame_secs = self.config.hop as f32 / self.sample_rate;
+            let weight = smoothing_coefficient(KEY_SECS * 1000.0, frame_secs)
+                .max(1.0 / self.key_frames as f32);
+            for (mean, &class) in self.key_chroma.iter_mut().zip(&frame) {
+                *mean += weight * (class - *mean);
+            }
+        }
+
+        let frame_secs = self.config.hop as f32 / self.sample_rate;
+        let attack = smoothing_coefficient(self.config.attack_ms, frame_secs);
+        let release = smoothing_coefficient(self.config.release_ms, frame_secs);
+        for (smoothed, &class) in self.chroma.iter_mut().zip(&frame) {
+            let alpha = if class > *smoothed { attack } else { release };
+            *smoothed += alpha * (class - *smoothed);
+        }
+    }
+}
+
+/// Power of the strongest pitch class, relative to a full-scale sine, below
+/// which a frame counts as silent: -80 dBFS.
+const CHROMA_FLOOR: f32 = 1e-8;
+/// Time constant of the chroma behind the key estimate, in seconds.
+const KEY_SECS: f32 = 8.0;
+
+/// Weight of a new frame in an exponential smoother with a time constant of
+/// `ms`, for frames `frame_secs` apart.
+fn smoothing_coefficient(ms: f32, frame_secs: f32) -> f32 {
+    if ms <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-frame_secs * 1000.0 / ms).exp()
+    }
+}
+
+/// The sample of `signal` in one interleaved frame.
+fn signal_sample(signal: Channel, frame: &[f32]) -> f32 {
+    match signal {
+        Channel::Mix => frame.iter().sum::<f32>() / frame.len() as f32,
+        Channel::Input(i) => frame[i],
+        Channel::Mid => 0.5 * (frame[0] + frame[1]),
+        Channel::Side => 0.5 * (frame[0] - frame[1]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MAX_FFT_SIZE, MIN_FFT_SIZE};
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const AMPLITUDE: f32 = 0.01;
+
+    fn sine(freq: f32, amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    fn peak_band(spectrum: &Spectrum) -> usize {
+        spectrum
+            .bands
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap()
+    }
+
+    /// Band whose center is closest to `freq` on the log axis.
+    fn nearest_band(frequencies: &[f32], freq: f32) -> usize {
+        frequencies
+            .iter()
+            .enumerate()
+            .min_by(|a, b| (a.1 / freq).ln().abs().total_cmp(&(b.1 / freq).ln().abs()))
+            .map(|(i, _)| i)
+            .unwrap()
+    }
+
+    fn last_frame(analyzer: &mut SpectrumAnalyzer) -> Spectrum {
+        let mut last = None;
+        while let Some(spectrum) = analyzer.pop() {
+            last = Some(spectrum);
+        }
+        last.expect("no frames analyzed")
+    }
+
+    #[test]
+    fn sine_peaks_in_nearest_band() {
+        for freq in [187.5, 1_000.0, 3_000.0, 12_000.0] {
+            let mut analyzer =
+                SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+            analyzer.push(&sine(freq, AMPLITUDE, 16_384));
+            let spectrum = last_frame(&mut analyzer);
+            assert_eq!(
+                peak_band(&spectrum),
+                nearest_band(analyzer.frequencies(), freq),
+                "{} Hz",
+                freq
+            );
+        }
+    }
+
+    #[test]
+    fn higher_sine_peaks_in_higher_band() {
+        let mut low = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        let mut high = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        low.push(&sine(440.0, AMPLITUDE, 8_192));
+        high.push(&sine(880.0, AMPLITUDE, 8_192));
+        assert!(peak_band(&last_frame(&mut high)) > peak_band(&last_frame(&mut low)));
+    }
+
+    #[test]
+    fn silence_has_no_level() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&vec![0.0; 4_096]);
+        let spectrum = last_frame(&mut analyzer);
+        assert!(spectrum.bands.iter().all(|&b| b == MIN_DBFS));
+        assert!(spectrum.bands_linear.iter().all(|&b| b == 0.0));
+        assert_eq!(spectrum.meter.rms, 0.0);
+        assert_eq!(spectrum.meter.peak, 0.0);
+    }
+
+    #[test]
+    fn chunking_does_not_change_frames() {
+        let config = AnalyzerConfig {
+            fft_size: 2048,
+            hop: 512,
+            ..AnalyzerConfig::default()
+        };
+        let signal = sine(1_000.0, 0.25, 10_000);
+
+        let mut whole = SpectrumAnalyzer::new(config.clone(), SAMPLE_RATE).unwrap();
+        whole.push(&signal);
+        let mut chunked = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        for chunk in signal.chunks(333) {
+            chunked.push(chunk);
+        }
+
+        // (10000 - 2048) / 512 + 1 full windows
+        let mut count = 0;
+        while let (Some(a), Some(b)) = (whole.pop(), chunked.pop()) {
+            assert_eq!(a.bands, b.bands);
+            assert_eq!(a.time, b.time);
+            count += 1;
+        }
+        assert_eq!(count, 16);
+        assert!(whole.pop().is_none() && chunked.pop().is_none());
+    }
+
+    #[test]
+    fn frame_time_is_end_of_window() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&vec![0.0; 1_024 + 512]);
+        assert_eq!(analyzer.pop().unwrap().time, 1_024.0 / SAMPLE_RATE as f64);
+        assert_eq!(analyzer.pop().unwrap().time, 1_536.0 / SAMPLE_RATE as f64);
+        assert!(analyzer.pop().is_none());
+    }
+
+    #[test]
+    fn sine_level_is_independent_of_window() {
+        // 4 kHz falls between bins, so scalloping would show without the correction
+        for window in WindowFunction::ALL {
+            let config = AnalyzerConfig {
+                fft_size: 8192,
+                hop: 8192,
+                attack_ms: 0.0,
+                release_ms: 0.0,
+                window,
+                ..AnalyzerConfig::default()
+            };
+            let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+            analyzer.push(&sine(4_000.0, 0.5, 8192));
+            let spectrum = last_frame(&mut analyzer);
+            let level = spectrum.bands_linear.iter().fold(0.0f32, |a, &b| a.max(b));
+            let db = 20.0 * (level / 0.5).log10();
+            assert!(db.abs() < 0.2, "{}: {:.2} dB off", window, db);
+        }
+    }
+
+    #[test]
+    fn full_scale_sine_reads_0_dbfs() {
+        let config = AnalyzerConfig {
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            window: WindowFunction::FlatTop,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        analyzer.push(&sine(1_000.0, 1.0, 4096));
+        let spectrum = last_frame(&mut analyzer);
+        let top = spectrum.bands.iter().fold(MIN_DBFS, |a, &b| a.max(b));
+        assert!(top.abs() < 0.05, "{} dBFS", top);
+    }
+
+    #[test]
+    fn level_is_independent_of_fft_size() {
+        for fft_size in [256, 1024, 4096, 16384] {
+            let config = AnalyzerConfig {
+                fft_size,
+                hop: fft_size,
+                attack_ms: 0.0,
+                release_ms: 0.0,
+                ..AnalyzerConfig::default()
+            };
+            let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+            analyzer.push(&sine(6_000.0, 0.1, fft_size));
+            let spectrum = last_frame(&mut analyzer);
+            let top = spectrum.bands.iter().fold(MIN_DBFS, |a, &b| a.max(b));
+            assert!((top + 20.0).abs() < 0.1, "{}: {} dBFS", fft_size, top);
+        }
+    }
+
+    #[test]
+    fn set_window_takes_effect() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.set_window(WindowFunction::Kaiser);
+        assert_eq!(analyzer.config().window, WindowFunction::Kaiser);
+        analyzer.push(&sine(1_000.0, AMPLITUDE, 4096));
+        assert_eq!(
+            peak_band(&last_frame(&mut analyzer)),
+            nearest_band(analyzer.frequencies(), 1_000.0)
+        );
+    }
+
+    #[test]
+    fn set_resolution_keeps_the_stream_going() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        let signal = sine(100.0, AMPLITUDE, 48_000);
+        analyzer.push(&signal[..4096]);
+        let before = last_frame(&mut analyzer);
+
+        analyzer.set_resolution(8192, 2048).unwrap();
+        assert_eq!(analyzer.config().fft_size, 8192);
+        // The ring still holds the tail of the last window, so the new size
+        // completes with fewer than 8192 fresh samples
+        analyzer.push(&signal[4096..4096 + 7680]);
+        let after = analyzer.pop().expect("frame after resize");
+        assert_eq!(
+            after.time,
+            (before.time * SAMPLE_RATE as f64 + 7680.0) / SAMPLE_RATE as f64
+        );
+
+        analyzer.push(&signal[4096 + 7680..]);
+        let spectrum = last_frame(&mut analyzer);
+        assert_eq!(
+            peak_band(&spectrum),
+            nearest_band(analyzer.frequencies(), 100.0)
+        );
+    }
+
+    #[test]
+    fn set_resolution_rejects_invalid_sizes() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        assert!(analyzer.set_resolution(3000, 1500).is_err());
+        assert!(analyzer.set_resolution(1024, 2048).is_err());
+        assert_eq!(analyzer.config().fft_size, 1024);
+        assert_eq!(analyzer.config().hop, 512);
+    }
+
+    /// Deterministic white noise, uniform in -1..1.
+    fn noise(len: usize) -> Vec<f32> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state as f32 / u32::MAX as f32 * 2.0 - 1.0
+            })
+            .collect()
+    }
+
+    fn band_at(analyzer: &SpectrumAnalyzer, nominal: f32) -> usize {
+        analyzer
+            .nominal_frequencies()
+            .iter()
+            .position(|&f| f == nominal)
+            .unwrap()
+    }
+
+    #[test]
+    fn third_octave_sine_reads_its_level() {
+        let config = AnalyzerConfig {
+            fft_size: 4096,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            scale: BandScale::ThirdOctave,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        analyzer.push(&sine(1_000.0, 0.5, 8192));
+        let spectrum = last_frame(&mut analyzer);
+        let band = band_at(&analyzer, 1000.0);
+        assert_eq!(peak_band(&spectrum), band);
+        assert!(
+            (spectrum.bands[band] + 6.02).abs() < 0.2,
+            "{}",
+            spectrum.bands[band]
+        );
+    }
+
+    #[test]
+    fn weighting_applies_to_the_bands() {
+        // Fine bins, since the weighting is steep at 100 Hz
+        let config = AnalyzerConfig {
+            fft_size: 16384,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            scale: BandScale::ThirdOctave,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        for (weighting, expected) in [(Weighting::A, -19.1), (Weighting::C, -0.3)] {
+            analyzer.set_weighting(weighting);
+            analyzer.push(&sine(100.0, 1.0, 16384));
+            let spectrum = last_frame(&mut analyzer);
+            let level = spectrum.bands[band_at(&analyzer, 100.0)];
+            assert!((level - expected).abs() < 0.2, "{}: {}", weighting, level);
+        }
+    }
+
+    #[test]
+    fn weighting_applies_to_the_meter() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        let signal = sine(100.0, 1.0, 2 * 48_000);
+        analyzer.push(&signal);
+        let flat = last_frame(&mut analyzer).meter;
+
+        // The filter settles within the first second
+        analyzer.set_weighting(Weighting::A);
+        analyzer.push(&signal);
+        let weighted = last_frame(&mut analyzer).meter;
+        let gain = 20.0 * (weighted.rms / flat.rms).log10();
+        assert!((gain + 19.1).abs() < 0.2, "{gain}");
+        assert!((20.0 * weighted.rms.log10() + 19.1 + 3.01).abs() < 0.3);
+    }
+
+    #[test]
+    fn noise_rises_1_db_per_third_octave() {
+        let config = AnalyzerConfig {
+            fft_size: 4096,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            averaging: Averaging::Infinite,
+            scale: BandScale::ThirdOctave,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        analyzer.push(&noise(480_000));
+        let spectrum = last_frame(&mut analyzer);
+        // White noise power grows with bandwidth: nine thirds is 9 dB
+        let rise =
+            spectrum.bands[band_at(&analyzer, 8000.0)] - spectrum.bands[band_at(&analyzer, 1000.0)];
+        assert!((rise - 9.0).abs() < 0.5, "{} dB", rise);
+    }
+
+    #[test]
+    fn bands_narrower_than_a_bin_are_not_empty() {
+        let config = AnalyzerConfig {
+            fft_size: 1024,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            scale: BandScale::TwentyFourthOctave,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        analyzer.push(&noise(4096));
+        let spectrum = last_frame(&mut analyzer);
+        assert!(spectrum.bands.iter().all(|&b| b > MIN_DBFS + 40.0));
+    }
+
+    #[test]
+    fn set_scale_changes_bands() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.set_scale(BandScale::Octave);
+        analyzer.push(&sine(250.0, AMPLITUDE, 4096));
+        let spectrum = last_frame(&mut analyzer);
+        assert_eq!(spectrum.bands.len(), 10);
+        assert_eq!(spectrum.nominal_frequencies[peak_band(&spectrum)], 250.0);
+    }
+
+    #[test]
+    fn perceptual_scales_put_a_sine_in_the_band_around_it() {
+        for scale in [BandScale::Mel, BandScale::Bark, BandScale::Erb] {
+            let config = AnalyzerConfig {
+                fft_size: 4096,
+                bands: 40,
+                scale,
+                ..AnalyzerConfig::default()
+            };
+            let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+            analyzer.push(&sine(3_000.0, AMPLITUDE, 8192));
+            let spectrum = last_frame(&mut analyzer);
+            let band = peak_band(&spectrum);
+            assert_eq!(spectrum.edges.len(), 41);
+            assert!(
+                spectrum.edges[band] <= 3_000.0 && 3_000.0 < spectrum.edges[band + 1],
+                "{}",
+                scale
+            );
+        }
+    }
+
+    fn interleave(left: &[f32], right: &[f32]) -> Vec<f32> {
+        left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect()
+    }
+
+    #[test]
+    fn mono_input_has_no_channel_spectra() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&sine(1_000.0, AMPLITUDE, 4096));
+        assert!(last_frame(&mut analyzer).channels.is_empty());
+    }
+
+    #[test]
+    fn stereo_channels_are_analyzed_separately() {
+        let config = AnalyzerConfig {
+            fft_size: 4096,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::with_channels(config, SAMPLE_RATE, 2).unwrap();
+        let left = sine(1_000.0, 0.5, 8192);
+        let right = sine(4_000.0, 0.5, 8192);
+        analyzer.push(&interleave(&left, &right));
+        let spectrum = last_frame(&mut analyzer);
+
+        let channels: Vec<Channel> = spectrum.channels.iter().map(|c| c.channel).collect();
+        assert_eq!(
+            channels,
+            [
+                Channel::Input(0),
+                Channel::Input(1),
+                Channel::Mid,
+                Channel::Side
+            ]
+        );
+        let level = |bands: &[f32], freq: f32| bands[nearest_band(&spectrum.frequencies, freq)];
+        let (l, r) = (&spectrum.channels[0].bands, &spectrum.channels[1].bands);
+        // Each channel holds its own full-level sine and none of the other
+        assert!((level(l, 1_000.0) + 6.02).abs() < 0.5);
+        assert!(level(l, 4_000.0) < -60.0);
+        assert!((level(r, 4_000.0) + 6.02).abs() < 0.5);
+        assert!(level(r, 1_000.0) < -60.0);
+        // The mix averages them, halving each
+        assert!((level(&spectrum.bands, 1_000.0) + 12.04).abs() < 0.5);
+        assert!((spectrum.channels[0].meter.peak - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn identical_channels_have_no_side() {
+        let mut analyzer =
+            SpectrumAnalyzer::with_channels(AnalyzerConfig::default(), SAMPLE_RATE, 2).unwrap();
+        let signal = sine(1_000.0, AMPLITUDE, 4096);
+        analyzer.push(&interleave(&signal, &signal));
+        let spectrum = last_frame(&mut analyzer);
+        let (mid, side) = (&spectrum.channels[2], &spectrum.channels[3]);
+        assert_eq!(mid.bands, spectrum.bands);
+        assert!(side.bands.iter().all(|&b| b == MIN_DBFS));
+        assert_eq!(side.meter.peak, 0.0);
+    }
+
+    #[test]
+    fn loudness_measures_each_sample_once() {
+        let left = sine(1_000.0, 0.1, 48_000);
+        let stereo = interleave(&left, &left);
+        let mut analyzer =
+            SpectrumAnalyzer::with_channels(AnalyzerConfig::default(), SAMPLE_RATE, 2).unwrap();
+        let (first, rest) = stereo.split_at(stereo.len() / 2);
+        analyzer.push(first);
+        // a smaller FFT leaves samples measured past the end of the next window
+        analyzer.set_resolution(256, 128).unwrap();
+        analyzer.push(rest);
+
+        let spectrum = last_frame(&mut analyzer);
+        let frames = (spectrum.time * SAMPLE_RATE as f64).round() as usize;
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        meter.push(&stereo[..frames * 2]);
+        assert_eq!(spectrum.loudness, meter.loudness());
+        assert!(spectrum.loudness.integrated.is_some());
+    }
+
+    #[test]
+    fn frames_carry_the_pitch_of_the_mix() {
+        let tone = sine(220.0, 0.5, 16_384);
+        let stereo = interleave(&tone, &tone);
+        let mut analyzer =
+            SpectrumAnalyzer::with_channels(AnalyzerConfig::default(), SAMPLE_RATE, 2).unwrap();
+        analyzer.push(&stereo[..2048]);
+        assert_eq!(last_frame(&mut analyzer).pitch, None);
+        analyzer.push(&stereo[2048..]);
+        let pitch = last_frame(&mut analyzer).pitch.expect("no pitch");
+        assert!((pitch.frequency - 220.0).abs() < 0.2, "{}", pitch.frequency);
+    }
+
+    #[test]
+    fn chords_give_their_chroma_and_key() {
+        // A minor triad, A3 C4 E4, with C the loudest
+        let chord: Vec<f32> = sine(220.0, 0.2, 48_000)
+            .iter()
+            .zip(sine(261.63, 0.3, 48_000))
+            .zip(sine(329.63, 0.2, 48_000))
+            .map(|((a, c), e)| a + c + e)
+            .collect();
+        // The chroma has a window of its own, finer than the FFT's
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&chord);
+        let spectrum = last_frame(&mut analyzer);
+        assert!((spectrum.chroma[0] - 1.0).abs() < 1e-3);
+        for class in [4, 9] {
+            assert!(spectrum.chroma[class] > 0.3, "{:?}", spectrum.chroma);
+        }
+        for class in [1, 3, 6, 8, 10] {
+            assert!(spectrum.chroma[class] < 0.1, "{:?}", spectrum.chroma);
+        }
+        assert_eq!(spectrum.key.unwrap().to_string(), "A minor");
+
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&vec![0.0; 16_384]);
+        let spectrum = last_frame(&mut analyzer);
+        assert_eq!((spectrum.chroma, spectrum.key), ([0.0; 12], None));
+    }
+
+    #[test]
+    fn click_tracks_give_onsets_beats_and_tempo() {
+        // A decaying 1 kHz click every half second, 120 BPM
+        let clicks: Vec<f32> = (0..8 * 48_000)
+            .map(|i| {
+                let t = (i % 24_000) as f32 / SAMPLE_RATE;
+                0.5 * (-t * 60.0).exp() * (std::f32::consts::TAU * 1_000.0 * t).sin()
+            })
+            .collect();
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&clicks);
+        let frames: Vec<Spectrum> = std::iter::from_fn(|| analyzer.pop()).collect();
+
+        let onsets: Vec<f64> = frames.iter().filter(|s| s.onset).map(|s| s.time).collect();
+        // All but the click in the first frame, which has nothing to rise from
+        assert_eq!(onsets.len(), 15, "{:?}", onsets);
+        for (i, time) in onsets.iter().enumerate() {
+            // The frame whose window first takes in the click
+            assert!((time - 0.5 * (i + 1) as f64).abs() < 0.02, "{:?}", onsets);
+        }
+        let beats = frames.iter().filter(|s| s.beat).count();
+        assert!((6..=9).contains(&beats), "{} beats", beats);
+        let tempo = frames.last().unwrap().tempo.expect("no tempo");
+        assert!((tempo.bpm - 120.0).abs() < 1.0, "{} BPM", tempo.bpm);
+    }
+
+    #[test]
+    fn frames_carry_the_features_of_the_mix() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&sine(1_000.0, 0.5, 8192));
+        let tone = last_frame(&mut analyzer).features;
+        assert!((tone.centroid - 1_000.0).abs() < 10.0, "{:?}", tone);
+        assert!(tone.bandwidth < 100.0, "{:?}", tone);
+        assert!(tone.flatness < 0.01, "{:?}", tone);
+        assert!((tone.zero_crossing_rate - 2_000.0 / SAMPLE_RATE).abs() < 1e-3);
+        assert!((tone.crest_factor - 3.01).abs() < 0.05, "{:?}", tone);
+
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&noise(8192));
+        let noise = last_frame(&mut analyzer).features;
+        // White noise spreads evenly up to Nyquist
+        assert!(
+            (noise.centroid - SAMPLE_RATE / 4.0).abs() < 1_000.0,
+            "{:?}",
+            noise
+        );
+        assert!(noise.flatness > 0.4, "{:?}", noise);
+        assert!(noise.zero_crossing_rate > 0.4, "{:?}", noise);
+    }
+
+    #[test]
+    fn seek_times_frames_from_the_new_position() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        analyzer.push(&sine(1_000.0, 0.5, 3 * 48_000));
+        assert!(last_frame(&mut analyzer).loudness.integrated.is_some());
+
+        // The partial window left before the seek is dropped
+        analyzer.seek(10 * 48_000);
+        analyzer.push(&sine(1_000.0, 0.5, 1024));
+        let spectrum = last_frame(&mut analyzer);
+        assert!((spectrum.time - (10.0 + 1024.0 / SAMPLE_RATE as f64)).abs() < 1e-9);
+        assert_eq!(spectrum.loudness, crate::loudness::Loudness::default());
+    }
+
+    #[test]
+    fn attack_and_release_follow_time_not_frames() {
+        // One time constant decays to 1/e however the time is divided into frames
+        for (frames, frame_secs) in [(5, 0.02), (20, 0.005), (100, 0.001)] {
+            let remaining = (1.0 - smoothing_coefficient(100.0, frame_secs)).powi(frames);
+            assert!((remaining - (-1.0f32).exp()).abs() < 1e-4);
+        }
+        assert_eq!(smoothing_coefficient(0.0, 0.01), 1.0);
+    }
+
+    #[test]
+    fn averaging_steadies_noise() {
+        let spread = |averaging| {
+            let config = AnalyzerConfig {
+                attack_ms: 0.0,
+                release_ms: 0.0,
+                averaging,
+                average_frames: 32,
+                scale: BandScale::ThirdOctave,
+                ..AnalyzerConfig::default()
+            };
+            let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+            analyzer.push(&noise(96_000));
+            let band = band_at(&analyzer, 1000.0);
+            let levels: Vec<f32> = std::iter::from_fn(|| analyzer.pop())
+                .skip(64)
+                .map(|spectrum| spectrum.bands[band])
+                .collect();
+            let mean = levels.iter().sum::<f32>() / levels.len() as f32;
+            levels.iter().map(|l| (l - mean).abs()).fold(0.0, f32::max)
+        };
+        let raw = spread(Averaging::Off);
+        for averaging in [
+            Averaging::Linear,
+            Averaging::Exponential,
+            Averaging::Infinite,
+        ] {
+            let averaged = spread(averaging);
+            assert!(
+                averaged < raw / 3.0,
+                "{}: {} vs {} dB",
+                averaging,
+                averaged,
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn reset_average_starts_over() {
+        let config = AnalyzerConfig {
+            averaging: Averaging::Infinite,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new(config, SAMPLE_RATE).unwrap();
+        analyzer.push(&noise(8192));
+        assert_eq!(analyzer.averaged_frames(), 15);
+        analyzer.reset_average();
+        assert_eq!(analyzer.averaged_frames(), 0);
+        analyzer.set_averaging(Averaging::Linear);
+        analyzer.push(&noise(1024));
+        assert_eq!(analyzer.averaged_frames(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let config = AnalyzerConfig {
+            fft_size: 1000,
+            ..AnalyzerConfig::default()
+        };
+        assert!(SpectrumAnalyzer::new(config, SAMPLE_RATE).is_err());
+    }
+
+    #[test]
+    fn bounds_the_fft_size() {
+        for fft_size in [MIN_FFT_SIZE, MAX_FFT_SIZE] {
+            let config = AnalyzerConfig {
+                fft_size,
+                hop: fft_size / 2,
+                ..AnalyzerConfig::default()
+            };
+            assert!(config.validate().is_ok(), "{fft_size} should be accepted");
+        }
+        for fft_size in [MIN_FFT_SIZE / 2, MAX_FFT_SIZE * 2] {
+            let config = AnalyzerConfig {
+                fft_size,
+                hop: fft_size / 2,
+                ..AnalyzerConfig::default()
+            };
+            assert!(config.validate().is_err(), "{fft_size} should be rejected");
+        }
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), SAMPLE_RATE).unwrap();
+        assert!(
+            analyzer
+                .set_resolution(MAX_FFT_SIZE * 2, MAX_FFT_SIZE)
+                .is_err()
+        );
+    }
+}
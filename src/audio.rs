@@ -1,140 +1,525 @@
-use cpal::traits::{DeviceTrait, StreamTrait};
+use anyhow::anyhow;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{
-    Device, FromSample, InputCallbackInfo, Sample, SampleFormat, SizedSample, Stream, StreamConfig,
-    StreamError,
+    Device, FromSample, Host, HostId, InputCallbackInfo, Sample, SampleFormat, SizedSample, Stream,
+    StreamConfig, StreamError, SupportedStreamConfig, SupportedStreamConfigRange,
 };
-use crossbeam_channel::Sender;
-use realfft::RealFftPlanner;
-use realfft::num_complex::Complex32;
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
-use crate::types::{Meter, Spectrum};
+use selara::analyzer::SpectrumAnalyzer;
+use selara::averaging::Averaging;
+use selara::bands::BandScale;
+use selara::truepeak::TruePeakMeter;
+use selara::types::{AnalyzerConfig, Spectrum};
+use selara::weighting::Weighting;
+use selara::window::WindowFunction;
 
+use crate::playback::{Playback, PlaybackOptions, decode_file};
+
+/// Changes applied to a running analyzer thread between chunks.
+#[derive(Clone, Copy, Debug)]
+pub enum AnalyzerCommand {
+    Window(WindowFunction),
+    Resolution {
+        fft_size: usize,
+        hop: usize,
+    },
+    Scale(BandScale),
+    ResetLoudness,
+    Averaging(Averaging),
+    ResetAverage,
+    Weighting(Weighting),
+    /// The source jumped, as in a seek or loop in a file; the samples that
+    /// follow start `frame` frames in.
+    Seek {
+        frame: u64,
+    },
+}
+
+impl AnalyzerCommand {
+    fn apply(self, analyzer: &mut SpectrumAnalyzer) {
+        match self {
+            AnalyzerCommand::Window(window) => analyzer.set_window(window),
+            AnalyzerCommand::Resolution { fft_size, hop } => {
+                // The UI only offers valid sizes
+                let _ = analyzer.set_resolution(fft_size, hop);
+            }
+            AnalyzerCommand::Scale(scale) => analyzer.set_scale(scale),
+            AnalyzerCommand::ResetLoudness => analyzer.reset_loudness(),
+            AnalyzerCommand::Averaging(averaging) => analyzer.set_averaging(averaging),
+            AnalyzerCommand::ResetAverage => analyzer.reset_average(),
+            AnalyzerCommand::Weighting(weighting) => analyzer.set_weighting(weighting),
+            AnalyzerCommand::Seek { frame } => analyzer.seek(frame),
+        }
+    }
+}
+
+/// What a source hands the analyzer thread, in the order it happens.
+pub enum AnalyzerInput {
+    /// Interleaved samples.
+    Samples(Vec<f32>),
+    /// A change tied to a point in the stream, such as a seek.
+    Command(AnalyzerCommand),
+}
+
+/// Runs a [`SpectrumAnalyzer`] on its own thread, feeding it interleaved chunks from
+/// `rx_frames` until the sender hangs up.
 pub fn start_spectrum_analyzer(
-    rx_frames: crossbeam_channel::Receiver<Vec<f32>>,
+    rx_frames: Receiver<AnalyzerInput>,
+    rx_command: Receiver<AnalyzerCommand>,
     tx_spec: Sender<Spectrum>,
-    sample_rate: f32,
+    mut analyzer: SpectrumAnalyzer,
 ) {
     std::thread::spawn(move || {
-        // FFT setup
-        let fft_size: usize = 1024;
-        let hop: usize = fft_size / 2;
-        let bands_target: usize = 96;
-        let smoothing_alpha: f32 = 0.6;
-
-        let mut planner = RealFftPlanner::<f32>::new();
-        let r2c = planner.plan_fft_forward(fft_size);
-
-        let mut input: Vec<f32> = r2c.make_input_vec();
-        let mut spectrum: Vec<Complex32> = r2c.make_output_vec();
-        let mut scratch = r2c.make_scratch_vec();
-
-        let window: Vec<f32> = (0..fft_size)
-            .map(|i| {
-                let n = i as f32;
-                0.5 - 0.5 * ((2.0 * std::f32::consts::PI * n) / fft_size as f32).cos()
-            })
-            .collect();
-
-        let num_bins = spectrum.len(); // == fft_size/2 + 1
-        let bands = bands_target;
-        let f_lo = 20.0f32;
-        let f_hi = (sample_rate / 2.0).min(20_000.0);
-        let bin_hz = |bin: usize| (bin as f32) * sample_rate / (fft_size as f32);
-
-        let mut bin_to_band = vec![0usize; num_bins];
-        for bin in 0..num_bins {
-            let f = bin_hz(bin).max(f_lo);
-            let t = ((f / f_lo).ln() / (f_hi / f_lo).ln()).clamp(0.0, 1.0);
-            let b = (t * (bands as f32 - 1.0)).round() as usize;
-            bin_to_band[bin] = b.min(bands - 1);
+        loop {
+            crossbeam_channel::select! {
+                recv(rx_frames) -> input => match input {
+                    Ok(AnalyzerInput::Samples(chunk)) => {
+                        analyzer.push(&chunk);
+                        while let Some(spectrum) = analyzer.pop() {
+                            let _ = tx_spec.try_send(spectrum);
+                        }
+                    }
+                    Ok(AnalyzerInput::Command(command)) => command.apply(&mut analyzer),
+                    Err(_) => break,
+                },
+                recv(rx_command) -> command => match command {
+                    Ok(command) => command.apply(&mut analyzer),
+                    Err(_) => break,
+                },
+            }
+        }
+    });
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    Input,
+    Output,
+}
+
+/// A device found on one of the available hosts.
+#[derive(Clone)]
+pub struct DeviceEntry {
+    pub host: HostId,
+    pub kind: DeviceKind,
+    pub name: String,
+    pub device: Device,
+    /// The config the stream is opened with, if the device reports one.
+    pub default_config: Option<SupportedStreamConfig>,
+    pub supported_configs: Vec<SupportedStreamConfigRange>,
+}
+
+impl DeviceEntry {
+    /// Output devices are captured as loopback, so both kinds open an input stream.
+    pub fn open(&self) -> Result<(Device, SupportedStreamConfig), anyhow::Error> {
+        let cfg = self
+            .default_config
+            .clone()
+            .ok_or_else(|| anyhow!("{} has no default stream config", self.name))?;
+        Ok((self.device.clone(), cfg))
+    }
+}
+
+pub fn describe_config(cfg: &SupportedStreamConfig) -> String {
+    format!(
+        "{} Hz, {} ch, {}",
+        cfg.sample_rate().0,
+        cfg.channels(),
+        cfg.sample_format()
+    )
+}
+
+pub fn describe_config_range(range: &SupportedStreamConfigRange) -> String {
+    let (min, max) = (range.min_sample_rate().0, range.max_sample_rate().0);
+    let rates = if min == max {
+        format!("{} Hz", min)
+    } else {
+        format!("{}-{} Hz", min, max)
+    };
+    format!(
+        "{}, {} ch, {}",
+        rates,
+        range.channels(),
+        range.sample_format()
+    )
+}
+
+/// Lists every output and input device on every host available on this platform.
+///
+/// Hosts that fail to initialize and devices that can't be queried are skipped.
+pub fn enumerate_devices() -> Vec<DeviceEntry> {
+    let mut entries = Vec::new();
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+
+        if let Ok(devices) = host.output_devices() {
+            for device in devices {
+                let name = device
+                    .name()
+                    .unwrap_or_else(|_| "Unknown Device".to_string());
+                entries.push(DeviceEntry {
+                    host: host_id,
+                    kind: DeviceKind::Output,
+                    name,
+                    default_config: device.default_output_config().ok(),
+                    supported_configs: device
+                        .supported_output_configs()
+                        .map(|configs| configs.collect())
+                        .unwrap_or_default(),
+                    device,
+                });
+            }
         }
 
-        // smoothing buffer
-        let mut smooth = vec![0.0f32; bands];
-        let mut smooth_linear = vec![0.0f32; bands];
-
-        // rolling buffer of mono frames
-        let mut ring: Vec<f32> = Vec::with_capacity(fft_size * 2);
-
-        let gain = 0.2;
-
-        while let Ok(chunk) = rx_frames.recv() {
-            // append new frames from callback
-            ring.extend_from_slice(&chunk);
-
-            // process as long as we have one full FFT frame
-            while ring.len() >= fft_size {
-                // copy + window (no alloc inside the loop)
-                for i in 0..fft_size {
-                    input[i] = ring[i] * window[i];
-                }
-
-                // FFT
-                r2c.process_with_scratch(&mut input, &mut spectrum, &mut scratch)
-                    .expect("FFT failed");
-
-                // magnitude → bands
-                let mut bands_pow = vec![0.0f32; bands];
-                let mut bands_cnt = vec![0u32; bands];
-
-                for (bin, c) in spectrum.iter().enumerate() {
-                    let mag2 = c.re * c.re + c.im * c.im; // power
-                    let b = bin_to_band[bin];
-                    bands_pow[b] += mag2;
-                    bands_cnt[b] += 1;
-                }
-
-                // average + compression + smoothing
-                for b in 0..bands {
-                    let p = if bands_cnt[b] > 0 {
-                        bands_pow[b] / (bands_cnt[b] as f32)
-                    } else {
-                        0.0
-                    };
-
-                    // Linear magnitude for linear mode
-                    let linear_level = if p > 0.0 {
-                        let magnitude = p.sqrt();
-                        (magnitude * gain * 0.8).clamp(0.0, 1.0) // Lower gain for more dynamics
-                    } else {
-                        0.0
-                    };
-
-                    // Convert to decibels with proper reference
-                    let db_level = if p > 0.0 {
-                        let magnitude = p.sqrt();
-                        let db = 20.0 * (magnitude * gain).log10();
-                        // Map from -60dB to 0dB range to 0.0-1.0
-                        ((db + 60.0) / 60.0).clamp(0.0, 1.0)
-                    } else {
-                        0.0
-                    };
-
-                    smooth[b] = smoothing_alpha * db_level + (1.0 - smoothing_alpha) * smooth[b];
-                    smooth_linear[b] =
-                        smoothing_alpha * linear_level + (1.0 - smoothing_alpha) * smooth_linear[b];
-                }
-
-                // send latest smoothed bands
-                let _ = tx_spec.try_send(Spectrum {
-                    bands: smooth.clone(),
-                    bands_linear: smooth_linear.clone(),
+        if let Ok(devices) = host.input_devices() {
+            for device in devices {
+                let name = device
+                    .name()
+                    .unwrap_or_else(|_| "Unknown Device".to_string());
+                entries.push(DeviceEntry {
+                    host: host_id,
+                    kind: DeviceKind::Input,
+                    name,
+                    default_config: device.default_input_config().ok(),
+                    supported_configs: device
+                        .supported_input_configs()
+                        .map(|configs| configs.collect())
+                        .unwrap_or_default(),
+                    device,
                 });
+            }
+        }
+    }
+    entries
+}
 
-                // advance by hop (50% overlap)
-                ring.drain(0..hop);
+/// Prints every device of every host with its default and supported stream configs.
+pub fn list_devices() {
+    let mut current: Option<(HostId, DeviceKind)> = None;
+    for entry in enumerate_devices() {
+        if current != Some((entry.host, entry.kind)) {
+            if current.map(|(host, _)| host) != Some(entry.host) {
+                println!("Host: {}", entry.host.name());
+            }
+            match entry.kind {
+                DeviceKind::Output => println!("  Output devices (captured as loopback):"),
+                DeviceKind::Input => println!("  Input devices:"),
             }
+            current = Some((entry.host, entry.kind));
         }
-    });
+        match &entry.default_config {
+            Some(cfg) => println!("    {} ({})", entry.name, describe_config(cfg)),
+            None => println!("    {}", entry.name),
+        }
+        for range in &entry.supported_configs {
+            println!("      {}", describe_config_range(range));
+        }
+    }
+}
+
+/// Resolves the capture device and its default stream config.
+///
+/// Without a name the default output device of `host` is used. Otherwise devices on
+/// every host are searched for a case-insensitive substring match, preferring `host`
+/// and, within a host, output devices over inputs.
+pub fn open_device(
+    host: &Host,
+    name: Option<&str>,
+) -> Result<(Device, SupportedStreamConfig), anyhow::Error> {
+    let Some(name) = name else {
+        let device = host
+            .default_output_device()
+            .ok_or_else(|| anyhow!("no default output device available"))?;
+        let cfg = device.default_output_config()?;
+        return Ok((device, cfg));
+    };
+
+    let needle = name.to_lowercase();
+    let matches: Vec<DeviceEntry> = enumerate_devices()
+        .into_iter()
+        .filter(|entry| entry.name.to_lowercase().contains(&needle))
+        .collect();
+
+    matches
+        .iter()
+        .find(|entry| entry.host == host.id())
+        .or_else(|| matches.first())
+        .ok_or_else(|| {
+            anyhow!(
+                "no device matching \"{}\" (use --list-devices to see what is available)",
+                name
+            )
+        })?
+        .open()
+}
+
+/// How spectrum frames are queued for the consumer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Buffering {
+    /// A small queue that drops frames the consumer is too slow to take, for live display.
+    Realtime,
+    /// An unbounded queue that keeps every frame, for export.
+    Lossless,
+}
+
+impl Buffering {
+    fn channel<T>(self, capacity: usize) -> (Sender<T>, Receiver<T>) {
+        match self {
+            Buffering::Realtime => crossbeam_channel::bounded(capacity),
+            Buffering::Lossless => crossbeam_channel::unbounded(),
+        }
+    }
+}
+
+/// The sending end of the analyzer's input, counting the blocks dropped because
+/// the analyzer fell behind.
+#[derive(Clone)]
+pub struct FrameSender {
+    tx: Sender<AnalyzerInput>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl FrameSender {
+    fn new(tx: Sender<AnalyzerInput>) -> FrameSender {
+        FrameSender {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Queues a block without waiting, dropping it if the queue is full.
+    /// Returns false once the analyzer has gone.
+    pub fn try_send(&self, samples: Vec<f32>) -> bool {
+        match self.tx.try_send(AnalyzerInput::Samples(samples)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// Queues a block, waiting for room. Returns false once the analyzer has gone.
+    pub fn send(&self, samples: Vec<f32>) -> bool {
+        self.tx.send(AnalyzerInput::Samples(samples)).is_ok()
+    }
+
+    /// Queues a command after the blocks already sent, waiting for room.
+    /// Returns false once the analyzer has gone.
+    pub fn send_command(&self, command: AnalyzerCommand) -> bool {
+        self.tx.send(AnalyzerInput::Command(command)).is_ok()
+    }
+}
+
+/// A running capture stream or file playback and the analyzer thread it feeds.
+///
+/// Dropping it stops the source, which closes the frame channel and ends the analyzer
+/// thread, so switching devices is a matter of replacing the `Capture`.
+pub struct Capture {
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub rx_meter: Receiver<Levels>,
+    pub rx_spec: Receiver<Spectrum>,
+    /// Raw samples for the oscilloscope; blocks are dropped while it is full.
+    pub rx_scope: Receiver<StereoBlock>,
+    pub playback: Option<Playback>,
+    tx_command: Sender<AnalyzerCommand>,
+    dropped: Arc<AtomicU64>,
+    _stream: Option<Stream>,
+}
+
+impl Capture {
+    pub fn start(
+        device: &Device,
+        stream_cfg: &SupportedStreamConfig,
+        analyzer: &AnalyzerConfig,
+        buffering: Buffering,
+    ) -> Result<Capture, anyhow::Error> {
+        let device_name = device
+            .name()
+            .unwrap_or_else(|_| "Unknown Device".to_string());
+
+        let cfg = stream_cfg.config();
+        let channels = cfg.channels as usize;
+        let sample_rate = cfg.sample_rate.0;
+
+        let (tx_meter, rx_meter) = crossbeam_channel::bounded::<Levels>(32);
+        let (tx_spec, rx_spec) = buffering.channel::<Spectrum>(8);
+        // The device keeps going whether or not the analyzer keeps up, so a
+        // lossless capture queues everything instead of dropping blocks
+        let (tx_frames, rx_frames) = buffering.channel::<AnalyzerInput>(16);
+        let tx_frames = FrameSender::new(tx_frames);
+        let dropped = tx_frames.dropped.clone();
+        let (tx_command, rx_command) = crossbeam_channel::unbounded();
+        let (tx_scope, rx_scope) = crossbeam_channel::bounded::<StereoBlock>(64);
+
+        // Start spectrum analyzer thread
+        let analyzer =
+            SpectrumAnalyzer::with_channels(analyzer.clone(), sample_rate as f32, channels)?;
+        start_spectrum_analyzer(rx_frames, rx_command, tx_spec, analyzer);
+
+        // Create audio stream
+        let stream = create_audio_stream(
+            device,
+            stream_cfg.sample_format(),
+            &cfg,
+            channels,
+            tx_meter,
+            tx_frames,
+            tx_scope,
+        )?;
+
+        Ok(Capture {
+            device_name,
+            sample_rate,
+            rx_meter,
+            rx_spec,
+            rx_scope,
+            playback: None,
+            tx_command,
+            dropped,
+            _stream: Some(stream),
+        })
+    }
+
+    /// Decodes `path` and plays it into the analyzer.
+    pub fn open_file(
+        path: &Path,
+        analyzer: &AnalyzerConfig,
+        options: PlaybackOptions,
+        buffering: Buffering,
+    ) -> Result<Capture, anyhow::Error> {
+        let audio = decode_file(path)?;
+        let sample_rate = audio.sample_rate;
+        let device_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        let (tx_meter, rx_meter) = crossbeam_channel::bounded::<Levels>(32);
+        let (tx_spec, rx_spec) = buffering.channel::<Spectrum>(8);
+        // Unlike a device, lossless or unpaced playback waits for room, so the
+        // queue stays small either way
+        let (tx_frames, rx_frames) = crossbeam_channel::bounded::<AnalyzerInput>(16);
+        let tx_frames = FrameSender::new(tx_frames);
+        let dropped = tx_frames.dropped.clone();
+        let (tx_command, rx_command) = crossbeam_channel::unbounded();
+        let (tx_scope, rx_scope) = crossbeam_channel::bounded::<StereoBlock>(64);
+
+        let analyzer =
+            SpectrumAnalyzer::with_channels(analyzer.clone(), sample_rate as f32, audio.channels)?;
+        start_spectrum_analyzer(rx_frames, rx_command, tx_spec, analyzer);
+        let playback = Playback::start(audio, options, buffering, tx_meter, tx_frames, tx_scope);
+
+        Ok(Capture {
+            device_name,
+            sample_rate,
+            rx_meter,
+            rx_spec,
+            rx_scope,
+            playback: Some(playback),
+            tx_command,
+            dropped,
+            _stream: None,
+        })
+    }
+
+    /// Blocks of samples dropped so far because the analyzer fell behind.
+    pub fn dropped_blocks(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Forwards a change to the analyzer thread.
+    pub fn send(&self, command: AnalyzerCommand) {
+        let _ = self.tx_command.send(command);
+    }
+}
+
+/// One block of samples from the first two channels of a source.
+#[derive(Clone, Debug)]
+pub struct StereoBlock {
+    pub left: Vec<f32>,
+    /// Empty for mono sources.
+    pub right: Vec<f32>,
+}
+
+impl StereoBlock {
+    /// Takes the left and right channels out of a block of interleaved frames.
+    pub fn from_interleaved(samples: &[f32], channels: usize) -> StereoBlock {
+        let channel = |index: usize| -> Vec<f32> {
+            samples
+                .chunks_exact(channels)
+                .map(|frame| frame[index])
+                .collect()
+        };
+        StereoBlock {
+            left: channel(0),
+            right: if channels > 1 { channel(1) } else { Vec::new() },
+        }
+    }
+}
+
+/// True peaks of one block and the clips counted so far, for each channel.
+///
+/// RMS is measured by the UI, after the level meter's weighting.
+#[derive(Clone, Debug)]
+pub struct Levels {
+    /// Oversampled peak of each channel in the block, linear.
+    pub true_peak: Vec<f32>,
+    /// Clips counted on each channel since the source started.
+    pub clips: Vec<u64>,
+    /// Frames in the block.
+    pub frames: usize,
+}
+
+/// Converts a block of interleaved frames to f32 and runs it through the
+/// source's true-peak meter.
+///
+/// A trailing partial frame is dropped. Returns `None` for an empty block.
+pub fn convert_block<T>(
+    data: &[T],
+    channels: usize,
+    true_peak: &mut TruePeakMeter,
+) -> Option<(Vec<f32>, Levels)>
+where
+    T: Sample,
+    f32: FromSample<<T as Sample>::Float>,
+{
+    let frames = data.len() / channels;
+    if frames == 0 {
+        return None;
+    }
+    let samples: Vec<f32> = data[..frames * channels]
+        .iter()
+        .map(|s| f32::from_sample(s.to_float_sample()))
+        .collect();
+
+    let peaks = true_peak.process(&samples);
+    Some((
+        samples,
+        Levels {
+            true_peak: peaks,
+            clips: true_peak.clips().to_vec(),
+            frames,
+        },
+    ))
 }
 
 pub fn build_loopback_stream<T>(
     device: &Device,
     cfg: &StreamConfig,
     channels: usize,
-    tx_meter: Sender<Meter>,
-    tx_frames: Sender<Vec<f32>>,
+    tx_meter: Sender<Levels>,
+    tx_frames: FrameSender,
+    tx_scope: Sender<StereoBlock>,
 ) -> Result<Stream, anyhow::Error>
 where
     T: Sample + Send + 'static + SizedSample + std::fmt::Debug,
@@ -142,44 +527,12 @@ where
 {
     let err_callback = |err: StreamError| eprintln!("an error occurred on stream: {}", err);
 
+    let mut true_peak = TruePeakMeter::new(channels);
     let input_callback = move |data: &[T], _info: &InputCallbackInfo| {
-        // Convert interleaved frames to mono f32
-        let mut rms_acc: f32 = 0.0;
-        let mut peak: f32 = 0.0;
-        let mut n: usize = 0;
-        let mut mono_chunk = Vec::with_capacity(data.len() / channels);
-
-        // Process per-frame without allocation
-        for frame in data.chunks(channels) {
-            let left = frame
-                .first()
-                .map(|s| f32::from_sample(s.to_float_sample()))
-                .unwrap_or(0.0f32);
-
-            let right = if channels > 1 {
-                frame
-                    .get(1)
-                    .map(|s| f32::from_sample((*s).to_float_sample()))
-                    .unwrap_or(0.0f32)
-            } else {
-                0.0f32
-            };
-
-            let mono = 0.5f32 * (left + right);
-            mono_chunk.push(mono);
-
-            let a = mono.abs();
-            if a > peak {
-                peak = a;
-            }
-            rms_acc += mono * mono;
-            n += 1
-        }
-
-        if n > 0 {
-            let rms = (rms_acc / n as f32).sqrt();
-            let _ = tx_meter.try_send(Meter { rms, peak });
-            let _ = tx_frames.try_send(mono_chunk);
+        if let Some((samples, levels)) = convert_block(data, channels, &mut true_peak) {
+            let _ = tx_meter.try_send(levels);
+            let _ = tx_scope.try_send(StereoBlock::from_interleaved(&samples, channels));
+            tx_frames.try_send(samples);
         }
     };
 
@@ -194,21 +547,20 @@ pub fn create_audio_stream(
     sample_format: SampleFormat,
     cfg: &StreamConfig,
     channels: usize,
-    tx_meter: Sender<Meter>,
-    tx_frames: Sender<Vec<f32>>,
+    tx_meter: Sender<Levels>,
+    tx_frames: FrameSender,
+    tx_scope: Sender<StereoBlock>,
 ) -> Result<Stream, anyhow::Error> {
     match sample_format {
         SampleFormat::F32 => {
-            build_loopback_stream::<f32>(device, cfg, channels, tx_meter, tx_frames)
+            build_loopback_stream::<f32>(device, cfg, channels, tx_meter, tx_frames, tx_scope)
         }
         SampleFormat::I16 => {
-            build_loopback_stream::<i16>(device, cfg, channels, tx_meter, tx_frames)
+            build_loopback_stream::<i16>(device, cfg, channels, tx_meter, tx_frames, tx_scope)
         }
         SampleFormat::U16 => {
-            build_loopback_stream::<u16>(device, cfg, channels, tx_meter, tx_frames)
-        }
-        _ => {
-            panic!("Unsupported sample format: {:?}", sample_format)
+            build_loopback_stream::<u16>(device, cfg, channels, tx_meter, tx_frames, tx_scope)
         }
+        _ => Err(anyhow!("Unsupported sample format: {:?}", sample_format)),
     }
 }
@@ -0,0 +1,181 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// How band powers are averaged over frames before they are smoothed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Averaging {
+    /// Each frame on its own
+    #[default]
+    Off,
+    /// Mean power of the last N frames
+    Linear,
+    /// Exponentially weighted mean power, each frame weighted 1/N
+    Exponential,
+    /// Mean power of every frame since the last reset, for noise measurements
+    Infinite,
+}
+
+impl Averaging {
+    pub const ALL: [Averaging; 4] = [
+        Averaging::Off,
+        Averaging::Linear,
+        Averaging::Exponential,
+        Averaging::Infinite,
+    ];
+
+    /// The mode after this one in [`Averaging::ALL`], wrapping around.
+    pub fn next(self) -> Averaging {
+        let i = Self::ALL.iter().position(|&a| a == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for Averaging {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Averaging::Off => "off",
+            Averaging::Linear => "linear",
+            Averaging::Exponential => "exponential",
+            Averaging::Infinite => "infinite",
+        })
+    }
+}
+
+/// Averages the band powers of successive frames of one signal.
+///
+/// Sums are kept in f64 so long infinite averages do not lose precision.
+#[derive(Clone)]
+pub(crate) struct BandAverager {
+    /// Running sum for linear and infinite averaging, running mean for
+    /// exponential averaging.
+    sum: Vec<f64>,
+    /// Frames in a linear average, oldest first.
+    recent: VecDeque<Vec<f32>>,
+    frames: u64,
+}
+
+impl BandAverager {
+    pub(crate) fn new(bands: usize) -> BandAverager {
+        BandAverager {
+            sum: vec![0.0; bands],
+            recent: VecDeque::new(),
+            frames: 0,
+        }
+    }
+
+    /// Frames averaged since the last reset.
+    pub(crate) fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.sum.fill(0.0);
+        self.recent.clear();
+        self.frames = 0;
+    }
+
+    /// Folds in the band powers of a new frame and replaces them with the
+    /// average. `n` is the length of linear and exponential averages.
+    pub(crate) fn push(&mut self, mode: Averaging, n: usize, power: &mut [f32]) {
+        self.frames += 1;
+        match mode {
+            Averaging::Off => {}
+            Averaging::Linear => {
+                for (sum, &p) in self.sum.iter_mut().zip(power.iter()) {
+                    *sum += p as f64;
+                }
+                self.recent.push_back(power.to_vec());
+                while self.recent.len() > n {
+                    let oldest = self.recent.pop_front().expect("average is not empty");
+                    for (sum, p) in self.sum.iter_mut().zip(oldest) {
+                        *sum -= p as f64;
+                    }
+                }
+                let count = self.recent.len() as f64;
+                for (p, &sum) in power.iter_mut().zip(&self.sum) {
+                    *p = (sum / count).max(0.0) as f32;
+                }
+            }
+            Averaging::Exponential => {
+                // The first frames are weighted equally, so the average settles
+                // without starting from zero
+                let weight = 1.0 / self.frames.min(n as u64) as f64;
+                for (mean, p) in self.sum.iter_mut().zip(power.iter_mut()) {
+                    *mean += weight * (*p as f64 - *mean);
+                    *p = *mean as f32;
+                }
+            }
+            Averaging::Infinite => {
+                for (sum, p) in self.sum.iter_mut().zip(power.iter_mut()) {
+                    *sum += *p as f64;
+                    *p = (*sum / self.frames as f64) as f32;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(mode: Averaging, n: usize, frames: &[f32]) -> Vec<f32> {
+        let mut averager = BandAverager::new(1);
+        frames
+            .iter()
+            .map(|&p| {
+                let mut power = [p];
+                averager.push(mode, n, &mut power);
+                power[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn off_passes_frames_through() {
+        assert_eq!(run(Averaging::Off, 4, &[1.0, 5.0, 2.0]), [1.0, 5.0, 2.0]);
+    }
+
+    #[test]
+    fn linear_averages_the_last_n_frames() {
+        let out = run(Averaging::Linear, 2, &[2.0, 4.0, 8.0, 0.0]);
+        assert_eq!(out, [2.0, 3.0, 6.0, 4.0]);
+    }
+
+    #[test]
+    fn exponential_weights_new_frames_by_one_over_n() {
+        let out = run(Averaging::Exponential, 2, &[4.0, 8.0, 0.0]);
+        // Equal weights while settling, then half of each new frame
+        assert_eq!(out, [4.0, 6.0, 3.0]);
+    }
+
+    #[test]
+    fn infinite_averages_everything_since_reset() {
+        let mut averager = BandAverager::new(2);
+        for p in [1.0, 2.0, 3.0, 6.0] {
+            averager.push(Averaging::Infinite, 1, &mut [p, 2.0 * p]);
+        }
+        let mut power = [0.0, 0.0];
+        averager.push(Averaging::Infinite, 1, &mut power);
+        assert_eq!(power, [2.4, 4.8]);
+        assert_eq!(averager.frames(), 5);
+
+        averager.reset();
+        let mut power = [7.0, 1.0];
+        averager.push(Averaging::Infinite, 1, &mut power);
+        assert_eq!(power, [7.0, 1.0]);
+    }
+
+    #[test]
+    fn averaging_power_lowers_noise_variance() {
+        // Alternating frames average to a steady level
+        let frames: Vec<f32> = (0..100)
+            .map(|i| if i % 2 == 0 { 0.5 } else { 1.5 })
+            .collect();
+        let out = run(Averaging::Linear, 10, &frames);
+        assert!(out[20..].iter().all(|&p| (p - 1.0).abs() < 1e-6));
+    }
+}
@@ -0,0 +1,422 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+
+/// Alignment level of EBU R68, where VU and broadcast PPM scales read their
+/// reference mark.
+pub const ALIGNMENT_DBFS: f32 = -18.0;
+/// Return rate of digital peak meters, 20 dB in 1.7 s per IEC 60268-18.
+pub const DIGITAL_RETURN_DB_PER_SEC: f32 = 20.0 / 1.7;
+/// How long the peak-hold marker stays at a peak before following the reading.
+const PEAK_HOLD_SECS: f32 = 2.0;
+
+/// Dynamic response of a level meter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ballistics {
+    /// Volume unit meter: rectified average reaching 99% in 300 ms
+    Vu,
+    /// IEC 60268-10 Type I (DIN) PPM: 5 ms integration, 20 dB return in 1.5 s
+    PpmType1,
+    /// IEC 60268-10 Type II (BBC) PPM: 10 ms integration, 24 dB return in 2.8 s
+    PpmType2,
+    /// Nordic PPM, Type I with a 20 dB return in 1.7 s
+    Nordic,
+    /// Sample peak with instant attack and a 20 dB return in 1.7 s
+    #[default]
+    DigitalPeak,
+}
+
+/// Range and markings of a meter scale, in the scale's own units.
+pub struct MeterScale {
+    /// Level in dBFS that the scale reads as 0.
+    pub reference_dbfs: f32,
+    pub unit: &'static str,
+    pub min: f32,
+    pub max: f32,
+    /// Marks as (position, label), bottom to top.
+    pub marks: &'static [(f32, &'static str)],
+}
+
+impl MeterScale {
+    /// Converts a linear level to scale units.
+    pub fn units(&self, level: f32) -> f32 {
+        20.0 * level.max(1e-10).log10() - self.reference_dbfs
+    }
+
+    /// Where a linear level sits on the scale, from 0.0 at the bottom to 1.0
+    /// at the top.
+    pub fn ratio(&self, level: f32) -> f32 {
+        ((self.units(level) - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+}
+
+const VU_SCALE: MeterScale = MeterScale {
+    reference_dbfs: ALIGNMENT_DBFS,
+    unit: "VU",
+    min: -20.0,
+    max: 3.0,
+    marks: &[
+        (-20.0, "-20"),
+        (-10.0, "-10"),
+        (-7.0, "-7"),
+        (-5.0, "-5"),
+        (-3.0, "-3"),
+        (-2.0, "-2"),
+        (-1.0, "-1"),
+        (0.0, "0"),
+        (1.0, "+1"),
+        (2.0, "+2"),
+        (3.0, "+3"),
+    ],
+};
+
+/// DIN scale, with 0 dB at the EBU R68 permitted maximum of -9 dBFS.
+const DIN_SCALE: MeterScale = MeterScale {
+    reference_dbfs: -9.0,
+    unit: "dB",
+    min: -50.0,
+    max: 5.0,
+    marks: &[
+        (-50.0, "-50"),
+        (-40.0, "-40"),
+        (-30.0, "-30"),
+        (-20.0, "-20"),
+        (-10.0, "-10"),
+        (-5.0, "-5"),
+        (0.0, "0"),
+        (5.0, "+5"),
+    ],
+};
+
+/// BBC scale: marks 1 to 7, 4 dB apart above 2 and 6 dB below it, with the
+/// alignment level at 4.
+const BBC_SCALE: MeterScale = MeterScale {
+    reference_dbfs: ALIGNMENT_DBFS,
+    unit: "dB",
+    min: -16.0,
+    max: 14.0,
+    marks: &[
+        (-14.0, "1"),
+        (-8.0, "2"),
+        (-4.0, "3"),
+        (0.0, "4"),
+        (4.0, "5"),
+        (8.0, "6"),
+        (12.0, "7"),
+    ],
+};
+
+const NORDIC_SCALE: MeterScale = MeterScale {
+    reference_dbfs: ALIGNMENT_DBFS,
+    unit: "dB",
+    min: -36.0,
+    max: 12.0,
+    marks: &[
+        (-36.0, "-36"),
+        (-30.0, "-30"),
+        (-24.0, "-24"),
+        (-18.0, "-18"),
+        (-12.0, "-12"),
+        (-6.0, "-6"),
+        (0.0, "TEST"),
+        (6.0, "+6"),
+        (9.0, "+9"),
+        (12.0, "+12"),
+    ],
+};
+
+const DIGITAL_SCALE: MeterScale = MeterScale {
+    reference_dbfs: 0.0,
+    unit: "dBFS",
+    min: -60.0,
+    max: 0.0,
+    marks: &[
+        (-60.0, "-60"),
+        (-50.0, "-50"),
+        (-40.0, "-40"),
+        (-30.0, "-30"),
+        (-20.0, "-20"),
+        (-10.0, "-10"),
+        (-6.0, "-6"),
+        (-3.0, "-3"),
+        (0.0, "0"),
+    ],
+};
+
+impl Ballistics {
+    pub const ALL: [Ballistics; 5] = [
+        Ballistics::Vu,
+        Ballistics::PpmType1,
+        Ballistics::PpmType2,
+        Ballistics::Nordic,
+        Ballistics::DigitalPeak,
+    ];
+
+    /// The ballistics after this one in [`Ballistics::ALL`], wrapping around.
+    pub fn next(self) -> Ballistics {
+        let i = Self::ALL.iter().position(|&b| b == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn scale(self) -> &'static MeterScale {
+        match self {
+            Ballistics::Vu => &VU_SCALE,
+            Ballistics::PpmType1 => &DIN_SCALE,
+            Ballistics::PpmType2 => &BBC_SCALE,
+            Ballistics::Nordic => &NORDIC_SCALE,
+            Ballistics::DigitalPeak => &DIGITAL_SCALE,
+        }
+    }
+
+    /// Charging time constant of the peak detector in seconds and the gain in
+    /// dB that makes it read a steady sine at its amplitude, or `None` for an
+    /// instant attack.
+    ///
+    /// IEC 60268-10 defines the integration time as the length of a 5 kHz
+    /// burst that reads 2 dB below a steady tone; the constants give that for
+    /// a detector charging on the rectified signal.
+    fn attack(self) -> Option<(f32, f32)> {
+        match self {
+            Ballistics::PpmType1 => Some((0.001_363, 0.174)),
+            Ballistics::Nordic => Some((0.001_355, 0.159)),
+            Ballistics::PpmType2 => Some((0.002_763, 0.213)),
+            Ballistics::Vu | Ballistics::DigitalPeak => None,
+        }
+    }
+
+    /// Fall rate of the peak detector in dB per second.
+    fn return_db_per_sec(self) -> f32 {
+        match self {
+            Ballistics::PpmType1 => 20.0 / 1.5,
+            Ballistics::PpmType2 => 24.0 / 2.8,
+            Ballistics::Nordic | Ballistics::DigitalPeak => DIGITAL_RETURN_DB_PER_SEC,
+            Ballistics::Vu => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Ballistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Ballistics::Vu => "VU",
+            Ballistics::PpmType1 => "PPM Type I",
+            Ballistics::PpmType2 => "PPM Type II",
+            Ballistics::Nordic => "Nordic PPM",
+            Ballistics::DigitalPeak => "Digital peak",
+        })
+    }
+}
+
+/// Natural frequency and damping of the VU movement: 99% of a steady reading
+/// after 300 ms with about 1.4% overshoot, per IEC 60268-17.
+const VU_OMEGA: f32 = 13.2;
+const VU_DAMPING: f32 = 0.8;
+
+/// Follows the level of one channel with the dynamics of a meter standard,
+/// and holds its peaks.
+///
+/// Readings are linear and sine-calibrated: a steady sine of amplitude `a`
+/// reads `a` on every meter.
+///
+/// ```
+/// use selara::ballistics::{Ballistics, BallisticMeter};
+///
+/// let tone: Vec<f32> = (0..48_000)
+///     .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1_000.0 * i as f32 / 48_000.0).sin())
+///     .collect();
+/// let mut meter = BallisticMeter::new(Ballistics::PpmType2, 48_000.0);
+/// meter.push(&tone);
+/// assert!((meter.level() - 0.5).abs() < 0.01);
+/// ```
+pub struct BallisticMeter {
+    ballistics: Ballistics,
+    /// Seconds per sample.
+    dt: f32,
+    attack: f32,
+    release: f32,
+    /// Calibration applied to the readings.
+    gain: f32,
+    level: f32,
+    /// Rate of change of the VU reading.
+    velocity: f32,
+    hold: f32,
+    hold_samples: usize,
+    hold_left: usize,
+}
+
+impl BallisticMeter {
+    pub fn new(ballistics: Ballistics, sample_rate: f32) -> BallisticMeter {
+        let (attack, gain) = ballistics.attack().map_or((1.0, 1.0), |(secs, gain_db)| {
+            (
+                1.0 - (-1.0 / (secs * sample_rate)).exp(),
+                10f32.powf(gain_db / 20.0),
+            )
+        });
+        let release = 10f32.powf(-ballistics.return_db_per_sec() / (20.0 * sample_rate));
+        BallisticMeter {
+            ballistics,
+            dt: 1.0 / sample_rate,
+            attack,
+            release,
+            gain,
+            level: 0.0,
+            velocity: 0.0,
+            hold: 0.0,
+            hold_samples: (PEAK_HOLD_SECS * sample_rate) as usize,
+            hold_left: 0,
+        }
+    }
+
+    pub fn ballistics(&self) -> Ballistics {
+        self.ballistics
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &x in samples {
+            let rectified = x.abs();
+            if self.ballistics == Ballistics::Vu {
+                // Average of the rectified sine is 2/π of its amplitude
+                let input = rectified * std::f32::consts::FRAC_PI_2;
+                let acceleration = VU_OMEGA * VU_OMEGA * (input - self.level)
+                    - 2.0 * VU_DAMPING * VU_OMEGA * self.velocity;
+                self.velocity += acceleration * self.dt;
+                self.level = (self.level + self.velocity * self.dt).max(0.0);
+            } else if rectified > self.level {
+                self.level += self.attack * (rectified - self.level);
+            } else {
+                self.level *= self.release;
+            }
+
+            if self.level >= self.hold {
+                self.hold = self.level;
+                self.hold_left = self.hold_samples;
+            } else if self.hold_left > 0 {
+                self.hold_left -= 1;
+            } else {
+                self.hold = self.level;
+            }
+        }
+    }
+
+    /// The current reading, linear.
+    pub fn level(&self) -> f32 {
+        self.level * self.gain
+    }
+
+    /// The highest reading of the last two seconds, linear.
+    pub fn hold(&self) -> f32 {
+        self.hold * self.gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    fn sine(freq: f32, amplitude: f32, secs: f32) -> Vec<f32> {
+        (0..(secs * RATE) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / RATE).sin())
+            .collect()
+    }
+
+    /// Highest reading of a meter fed `samples` and then silence.
+    fn max_reading(ballistics: Ballistics, samples: &[f32]) -> f32 {
+        let mut meter = BallisticMeter::new(ballistics, RATE);
+        meter.push(samples);
+        let level = meter.level();
+        meter.push(&vec![0.0; 4800]);
+        level.max(meter.hold())
+    }
+
+    fn db(level: f32) -> f32 {
+        20.0 * level.log10()
+    }
+
+    #[test]
+    fn steady_sine_reads_its_amplitude() {
+        let tone = sine(1_000.0, 0.25, 2.0);
+        for ballistics in Ballistics::ALL {
+            let mut meter = BallisticMeter::new(ballistics, RATE);
+            meter.push(&tone);
+            assert!(db(meter.level() / 0.25).abs() < 0.1, "{}", ballistics);
+        }
+    }
+
+    #[test]
+    fn vu_reaches_99_percent_in_300_ms() {
+        let mut meter = BallisticMeter::new(Ballistics::Vu, RATE);
+        meter.push(&sine(1_000.0, 1.0, 0.28));
+        assert!(meter.level() < 0.99);
+        meter.push(&sine(1_000.0, 1.0, 0.04));
+        assert!(meter.level() > 0.98);
+
+        // Overshoot of 1 to 1.5%
+        meter.push(&sine(1_000.0, 1.0, 0.7));
+        assert!((1.005..1.02).contains(&meter.hold()), "{}", meter.hold());
+    }
+
+    #[test]
+    fn ppm_integration_time_reads_2_db_low() {
+        for (ballistics, secs) in [
+            (Ballistics::PpmType1, 0.005),
+            (Ballistics::Nordic, 0.005),
+            (Ballistics::PpmType2, 0.010),
+        ] {
+            let burst = sine(5_000.0, 1.0, secs);
+            let reading = db(max_reading(ballistics, &burst));
+            assert!((reading + 2.0).abs() < 0.3, "{} {}", ballistics, reading);
+        }
+    }
+
+    #[test]
+    fn return_rates() {
+        for (ballistics, fall_db, secs) in [
+            (Ballistics::PpmType1, 20.0, 1.5),
+            (Ballistics::PpmType2, 24.0, 2.8),
+            (Ballistics::Nordic, 20.0, 1.7),
+            (Ballistics::DigitalPeak, 20.0, 1.7),
+        ] {
+            let mut meter = BallisticMeter::new(ballistics, RATE);
+            meter.push(&sine(1_000.0, 1.0, 1.0));
+            let start = meter.level();
+            meter.push(&vec![0.0; (secs * RATE) as usize]);
+            let fall = db(start) - db(meter.level());
+            assert!((fall - fall_db).abs() < 0.5, "{} {}", ballistics, fall);
+        }
+    }
+
+    #[test]
+    fn digital_peak_catches_a_single_sample() {
+        let mut samples = vec![0.0; 1000];
+        samples[500] = -0.5;
+        let mut meter = BallisticMeter::new(Ballistics::DigitalPeak, RATE);
+        meter.push(&samples[..501]);
+        assert_eq!(meter.level(), 0.5);
+    }
+
+    #[test]
+    fn hold_drops_after_two_seconds() {
+        let mut meter = BallisticMeter::new(Ballistics::DigitalPeak, RATE);
+        meter.push(&[0.8]);
+        meter.push(&vec![0.0; (1.9 * RATE) as usize]);
+        assert_eq!(meter.hold(), 0.8);
+        meter.push(&vec![0.0; (0.2 * RATE) as usize]);
+        assert_eq!(meter.hold(), meter.level());
+        assert!(meter.hold() < 0.8);
+    }
+
+    #[test]
+    fn scales_place_the_alignment_level() {
+        let alignment = 10f32.powf(ALIGNMENT_DBFS / 20.0);
+        assert!(Ballistics::Vu.scale().units(alignment).abs() < 1e-3);
+        assert!(Ballistics::PpmType2.scale().units(alignment).abs() < 1e-3);
+        assert!((Ballistics::PpmType1.scale().units(alignment) + 9.0).abs() < 1e-3);
+        let scale = Ballistics::DigitalPeak.scale();
+        assert_eq!(scale.ratio(1.0), 1.0);
+        assert_eq!(scale.ratio(0.0), 0.0);
+        assert!((scale.ratio(10f32.powf(-30.0 / 20.0)) - 0.5).abs() < 1e-3);
+    }
+}
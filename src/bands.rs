@@ -0,0 +1,379 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+
+/// How the analyzed range is divided into bands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BandScale {
+    /// `bands` log-spaced bands between the minimum and maximum frequency
+    #[default]
+    Log,
+    /// Octave bands
+    Octave,
+    /// One-third-octave bands
+    ThirdOctave,
+    /// One-sixth-octave bands
+    SixthOctave,
+    /// One-twelfth-octave bands
+    TwelfthOctave,
+    /// One-twenty-fourth-octave bands
+    TwentyFourthOctave,
+    /// `bands` bands of equal width on the mel scale (HTK formula)
+    Mel,
+    /// `bands` bands of equal width on the Bark critical-band scale (Traunmüller)
+    Bark,
+    /// `bands` bands of equal width on the ERB-rate scale (Glasberg & Moore)
+    Erb,
+}
+
+impl BandScale {
+    pub const ALL: [BandScale; 9] = [
+        BandScale::Log,
+        BandScale::Octave,
+        BandScale::ThirdOctave,
+        BandScale::SixthOctave,
+        BandScale::TwelfthOctave,
+        BandScale::TwentyFourthOctave,
+        BandScale::Mel,
+        BandScale::Bark,
+        BandScale::Erb,
+    ];
+
+    /// The scale after this one in [`BandScale::ALL`], wrapping around.
+    pub fn next(self) -> BandScale {
+        let i = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Bands per octave for the fractional-octave scales.
+    pub fn bands_per_octave(self) -> Option<u32> {
+        match self {
+            BandScale::Log | BandScale::Mel | BandScale::Bark | BandScale::Erb => None,
+            BandScale::Octave => Some(1),
+            BandScale::ThirdOctave => Some(3),
+            BandScale::SixthOctave => Some(6),
+            BandScale::TwelfthOctave => Some(12),
+            BandScale::TwentyFourthOctave => Some(24),
+        }
+    }
+}
+
+impl fmt::Display for BandScale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BandScale::Log => f.write_str("Log"),
+            BandScale::Mel => f.write_str("Mel"),
+            BandScale::Bark => f.write_str("Bark"),
+            BandScale::Erb => f.write_str("ERB"),
+            _ => write!(f, "1/{} octave", self.bands_per_octave().unwrap_or(1)),
+        }
+    }
+}
+
+/// Center and edge frequencies of each band, in Hz.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BandLayout {
+    /// Exact center of each band.
+    pub centers: Vec<f32>,
+    /// Rounded center used to label each band, such as 31.5 for 31.62 Hz.
+    pub nominal: Vec<f32>,
+    /// Band edges from low to high; band `i` spans `edges[i]..edges[i + 1]`.
+    pub edges: Vec<f32>,
+}
+
+/// Octave frequency ratio of the base-ten system in IEC 61260-1.
+const OCTAVE_RATIO: f64 = 1.995_262_314_968_879_5; // 10^(3/10)
+const REFERENCE_HZ: f64 = 1000.0;
+
+impl BandLayout {
+    /// Bands covering `f_lo..f_hi`. `bands` is not used by the fractional-octave
+    /// scales.
+    ///
+    /// Fractional-octave scales keep the bands whose nominal center lies in the
+    /// range, and at least the one closest to its middle.
+    pub fn new(scale: BandScale, bands: usize, f_lo: f32, f_hi: f32) -> BandLayout {
+        if let Some(b) = scale.bands_per_octave() {
+            return BandLayout::fractional_octave(b, f_lo, f_hi);
+        }
+        match scale {
+            BandScale::Mel => BandLayout::warped(bands, f_lo, f_hi, hz_to_mel, mel_to_hz),
+            BandScale::Bark => BandLayout::warped(bands, f_lo, f_hi, hz_to_bark, bark_to_hz),
+            BandScale::Erb => BandLayout::warped(bands, f_lo, f_hi, hz_to_erb, erb_to_hz),
+            _ => BandLayout::log(bands, f_lo, f_hi),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.centers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.centers.is_empty()
+    }
+
+    fn log(bands: usize, f_lo: f32, f_hi: f32) -> BandLayout {
+        let step = (f_hi / f_lo).powf(1.0 / (bands as f32 - 1.0));
+        let centers: Vec<f32> = (0..bands)
+            .map(|b| f_lo * (f_hi / f_lo).powf(b as f32 / (bands as f32 - 1.0)))
+            .collect();
+        // Edges halfway between centers on the log axis
+        let half_step = step.sqrt();
+        let edges = std::iter::once(centers[0] / half_step)
+            .chain(centers.iter().map(|c| c * half_step))
+            .collect();
+        BandLayout {
+            nominal: centers.clone(),
+            centers,
+            edges,
+        }
+    }
+
+    /// Bands of equal width on a perceptual scale, with edges at `f_lo` and `f_hi`
+    /// and each center halfway between its edges on that scale.
+    fn warped(bands: usize, f_lo: f32, f_hi: f32, to_scale: Warp, to_hz: Warp) -> BandLayout {
+        let (lo, hi) = (to_scale(f_lo as f64), to_scale(f_hi as f64));
+        let width = (hi - lo) / bands as f64;
+        let edges = (0..=bands)
+            .map(|i| to_hz(lo + i as f64 * width) as f32)
+            .collect();
+        let centers: Vec<f32> = (0..bands)
+            .map(|i| to_hz(lo + (i as f64 + 0.5) * width) as f32)
+            .collect();
+        BandLayout {
+            nominal: centers
+                .iter()
+                .map(|&c| round_significant(c as f64, 3) as f32)
+                .collect(),
+            centers,
+            edges,
+        }
+    }
+
+    /// Base-ten fractional-octave bands per IEC 61260-1, with ISO 266 nominal centers.
+    fn fractional_octave(b: u32, f_lo: f32, f_hi: f32) -> BandLayout {
+        let b = b as f64;
+        // Odd fractions center a band on 1 kHz, even ones put an edge there
+        let offset = if b as u32 % 2 == 1 { 0.0 } else { 0.5 };
+        let center = |x: i32| REFERENCE_HZ * OCTAVE_RATIO.powf((x as f64 + offset) / b);
+        let half_band = OCTAVE_RATIO.powf(1.0 / (2.0 * b));
+        let index_of =
+            |f: f32| ((f as f64 / REFERENCE_HZ).log(OCTAVE_RATIO) * b - offset).round() as i32;
+
+        let in_range = |x: i32| {
+            let nominal = nominal_frequency(center(x), b as u32);
+            nominal >= f_lo as f64 && nominal <= f_hi as f64
+        };
+        let mut indices: Vec<i32> = (index_of(f_lo) - 1..=index_of(f_hi) + 1)
+            .filter(|&x| in_range(x))
+            .collect();
+        if indices.is_empty() {
+            indices.push(index_of((f_lo * f_hi).sqrt()));
+        }
+
+        let centers: Vec<f64> = indices.iter().map(|&x| center(x)).collect();
+        BandLayout {
+            nominal: centers
+                .iter()
+                .map(|&c| nominal_frequency(c, b as u32) as f32)
+                .collect(),
+            edges: std::iter::once(centers[0] / half_band)
+                .chain(centers.iter().map(|c| c * half_band))
+                .map(|f| f as f32)
+                .collect(),
+            centers: centers.into_iter().map(|c| c as f32).collect(),
+        }
+    }
+}
+
+/// ISO 266 R10 preferred numbers, the nominal octave and third-octave centers.
+const R10: [f64; 10] = [1.0, 1.25, 1.6, 2.0, 2.5, 3.15, 4.0, 5.0, 6.3, 8.0];
+
+/// Nominal center for an exact midband frequency of a 1/`b`-octave band.
+///
+/// Octave and third-octave centers snap to the R10 series; finer bands are
+/// rounded to three significant digits.
+fn nominal_frequency(exact: f64, b: u32) -> f64 {
+    let decade = 10f64.powf(exact.log10().floor());
+    if b == 1 || b == 3 {
+        let mantissa = exact / decade;
+        let nearest = R10
+            .iter()
+            .chain(&[10.0])
+            .copied()
+            .min_by(|a, b| {
+                (mantissa / a)
+                    .ln()
+                    .abs()
+                    .total_cmp(&(mantissa / b).ln().abs())
+            })
+            .unwrap();
+        nearest * decade
+    } else {
+        round_significant(exact, 3)
+    }
+}
+
+fn round_significant(value: f64, digits: i32) -> f64 {
+    let scale = 10f64.powi(digits - 1) / 10f64.powf(value.log10().floor());
+    (value * scale).round() / scale
+}
+
+/// Maps frequencies in Hz to a perceptual scale or back.
+type Warp = fn(f64) -> f64;
+
+fn hz_to_mel(hz: f64) -> f64 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    700.0 * (10f64.powf(mel / 2595.0) - 1.0)
+}
+
+/// Critical-band rate in Bark, after Traunmüller (1990).
+fn hz_to_bark(hz: f64) -> f64 {
+    26.81 * hz / (1960.0 + hz) - 0.53
+}
+
+fn bark_to_hz(bark: f64) -> f64 {
+    1960.0 * (bark + 0.53) / (26.28 - bark)
+}
+
+/// ERB-rate (ERB number), after Glasberg & Moore (1990).
+fn hz_to_erb(hz: f64) -> f64 {
+    21.4 * (1.0 + 0.00437 * hz).log10()
+}
+
+fn erb_to_hz(erb: f64) -> f64 {
+    (10f64.powf(erb / 21.4) - 1.0) / 0.00437
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rounded(values: &[f32]) -> Vec<f32> {
+        values.iter().map(|v| (v * 100.0).round() / 100.0).collect()
+    }
+
+    #[test]
+    fn octave_nominal_centers() {
+        let layout = BandLayout::new(BandScale::Octave, 0, 20.0, 20_000.0);
+        assert_eq!(
+            rounded(&layout.nominal),
+            [
+                31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0
+            ]
+        );
+    }
+
+    #[test]
+    fn third_octave_nominal_centers() {
+        let layout = BandLayout::new(BandScale::ThirdOctave, 0, 20.0, 20_000.0);
+        assert_eq!(layout.len(), 31);
+        assert_eq!(
+            rounded(&layout.nominal[..8]),
+            [20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0]
+        );
+        assert_eq!(
+            rounded(&layout.nominal[layout.len() - 3..]),
+            [12500.0, 16000.0, 20000.0]
+        );
+        // 1 kHz is an exact center
+        assert!(layout.centers.iter().any(|&c| (c - 1000.0).abs() < 1e-3));
+    }
+
+    #[test]
+    fn even_fractions_put_an_edge_at_1khz() {
+        for scale in [BandScale::SixthOctave, BandScale::TwelfthOctave] {
+            let layout = BandLayout::new(scale, 0, 20.0, 20_000.0);
+            assert!(layout.edges.iter().any(|&e| (e - 1000.0).abs() < 1e-2));
+            assert!(!layout.centers.iter().any(|&c| (c - 1000.0).abs() < 1.0));
+        }
+    }
+
+    #[test]
+    fn bands_are_contiguous() {
+        for scale in BandScale::ALL {
+            let layout = BandLayout::new(scale, 96, 20.0, 20_000.0);
+            assert_eq!(layout.edges.len(), layout.len() + 1);
+            for (i, &center) in layout.centers.iter().enumerate() {
+                let (lo, hi) = (layout.edges[i], layout.edges[i + 1]);
+                assert!(lo < center && center < hi, "{}: band {}", scale, i);
+            }
+        }
+    }
+
+    #[test]
+    fn log_and_octave_centers_are_geometric_means() {
+        for scale in BandScale::ALL {
+            if matches!(scale, BandScale::Mel | BandScale::Bark | BandScale::Erb) {
+                continue;
+            }
+            let layout = BandLayout::new(scale, 96, 20.0, 20_000.0);
+            for (i, &center) in layout.centers.iter().enumerate() {
+                let (lo, hi) = (layout.edges[i], layout.edges[i + 1]);
+                assert!(((lo * hi).sqrt() / center - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn perceptual_scales_match_reference_values() {
+        assert!((hz_to_mel(1000.0) - 1000.0).abs() < 0.1);
+        assert!((hz_to_bark(1000.0) - 8.527).abs() < 1e-3);
+        assert!((hz_to_erb(1000.0) - 15.62).abs() < 0.01);
+        for hz in [20.0, 440.0, 1000.0, 16_000.0] {
+            assert!((mel_to_hz(hz_to_mel(hz)) / hz - 1.0).abs() < 1e-9);
+            assert!((bark_to_hz(hz_to_bark(hz)) / hz - 1.0).abs() < 1e-9);
+            assert!((erb_to_hz(hz_to_erb(hz)) / hz - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn perceptual_bands_are_equally_wide_on_their_scale() {
+        let cases: [(BandScale, Warp); 3] = [
+            (BandScale::Mel, hz_to_mel),
+            (BandScale::Bark, hz_to_bark),
+            (BandScale::Erb, hz_to_erb),
+        ];
+        for (scale, to_scale) in cases {
+            let layout = BandLayout::new(scale, 40, 20.0, 16_000.0);
+            assert_eq!(layout.len(), 40);
+            assert!((layout.edges[0] - 20.0).abs() < 1e-3);
+            assert!((layout.edges[40] - 16_000.0).abs() < 0.1);
+            let width =
+                |i: usize| to_scale(layout.edges[i + 1] as f64) - to_scale(layout.edges[i] as f64);
+            for i in 0..40 {
+                assert!(
+                    (width(i) / width(0) - 1.0).abs() < 1e-3,
+                    "{}: band {}",
+                    scale,
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn band_counts_per_octave() {
+        // 20 Hz to 20 kHz is just under ten octaves
+        let count = |scale| BandLayout::new(scale, 0, 20.0, 20_000.0).len();
+        assert_eq!(count(BandScale::SixthOctave), 60);
+        assert_eq!(count(BandScale::TwelfthOctave), 120);
+        assert_eq!(count(BandScale::TwentyFourthOctave), 240);
+    }
+
+    #[test]
+    fn narrow_range_keeps_one_band() {
+        let layout = BandLayout::new(BandScale::Octave, 0, 1100.0, 1200.0);
+        assert_eq!(layout.nominal, [1000.0]);
+    }
+
+    #[test]
+    fn finer_nominal_centers_have_three_digits() {
+        assert_eq!(nominal_frequency(1059.25, 6), 1060.0);
+        assert_eq!(nominal_frequency(29.85, 12), 29.9);
+        assert_eq!(nominal_frequency(15_848.9, 1), 16_000.0);
+    }
+}
@@ -0,0 +1,323 @@
+use realfft::num_complex::Complex32;
+use realfft::{RealFftPlanner, RealToComplex};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::pitch::{NOTE_NAMES, Note};
+use crate::window::{WindowFunction, enbw};
+
+/// Pitch classes from C to B.
+pub const PITCH_CLASSES: usize = 12;
+/// Frequencies folded into the chroma, in Hz: from A1 up to where harmonics
+/// add little but noise.
+pub const MIN_CHROMA_HZ: f32 = 55.0;
+pub const MAX_CHROMA_HZ: f32 = 5_000.0;
+/// Widest bin folded into the chroma, in semitones; wider bins cannot tell
+/// neighbouring notes apart.
+const MAX_BIN_SEMITONES: f32 = 2.0;
+
+/// Krumhansl-Kessler probe-tone ratings of each pitch class above the tonic.
+const MAJOR_PROFILE: [f32; PITCH_CLASSES] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; PITCH_CLASSES] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        })
+    }
+}
+
+/// A musical key estimated from a chroma profile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    /// Pitch class of the tonic; C is 0.
+    pub tonic: usize,
+    pub mode: Mode,
+    /// Correlation of the chroma with the key's profile, 0 to 1; higher when
+    /// the music fits the key more clearly.
+    pub confidence: f32,
+}
+
+impl Key {
+    /// Name of the tonic, such as `F#`.
+    pub fn tonic_name(&self) -> &'static str {
+        NOTE_NAMES[self.tonic]
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.tonic_name(), self.mode)
+    }
+}
+
+/// The key whose Krumhansl-Kessler profile best correlates with a chroma
+/// profile, indexed from C, or `None` when the profile is flat.
+///
+/// ```
+/// use selara::chroma::{Mode, estimate_key};
+///
+/// // The notes of a C major scale, the tonic triad stronger
+/// let chroma = [3.0, 0.0, 1.0, 0.0, 2.0, 1.0, 0.0, 2.0, 0.0, 1.0, 0.0, 1.0];
+/// let key = estimate_key(&chroma).unwrap();
+/// assert_eq!((key.tonic, key.mode), (0, Mode::Major));
+/// ```
+pub fn estimate_key(chroma: &[f32; PITCH_CLASSES]) -> Option<Key> {
+    let mut best: Option<Key> = None;
+    for tonic in 0..PITCH_CLASSES {
+        for (mode, profile) in [(Mode::Major, &MAJOR_PROFILE), (Mode::Minor, &MINOR_PROFILE)] {
+            let rotated: Vec<f32> = (0..PITCH_CLASSES)
+                .map(|class| profile[(class + PITCH_CLASSES - tonic) % PITCH_CLASSES])
+                .collect();
+            let r = correlation(chroma, &rotated)?;
+            if best.is_none_or(|key| r > key.confidence) {
+                best = Some(Key {
+                    tonic,
+                    mode,
+                    confidence: r,
+                });
+            }
+        }
+    }
+    best.map(|key| Key {
+        confidence: key.confidence.max(0.0),
+        ..key
+    })
+}
+
+/// Pearson correlation of two equally long series, or `None` if either is
+/// constant.
+fn correlation(a: &[f32], b: &[f32]) -> Option<f32> {
+    let n = a.len() as f32;
+    let (mean_a, mean_b) = (a.iter().sum::<f32>() / n, b.iter().sum::<f32>() / n);
+    let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        let (x, y) = (x - mean_a, y - mean_b);
+        ab += x * y;
+        aa += x * x;
+        bb += y * y;
+    }
+    (aa > 1e-12 && bb > 1e-12).then(|| ab / (aa * bb).sqrt())
+}
+
+/// Measures the power in each pitch class of mono input.
+///
+/// The latest samples go through a Hann-windowed FFT of its own, about a
+/// third of a second long so neighbouring notes fall in different bins down to
+/// [`MIN_CHROMA_HZ`]. Each bin's power is shared between the classes its width
+/// covers on the semitone scale.
+///
+/// ```
+/// use selara::chroma::ChromaAnalyzer;
+///
+/// let mut analyzer = ChromaAnalyzer::new(48_000.0, 440.0);
+/// analyzer.push((0..16384).map(|i| (std::f32::consts::TAU * 440.0 * i as f32 / 48_000.0).sin()));
+/// let chroma = analyzer.chroma().unwrap();
+/// // Nearly all of a full-scale A in class 9
+/// assert!(chroma[9] > 0.9);
+/// ```
+pub struct ChromaAnalyzer {
+    window: Vec<f32>,
+    /// Turns summed bin power into power relative to a full-scale sine.
+    scale: f32,
+    /// The latest samples, oldest first.
+    history: VecDeque<f32>,
+    r2c: Arc<dyn RealToComplex<f32>>,
+    input: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    /// (bin, pitch class, share of the bin's power)
+    shares: Vec<(usize, usize, f32)>,
+}
+
+impl ChromaAnalyzer {
+    /// An analyzer naming pitch classes relative to `a4` Hz.
+    pub fn new(sample_rate: f32, a4: f32) -> ChromaAnalyzer {
+        let size = ((sample_rate / 3.0) as usize).next_power_of_two();
+        let window = WindowFunction::Hann.coefficients(size, 0.0);
+        let gain = 2.0 / window.iter().sum::<f32>();
+        let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(size);
+        ChromaAnalyzer {
+            scale: gain * gain / enbw(&window),
+            history: VecDeque::with_capacity(size),
+            input: r2c.make_input_vec(),
+            spectrum: r2c.make_output_vec(),
+            shares: pitch_class_shares(size, sample_rate, a4),
+            window,
+            r2c,
+        }
+    }
+
+    /// Appends mono samples, keeping the latest window.
+    pub fn push(&mut self, samples: impl IntoIterator<Item = f32>) {
+        self.history.extend(samples);
+        let excess = self.history.len().saturating_sub(self.window.len());
+        self.history.drain(..excess);
+    }
+
+    /// Power of each pitch class from C in the latest window, relative to a
+    /// full-scale sine, or `None` until a window has arrived.
+    pub fn chroma(&mut self) -> Option<[f32; PITCH_CLASSES]> {
+        if self.history.len() < self.window.len() {
+            return None;
+        }
+        for ((x, &s), w) in self.input.iter_mut().zip(&self.history).zip(&self.window) {
+            *x = s * w;
+        }
+        self.r2c
+            .process(&mut self.input, &mut self.spectrum)
+            .expect("FFT failed");
+        let mut chroma = [0.0; PITCH_CLASSES];
+        for &(bin, class, share) in &self.shares {
+            chroma[class] += share * self.spectrum[bin].norm_sqr() * self.scale;
+        }
+        Some(chroma)
+    }
+}
+
+/// How the power of each FFT bin is shared between pitch classes, for bins
+/// between [`MIN_CHROMA_HZ`] and [`MAX_CHROMA_HZ`] no wider than
+/// [`MAX_BIN_SEMITONES`].
+fn pitch_class_shares(fft_size: usize, sample_rate: f32, a4: f32) -> Vec<(usize, usize, f32)> {
+    let bin_hz = sample_rate / fft_size as f32;
+    let max_hz = MAX_CHROMA_HZ.min(sample_rate / 2.0);
+    // Semitones above C0, whose pitch class is 0
+    let c0 = Note::frequency(12, a4);
+    let semitones = |hz: f32| 12.0 * (hz / c0).log2();
+
+    let mut shares = Vec::new();
+    for bin in 1..=fft_size / 2 {
+        let lo = ((bin as f32 - 0.5) * bin_hz).max(MIN_CHROMA_HZ);
+        let hi = ((bin as f32 + 0.5) * bin_hz).min(max_hz);
+        if lo >= hi {
+            continue;
+        }
+        let (lo, hi) = (semitones(lo), semitones(hi));
+        if hi - lo > MAX_BIN_SEMITONES {
+            continue;
+        }
+        // Class n covers n - 0.5 to n + 0.5 semitones
+        let mut note = (lo + 0.5).floor();
+        while note - 0.5 < hi {
+            let overlap = (hi.min(note + 0.5) - lo.max(note - 0.5)) / (hi - lo);
+            if overlap > 0.0 {
+                let class = (note as i32).rem_euclid(PITCH_CLASSES as i32) as usize;
+                shares.push((bin, class, overlap));
+            }
+            note += 1.0;
+        }
+    }
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_is_found_from_its_own_profile() {
+        for tonic in 0..PITCH_CLASSES {
+            for (mode, profile) in [(Mode::Major, MAJOR_PROFILE), (Mode::Minor, MINOR_PROFILE)] {
+                let chroma = std::array::from_fn(|class| {
+                    profile[(class + PITCH_CLASSES - tonic) % PITCH_CLASSES]
+                });
+                let key = estimate_key(&chroma).unwrap();
+                assert_eq!((key.tonic, key.mode), (tonic, mode));
+                assert!((key.confidence - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn triads_name_their_key() {
+        // A minor: A, C, E
+        let mut chroma = [0.1; PITCH_CLASSES];
+        for (class, weight) in [(9, 1.0), (0, 0.7), (4, 0.8)] {
+            chroma[class] = weight;
+        }
+        let key = estimate_key(&chroma).unwrap();
+        assert_eq!(key.to_string(), "A minor");
+
+        // F# major: F#, A#, C#
+        let mut chroma = [0.1; PITCH_CLASSES];
+        for (class, weight) in [(6, 1.0), (10, 0.7), (1, 0.8)] {
+            chroma[class] = weight;
+        }
+        assert_eq!(estimate_key(&chroma).unwrap().to_string(), "F# major");
+    }
+
+    #[test]
+    fn flat_chroma_has_no_key() {
+        assert_eq!(estimate_key(&[0.0; PITCH_CLASSES]), None);
+        assert_eq!(estimate_key(&[0.5; PITCH_CLASSES]), None);
+    }
+
+    fn sine(freq: f32, len: usize) -> impl Iterator<Item = f32> {
+        (0..len).map(move |i| 0.5 * (std::f32::consts::TAU * freq * i as f32 / 48_000.0).sin())
+    }
+
+    fn loudest(chroma: &[f32; PITCH_CLASSES]) -> usize {
+        (0..PITCH_CLASSES)
+            .max_by(|&a, &b| chroma[a].total_cmp(&chroma[b]))
+            .unwrap()
+    }
+
+    #[test]
+    fn notes_fold_into_their_pitch_class() {
+        for (hz, class) in [
+            (440.0, 9),
+            (261.63, 0),
+            (1_479.98, 6),
+            (61.74, 11),
+            (98.0, 7),
+        ] {
+            let mut analyzer = ChromaAnalyzer::new(48_000.0, 440.0);
+            analyzer.push(sine(hz, 16384));
+            let chroma = analyzer.chroma().unwrap();
+            assert_eq!(loudest(&chroma), class, "{} Hz", hz);
+            // A quarter of full-scale power, most of it in one class even
+            // where semitones are only a few bins apart
+            assert!(
+                (chroma.iter().sum::<f32>() - 0.25).abs() < 0.01,
+                "{:?}",
+                chroma
+            );
+            assert!(chroma[class] > 0.15, "{:?}", chroma);
+        }
+    }
+
+    #[test]
+    fn reference_pitch_moves_the_classes() {
+        // 440 Hz is G# when A4 is a semitone higher
+        let mut analyzer = ChromaAnalyzer::new(48_000.0, 466.16);
+        analyzer.push(sine(440.0, 16384));
+        assert_eq!(loudest(&analyzer.chroma().unwrap()), 8);
+    }
+
+    #[test]
+    fn chroma_needs_a_full_window() {
+        let mut analyzer = ChromaAnalyzer::new(48_000.0, 440.0);
+        analyzer.push(sine(440.0, 8192));
+        assert_eq!(analyzer.chroma(), None);
+    }
+
+    #[test]
+    fn bins_too_wide_for_a_note_are_left_out() {
+        let shares = pitch_class_shares(1024, 48_000.0, 440.0);
+        // 47 Hz bins are narrower than two semitones from about 420 Hz up
+        assert!(shares.iter().all(|&(bin, _, _)| bin >= 9));
+        assert!(shares.iter().any(|&(bin, _, _)| bin == 9));
+    }
+}
@@ -0,0 +1,275 @@
+use clap::{Parser, ValueEnum};
+use selara::averaging::Averaging;
+use selara::ballistics::Ballistics;
+use selara::bands::BandScale;
+use selara::loudness::LoudnessTarget;
+use selara::weighting::Weighting;
+use selara::window::WindowFunction;
+use std::path::PathBuf;
+
+use crate::config::AnalyzerOverrides;
+use crate::playback::PlaybackOptions;
+use crate::waterfall::Colormap;
+
+/// A real-time audio spectrum analyzer and visualizer for the terminal
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Configuration file [default: $XDG_CONFIG_HOME/selara/config.toml]
+    #[arg(short, long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Configuration profile to start with [default: the file's default_profile]
+    #[arg(short, long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Capture device, matched as a case-insensitive substring of the device name
+    /// [default: system output device]
+    #[arg(short, long, value_name = "NAME")]
+    pub device: Option<String>,
+
+    /// Analyze a WAV, FLAC or Ogg Vorbis file instead of capturing live audio
+    #[arg(short, long, value_name = "PATH", conflicts_with = "device")]
+    pub file: Option<PathBuf>,
+
+    /// File playback speed as a multiple of real time; 0 runs as fast as possible
+    /// [default: 1, or 0 with --headless]
+    #[arg(long, value_name = "X", requires = "file", value_parser = parse_speed)]
+    pub speed: Option<f32>,
+
+    /// Restart file playback when it reaches the end
+    #[arg(long = "loop", requires = "file")]
+    pub looping: bool,
+
+    /// Run without the terminal UI and write every spectrum frame to the output
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Seconds of audio to analyze in headless mode [default: whole file]
+    #[arg(long, value_name = "SECS", requires = "headless")]
+    pub duration: Option<f64>,
+
+    /// Headless output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl, requires = "headless")]
+    pub format: ExportFormat,
+
+    /// Headless output file [default: stdout]
+    #[arg(short, long, value_name = "PATH", requires = "headless")]
+    pub output: Option<PathBuf>,
+
+    /// List the available capture devices and exit
+    #[arg(long)]
+    pub list_devices: bool,
+
+    /// FFT size in samples, a power of two from 256 to 32768 [default: 1024]
+    #[arg(long, value_name = "N")]
+    pub fft_size: Option<usize>,
+
+    /// Samples between successive FFT frames [default: fft-size / 2]
+    #[arg(long, value_name = "N")]
+    pub hop: Option<usize>,
+
+    /// Number of spectrum bands on the log scale [default: 96]
+    #[arg(long, value_name = "N")]
+    pub bands: Option<usize>,
+
+    /// How the frequency range is divided into bands [default: log]
+    #[arg(long, value_enum)]
+    pub scale: Option<BandScale>,
+
+    /// Lowest analyzed frequency in Hz [default: 20]
+    #[arg(long, value_name = "HZ")]
+    pub min_freq: Option<f32>,
+
+    /// Highest analyzed frequency in Hz, capped at Nyquist [default: 20000]
+    #[arg(long, value_name = "HZ")]
+    pub max_freq: Option<f32>,
+
+    /// Time constant of rising band levels in ms, 0 for none [default: 10]
+    #[arg(long, value_name = "MS")]
+    pub attack_ms: Option<f32>,
+
+    /// Time constant of falling band levels in ms, 0 for none [default: 100]
+    #[arg(long, value_name = "MS")]
+    pub release_ms: Option<f32>,
+
+    /// Deprecated: an old smoothing factor, 0 < α ≤ 1, converted to a release time
+    #[arg(long, value_name = "ALPHA", conflicts_with = "release_ms")]
+    pub smoothing: Option<f32>,
+
+    /// How band powers are averaged over frames [default: off]
+    #[arg(long, value_enum)]
+    pub averaging: Option<Averaging>,
+
+    /// Frames in a linear or exponential average, 1 to 256 [default: 8]
+    #[arg(long, value_name = "N")]
+    pub average_frames: Option<usize>,
+
+    /// Window applied before each FFT [default: hann]
+    #[arg(long, value_enum)]
+    pub window: Option<WindowFunction>,
+
+    /// Shape of the Kaiser window, 0 to 50 [default: 8.6]
+    #[arg(long, value_name = "BETA")]
+    pub kaiser_beta: Option<f32>,
+
+    /// Frequency weighting of the spectrum and level meter [default: z]
+    #[arg(long, value_enum)]
+    pub weighting: Option<Weighting>,
+
+    /// Start in linear display mode instead of dB
+    #[arg(long, overrides_with = "no_linear")]
+    pub linear: bool,
+
+    /// Start in dB display mode even if the profile sets linear
+    #[arg(long, overrides_with = "linear")]
+    pub no_linear: bool,
+
+    /// Level at the bottom of the dB spectrum in dBFS [default: -80]
+    #[arg(long, value_name = "DB", allow_negative_numbers = true)]
+    pub db_floor: Option<f32>,
+
+    /// Level at the top of the dB spectrum in dBFS [default: 0]
+    #[arg(long, value_name = "DB", allow_negative_numbers = true)]
+    pub db_ceiling: Option<f32>,
+
+    /// Show the waterfall below the spectrum
+    #[arg(long)]
+    pub waterfall: bool,
+
+    /// Show the oscilloscope
+    #[arg(long)]
+    pub oscilloscope: bool,
+
+    /// Show the goniometer beside the level meters
+    #[arg(long)]
+    pub goniometer: bool,
+
+    /// Show the phase correlation meter and its history
+    #[arg(long)]
+    pub correlation: bool,
+
+    /// Show the stereo balance readout
+    #[arg(long)]
+    pub balance: bool,
+
+    /// Show the EBU R128 loudness meter
+    #[arg(long)]
+    pub loudness: bool,
+
+    /// Programme loudness the meter compares against [default: broadcast]
+    #[arg(long, value_enum)]
+    pub loudness_target: Option<LoudnessTarget>,
+
+    /// Dynamics and scale of the level meter [default: digital-peak]
+    #[arg(long, value_enum)]
+    pub ballistics: Option<Ballistics>,
+
+    /// Show the loudest level of each band since the last reset
+    #[arg(long)]
+    pub max_hold: bool,
+
+    /// Show the quietest level of each band since the last reset
+    #[arg(long)]
+    pub min_hold: bool,
+
+    /// Seconds the peak caps on the bars hold before falling, 0 to 10 [default: 1]
+    #[arg(long, value_name = "SECS")]
+    pub peak_hold: Option<f32>,
+
+    /// Fall rate of the peak caps in dB per second, up to 200 [default: 20]
+    #[arg(long, value_name = "DB")]
+    pub peak_fall: Option<f32>,
+
+    /// Show the tuner with the detected note
+    #[arg(long)]
+    pub tuner: bool,
+
+    /// Label the spectrum axis with the C of every octave
+    #[arg(long)]
+    pub note_labels: bool,
+
+    /// Show the chroma of the pitch classes and the estimated key
+    #[arg(long)]
+    pub chroma: bool,
+
+    /// Show the tempo and a beat indicator in the title
+    #[arg(long)]
+    pub tempo: bool,
+
+    /// Show the spectral and waveform features with their recent history
+    #[arg(long)]
+    pub features: bool,
+
+    /// Frequency of A4 for note names, 400 to 480 Hz [default: 440]
+    #[arg(long, value_name = "HZ")]
+    pub a4: Option<f32>,
+
+    /// Colors of the waterfall [default: viridis]
+    #[arg(long, value_enum)]
+    pub colormap: Option<Colormap>,
+
+    /// Target UI frame rate [default: 60]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=240))]
+    pub fps: Option<u32>,
+}
+
+impl Cli {
+    /// Playback settings for `--file`. Headless runs stop at the end of the file.
+    pub fn playback_options(&self) -> PlaybackOptions {
+        let default_speed = if self.headless { 0.0 } else { 1.0 };
+        let speed = self.speed.unwrap_or(default_speed);
+        PlaybackOptions {
+            speed: if speed > 0.0 { Some(speed) } else { None },
+            looping: self.looping,
+            exit_at_end: self.headless,
+        }
+    }
+
+    /// Display mode given on the command line; the last of `--linear` and
+    /// `--no-linear` wins.
+    pub fn linear(&self) -> Option<bool> {
+        match (self.linear, self.no_linear) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Analyzer options given on the command line, applied on top of the profile.
+    pub fn analyzer_overrides(&self) -> AnalyzerOverrides {
+        AnalyzerOverrides {
+            fft_size: self.fft_size,
+            hop: self.hop,
+            bands: self.bands,
+            scale: self.scale,
+            min_freq: self.min_freq,
+            max_freq: self.max_freq,
+            attack_ms: self.attack_ms,
+            release_ms: self.release_ms,
+            smoothing: self.smoothing,
+            averaging: self.averaging,
+            average_frames: self.average_frames,
+            window: self.window,
+            kaiser_beta: self.kaiser_beta,
+            weighting: self.weighting,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per frame
+    Jsonl,
+    /// A header row, then one row per frame
+    Csv,
+}
+
+fn parse_speed(s: &str) -> Result<f32, String> {
+    let speed: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if speed.is_finite() && speed >= 0.0 {
+        Ok(speed)
+    } else {
+        Err("speed must be 0 or a positive number".to_string())
+    }
+}
@@ -0,0 +1,510 @@
+use anyhow::{Context, anyhow, bail};
+use clap::Parser;
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use selara::averaging::Averaging;
+use selara::ballistics::Ballistics;
+use selara::bands::BandScale;
+use selara::loudness::LoudnessTarget;
+use selara::pitch::{DEFAULT_A4_HZ, MAX_A4_HZ, MIN_A4_HZ};
+use selara::types::{AnalyzerConfig, MAX_TIME_CONSTANT_MS, MIN_DBFS};
+use selara::weighting::Weighting;
+use selara::window::WindowFunction;
+
+use crate::cli::Cli;
+use crate::keys::{Action, KeyMap, KeySpec};
+use crate::peaks::{
+    DEFAULT_FALL_DB_PER_SEC, DEFAULT_HOLD_SECS, MAX_FALL_DB_PER_SEC, MAX_HOLD_SECS,
+};
+use crate::waterfall::{Colormap, DEFAULT_HISTORY, MAX_HISTORY, MIN_HISTORY};
+
+/// Highest dB ceiling the display accepts; levels above 0 dBFS only occur with clipping.
+pub const MAX_DB_CEILING: f32 = 20.0;
+
+/// Contents of `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Profile used when none is given with `--profile`.
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub device: Option<String>,
+    #[serde(default)]
+    pub analyzer: AnalyzerOverrides,
+    #[serde(default)]
+    pub display: DisplayOverrides,
+    pub theme: Option<Theme>,
+    pub layout: Option<LayoutConfig>,
+    #[serde(default)]
+    pub keys: HashMap<Action, KeySpec>,
+}
+
+/// Analyzer parameters that a profile or the command line may override.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnalyzerOverrides {
+    pub fft_size: Option<usize>,
+    pub hop: Option<usize>,
+    pub bands: Option<usize>,
+    pub scale: Option<BandScale>,
+    pub min_freq: Option<f32>,
+    pub max_freq: Option<f32>,
+    pub attack_ms: Option<f32>,
+    pub release_ms: Option<f32>,
+    /// Deprecated per-frame smoothing factor, 0 < α ≤ 1, taken as a release time.
+    pub smoothing: Option<f32>,
+    pub averaging: Option<Averaging>,
+    pub average_frames: Option<usize>,
+    pub window: Option<WindowFunction>,
+    pub kaiser_beta: Option<f32>,
+    pub weighting: Option<Weighting>,
+}
+
+impl AnalyzerOverrides {
+    pub fn apply(&self, config: &mut AnalyzerConfig) -> Result<(), anyhow::Error> {
+        if let Some(fft_size) = self.fft_size {
+            config.fft_size = fft_size;
+            config.hop = fft_size / 2;
+        }
+        if let Some(hop) = self.hop {
+            config.hop = hop;
+        }
+        if let Some(bands) = self.bands {
+            config.bands = bands;
+        }
+        if let Some(scale) = self.scale {
+            config.scale = scale;
+        }
+        if let Some(min_freq) = self.min_freq {
+            config.min_freq = min_freq;
+        }
+        if let Some(max_freq) = self.max_freq {
+            config.max_freq = max_freq;
+        }
+        if let Some(attack_ms) = self.attack_ms {
+            config.attack_ms = attack_ms;
+        }
+        if let Some(smoothing) = self.smoothing {
+            config.release_ms = release_for_smoothing(smoothing, config.hop)?;
+        }
+        if let Some(release_ms) = self.release_ms {
+            config.release_ms = release_ms;
+        }
+        if let Some(averaging) = self.averaging {
+            config.averaging = averaging;
+        }
+        if let Some(average_frames) = self.average_frames {
+            config.average_frames = average_frames;
+        }
+        if let Some(window) = self.window {
+            config.window = window;
+        }
+        if let Some(kaiser_beta) = self.kaiser_beta {
+            config.kaiser_beta = kaiser_beta;
+        }
+        if let Some(weighting) = self.weighting {
+            config.weighting = weighting;
+        }
+        Ok(())
+    }
+}
+
+/// Release time in ms that falls as fast as the old smoothing factor, which
+/// gave each new frame a weight of `smoothing`, for frames `hop` samples apart
+/// at 48 kHz.
+fn release_for_smoothing(smoothing: f32, hop: usize) -> Result<f32, anyhow::Error> {
+    if !(smoothing > 0.0 && smoothing <= 1.0) {
+        bail!(
+            "smoothing must be in (0, 1], got {}; it is deprecated in favor of attack_ms and release_ms",
+            smoothing
+        );
+    }
+    let frame_ms = hop as f32 / 48.0;
+    Ok((-frame_ms / (1.0 - smoothing).ln()).min(MAX_TIME_CONSTANT_MS))
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DisplayOverrides {
+    pub linear: Option<bool>,
+    pub fps: Option<u32>,
+    pub db_floor: Option<f32>,
+    pub db_ceiling: Option<f32>,
+    pub colormap: Option<Colormap>,
+    pub waterfall_history: Option<usize>,
+    pub loudness_target: Option<LoudnessTarget>,
+    pub ballistics: Option<Ballistics>,
+    pub peak_hold: Option<f32>,
+    pub peak_fall: Option<f32>,
+    pub a4: Option<f32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub border: Color,
+    pub title: Color,
+    pub text: Color,
+    pub label: Color,
+    pub value: Color,
+    pub key: Color,
+    pub dim: Color,
+    pub axis: Color,
+    pub peak: Color,
+    /// Traces of the left and right channel.
+    pub left: Color,
+    pub right: Color,
+    /// Colors the spectrum and level gradient passes through from low to high.
+    pub gradient: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            border: Color::Rgb(96, 160, 192),
+            title: Color::Rgb(128, 224, 208),
+            text: Color::Rgb(200, 200, 200),
+            label: Color::Rgb(128, 160, 192),
+            value: Color::White,
+            key: Color::Rgb(255, 255, 0),
+            dim: Color::Rgb(128, 128, 128),
+            axis: Color::Rgb(160, 160, 160),
+            peak: Color::Rgb(255, 255, 255),
+            left: Color::Rgb(64, 224, 208),
+            right: Color::Rgb(224, 128, 160),
+            gradient: vec![
+                Color::Rgb(64, 224, 208),
+                Color::Rgb(128, 160, 128),
+                Color::Rgb(64, 224, 224),
+                Color::Rgb(128, 96, 160),
+            ],
+        }
+    }
+}
+
+/// Which optional panels are shown.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub rms_meter: bool,
+    pub frequency_labels: bool,
+    pub status_bar: bool,
+    pub waterfall: bool,
+    pub oscilloscope: bool,
+    pub goniometer: bool,
+    pub correlation: bool,
+    pub balance: bool,
+    pub loudness: bool,
+    pub peak_caps: bool,
+    pub max_hold: bool,
+    pub min_hold: bool,
+    pub tuner: bool,
+    pub note_labels: bool,
+    pub chroma: bool,
+    pub tempo: bool,
+    pub features: bool,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            rms_meter: true,
+            frequency_labels: true,
+            status_bar: true,
+            waterfall: false,
+            oscilloscope: false,
+            goniometer: false,
+            correlation: false,
+            balance: false,
+            loudness: false,
+            peak_caps: true,
+            max_hold: false,
+            min_hold: false,
+            tuner: false,
+            note_labels: false,
+            chroma: false,
+            tempo: false,
+            features: false,
+        }
+    }
+}
+
+/// Fully resolved settings: defaults, then the selected profile, then the command line.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub profile: Option<String>,
+    pub device: Option<String>,
+    pub analyzer: AnalyzerConfig,
+    pub linear: bool,
+    pub fps: u32,
+    /// Levels shown at the bottom and top of the dB spectrum.
+    pub db_floor: f32,
+    pub db_ceiling: f32,
+    pub colormap: Colormap,
+    /// Spectra shown in the waterfall, newest at the top.
+    pub waterfall_history: usize,
+    pub loudness_target: LoudnessTarget,
+    pub ballistics: Ballistics,
+    /// Seconds the peak caps hold and their fall rate in dB per second.
+    pub peak_hold: f32,
+    pub peak_fall: f32,
+    /// Frequency of A4 in Hz, the reference for note names.
+    pub a4: f32,
+    pub theme: Theme,
+    pub layout: LayoutConfig,
+    pub keys: KeyMap,
+}
+
+impl Default for Settings {
+    /// The built-in defaults, ignoring any config file.
+    fn default() -> Self {
+        let cli = Cli::parse_from(["selara"]);
+        Settings::from_config(&cli, &ConfigFile::default()).expect("default settings are valid")
+    }
+}
+
+impl Settings {
+    pub fn resolve(cli: &Cli) -> Result<Settings, anyhow::Error> {
+        let config = match &cli.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => match default_config_path() {
+                Some(path) if path.exists() => ConfigFile::load(&path)?,
+                _ => ConfigFile::default(),
+            },
+        };
+        Settings::from_config(cli, &config)
+    }
+
+    /// Resolves the settings against an already loaded config file.
+    fn from_config(cli: &Cli, config: &ConfigFile) -> Result<Settings, anyhow::Error> {
+        let profile_name = cli.profile.clone().or(config.default_profile.clone());
+        let profile = match &profile_name {
+            Some(name) => config.profiles.get(name).cloned().ok_or_else(|| {
+                let available: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+                anyhow!(
+                    "unknown profile \"{}\" (available: {})",
+                    name,
+                    if available.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available.join(", ")
+                    }
+                )
+            })?,
+            None => Profile::default(),
+        };
+
+        let mut analyzer = AnalyzerConfig::default();
+        profile.analyzer.apply(&mut analyzer)?;
+        cli.analyzer_overrides().apply(&mut analyzer)?;
+        analyzer.validate()?;
+        if profile.analyzer.smoothing.is_some() || cli.smoothing.is_some() {
+            eprintln!("warning: smoothing is deprecated; set attack_ms and release_ms instead");
+        }
+
+        let fps = cli.fps.or(profile.display.fps).unwrap_or(60);
+        if !(1..=240).contains(&fps) {
+            bail!("fps must be between 1 and 240, got {}", fps);
+        }
+
+        let db_floor = cli.db_floor.or(profile.display.db_floor).unwrap_or(-80.0);
+        let db_ceiling = cli.db_ceiling.or(profile.display.db_ceiling).unwrap_or(0.0);
+        if db_floor < MIN_DBFS || db_ceiling > MAX_DB_CEILING || db_floor >= db_ceiling {
+            bail!(
+                "dB range must satisfy {} <= floor < ceiling <= {}, got {}..{}",
+                MIN_DBFS,
+                MAX_DB_CEILING,
+                db_floor,
+                db_ceiling
+            );
+        }
+
+        let waterfall_history = profile.display.waterfall_history.unwrap_or(DEFAULT_HISTORY);
+        if !(MIN_HISTORY..=MAX_HISTORY).contains(&waterfall_history) {
+            bail!(
+                "waterfall_history must be between {} and {}, got {}",
+                MIN_HISTORY,
+                MAX_HISTORY,
+                waterfall_history
+            );
+        }
+
+        let peak_hold = cli
+            .peak_hold
+            .or(profile.display.peak_hold)
+            .unwrap_or(DEFAULT_HOLD_SECS);
+        if !(0.0..=MAX_HOLD_SECS).contains(&peak_hold) {
+            bail!(
+                "peak_hold must be between 0 and {} seconds, got {}",
+                MAX_HOLD_SECS,
+                peak_hold
+            );
+        }
+        let peak_fall = cli
+            .peak_fall
+            .or(profile.display.peak_fall)
+            .unwrap_or(DEFAULT_FALL_DB_PER_SEC);
+        if !(peak_fall > 0.0 && peak_fall <= MAX_FALL_DB_PER_SEC) {
+            bail!(
+                "peak_fall must be above 0 and at most {} dB per second, got {}",
+                MAX_FALL_DB_PER_SEC,
+                peak_fall
+            );
+        }
+
+        let a4 = cli.a4.or(profile.display.a4).unwrap_or(DEFAULT_A4_HZ);
+        if !(MIN_A4_HZ..=MAX_A4_HZ).contains(&a4) {
+            bail!(
+                "a4 must be between {} and {} Hz, got {}",
+                MIN_A4_HZ,
+                MAX_A4_HZ,
+                a4
+            );
+        }
+
+        let mut layout = profile.layout.unwrap_or_default();
+        layout.waterfall |= cli.waterfall;
+        layout.oscilloscope |= cli.oscilloscope;
+        layout.goniometer |= cli.goniometer;
+        layout.correlation |= cli.correlation;
+        layout.balance |= cli.balance;
+        layout.loudness |= cli.loudness;
+        layout.max_hold |= cli.max_hold;
+        layout.min_hold |= cli.min_hold;
+        layout.tuner |= cli.tuner;
+        layout.note_labels |= cli.note_labels;
+        layout.chroma |= cli.chroma;
+        layout.tempo |= cli.tempo;
+        layout.features |= cli.features;
+
+        Ok(Settings {
+            profile: profile_name,
+            device: cli.device.clone().or(profile.device),
+            analyzer,
+            linear: cli.linear().or(profile.display.linear).unwrap_or(false),
+            fps,
+            db_floor,
+            db_ceiling,
+            colormap: cli
+                .colormap
+                .or(profile.display.colormap)
+                .unwrap_or_default(),
+            waterfall_history,
+            loudness_target: cli
+                .loudness_target
+                .or(profile.display.loudness_target)
+                .unwrap_or_default(),
+            ballistics: cli
+                .ballistics
+                .or(profile.display.ballistics)
+                .unwrap_or_default(),
+            peak_hold,
+            peak_fall,
+            a4,
+            theme: profile.theme.unwrap_or_default(),
+            layout,
+            keys: KeyMap::new(&profile.keys).context("invalid key binding in profile")?,
+        })
+    }
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<ConfigFile, anyhow::Error> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("failed to parse config file {}", path.display()))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/selara/config.toml` or the platform equivalent.
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("selara").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        default_profile = "mixing"
+
+        [profiles.mixing]
+        analyzer = { fft_size = 4096 }
+        display = { linear = true, db_floor = -60 }
+
+        [profiles.voice]
+        analyzer = { fft_size = 2048, bands = 48 }
+
+        [profiles.old]
+        analyzer = { smoothing = 0.6 }
+    "#;
+
+    fn resolve(args: &[&str]) -> Result<Settings, anyhow::Error> {
+        let cli = Cli::parse_from(std::iter::once("selara").chain(args.iter().copied()));
+        Settings::from_config(&cli, &toml::from_str(CONFIG).unwrap())
+    }
+
+    #[test]
+    fn profile_on_the_command_line_replaces_the_default() {
+        let settings = resolve(&[]).unwrap();
+        assert_eq!(settings.profile.as_deref(), Some("mixing"));
+        assert_eq!(settings.analyzer.fft_size, 4096);
+        assert!(settings.linear);
+
+        let settings = resolve(&["--profile", "voice"]).unwrap();
+        assert_eq!(settings.profile.as_deref(), Some("voice"));
+        assert_eq!(settings.analyzer.fft_size, 2048);
+        assert_eq!(settings.analyzer.bands, 48);
+        assert!(!settings.linear);
+        assert_eq!(settings.db_floor, -80.0);
+    }
+
+    #[test]
+    fn unknown_profiles_list_the_available_ones() {
+        let error = resolve(&["--profile", "live"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown profile \"live\" (available: mixing, old, voice)"
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_the_profile() {
+        let settings =
+            resolve(&["--fft-size", "8192", "--db-floor", "-90", "--no-linear"]).unwrap();
+        assert_eq!(settings.analyzer.fft_size, 8192);
+        assert_eq!(settings.analyzer.hop, 4096);
+        assert_eq!(settings.db_floor, -90.0);
+        assert!(!settings.linear);
+
+        // The last of --linear and --no-linear wins
+        assert!(resolve(&["--no-linear", "--linear"]).unwrap().linear);
+        let settings = resolve(&["--profile", "voice", "--linear"]).unwrap();
+        assert!(settings.linear);
+    }
+
+    #[test]
+    fn smoothing_still_sets_the_release() {
+        // Frames 512 samples apart, each weighted by 0.6
+        let settings = resolve(&["--profile", "old"]).unwrap();
+        let expected = -512.0 / 48.0 / 0.4f32.ln();
+        assert!((settings.analyzer.release_ms - expected).abs() < 1e-3);
+        assert_eq!(settings.analyzer.attack_ms, 10.0);
+
+        let settings = resolve(&["--profile", "old", "--smoothing", "1"]).unwrap();
+        assert_eq!(settings.analyzer.release_ms, 0.0);
+        let settings = resolve(&["--profile", "old", "--release-ms", "300"]).unwrap();
+        assert_eq!(settings.analyzer.release_ms, 300.0);
+        assert!(resolve(&["--smoothing", "0"]).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use crossbeam_channel::RecvTimeoutError;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use selara::features::Features;
+use selara::loudness::Loudness;
+use selara::tempo::Tempo;
+use selara::types::{AnalyzerConfig, Spectrum};
+
+use crate::audio::Capture;
+use crate::cli::ExportFormat;
+
+#[derive(Serialize)]
+struct FrameRecord<'a> {
+    time: f64,
+    rms: f32,
+    peak: f32,
+    frequencies: &'a [f32],
+    edges: &'a [f32],
+    bands: &'a [f32],
+    bands_linear: &'a [f32],
+    loudness: Loudness,
+    onset: bool,
+    beat: bool,
+    tempo: Option<Tempo>,
+    features: Features,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    channels: Vec<ChannelRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct ChannelRecord<'a> {
+    channel: String,
+    rms: f32,
+    peak: f32,
+    bands: &'a [f32],
+    bands_linear: &'a [f32],
+}
+
+/// Writes spectrum frames as JSON Lines or CSV.
+pub struct Exporter<W: Write> {
+    writer: W,
+    format: ExportFormat,
+    wrote_header: bool,
+}
+
+impl<W: Write> Exporter<W> {
+    pub fn new(writer: W, format: ExportFormat) -> Exporter<W> {
+        Exporter {
+            writer,
+            format,
+            wrote_header: false,
+        }
+    }
+
+    pub fn write(&mut self, spectrum: &Spectrum) -> Result<(), anyhow::Error> {
+        match self.format {
+            ExportFormat::Jsonl => {
+                let record = FrameRecord {
+                    time: spectrum.time,
+                    rms: spectrum.meter.rms,
+                    peak: spectrum.meter.peak,
+                    frequencies: &spectrum.frequencies,
+                    edges: &spectrum.edges,
+                    bands: &spectrum.bands,
+                    bands_linear: &spectrum.bands_linear,
+                    loudness: spectrum.loudness,
+                    onset: spectrum.onset,
+                    beat: spectrum.beat,
+                    tempo: spectrum.tempo,
+                    features: spectrum.features,
+                    channels: spectrum
+                        .channels
+                        .iter()
+                        .map(|c| ChannelRecord {
+                            channel: c.channel.to_string(),
+                            rms: c.meter.rms,
+                            peak: c.meter.peak,
+                            bands: &c.bands,
+                            bands_linear: &c.bands_linear,
+                        })
+                        .collect(),
+                };
+                serde_json::to_writer(&mut self.writer, &record)?;
+                writeln!(self.writer)?;
+            }
+            ExportFormat::Csv => {
+                // Columns are named after the band centers of the first frame
+                if !self.wrote_header {
+                    write!(
+                        self.writer,
+                        "time,rms,peak,momentary,short_term,integrated,loudness_range,onset,\
+                         beat,bpm,tempo_confidence,centroid,bandwidth,rolloff,flatness,flux,\
+                         zero_crossing_rate,crest_factor"
+                    )?;
+                    for f in spectrum.frequencies.iter() {
+                        write!(self.writer, ",band_{:.1}", f)?;
+                    }
+                    for f in spectrum.frequencies.iter() {
+                        write!(self.writer, ",linear_{:.1}", f)?;
+                    }
+                    // Then each channel of a multi-channel source, prefixed
+                    // with its name
+                    for channel in &spectrum.channels {
+                        let name = channel.channel;
+                        write!(self.writer, ",{}_rms,{}_peak", name, name)?;
+                        for f in spectrum.frequencies.iter() {
+                            write!(self.writer, ",{}_band_{:.1}", name, f)?;
+                        }
+                        for f in spectrum.frequencies.iter() {
+                            write!(self.writer, ",{}_linear_{:.1}", name, f)?;
+                        }
+                    }
+                    writeln!(self.writer)?;
+                    self.wrote_header = true;
+                }
+                write!(
+                    self.writer,
+                    "{:.6},{},{}",
+                    spectrum.time, spectrum.meter.rms, spectrum.meter.peak
+                )?;
+                // Loudness not yet measured leaves its columns empty, like the tempo
+                let loudness = &spectrum.loudness;
+                for reading in [
+                    loudness.momentary,
+                    loudness.short_term,
+                    loudness.integrated,
+                    loudness.range,
+                ] {
+                    match reading {
+                        Some(value) => write!(self.writer, ",{}", value)?,
+                        None => write!(self.writer, ",")?,
+                    }
+                }
+                write!(
+                    self.writer,
+                    ",{},{},",
+                    spectrum.onset as u8, spectrum.beat as u8
+                )?;
+                // A tempo not yet known leaves its columns empty
+                if let Some(tempo) = spectrum.tempo {
+                    write!(self.writer, "{},{}", tempo.bpm, tempo.confidence)?;
+                } else {
+                    write!(self.writer, ",")?;
+                }
+                let features = &spectrum.features;
+                write!(
+                    self.writer,
+                    ",{},{},{},{},{},{},{}",
+                    features.centroid,
+                    features.bandwidth,
+                    features.rolloff,
+                    features.flatness,
+                    features.flux,
+                    features.zero_crossing_rate,
+                    features.crest_factor
+                )?;
+                for v in spectrum.bands.iter().chain(&spectrum.bands_linear) {
+                    write!(self.writer, ",{}", v)?;
+                }
+                for channel in &spectrum.channels {
+                    write!(self.writer, ",{},{}", channel.meter.rms, channel.meter.peak)?;
+                    for v in channel.bands.iter().chain(&channel.bands_linear) {
+                        write!(self.writer, ",{}", v)?;
+                    }
+                }
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), anyhow::Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Exports every spectrum frame from `capture` until the source ends, `duration`
+/// seconds of audio have been analyzed, or Ctrl-C is pressed.
+pub fn run_headless(
+    capture: Capture,
+    analyzer: &AnalyzerConfig,
+    format: ExportFormat,
+    duration: Option<f64>,
+    output: Option<&Path>,
+) -> Result<(), anyhow::Error> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_handler = stop.clone();
+    ctrlc::set_handler(move || stop_handler.store(true, Ordering::Relaxed))
+        .expect("Error setting Ctrl-C handler");
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut exporter = Exporter::new(writer, format);
+
+    // Frame times start over when a looped file wraps, so the duration counts
+    // the audio behind the frames written instead
+    let mut frames = 0usize;
+    while !stop.load(Ordering::Relaxed) {
+        match capture.rx_spec.recv_timeout(Duration::from_millis(100)) {
+            Ok(spectrum) => {
+                let analyzed =
+                    (analyzer.fft_size + frames * analyzer.hop) as f64 / capture.sample_rate as f64;
+                if duration.is_some_and(|d| analyzed > d) {
+                    break;
+                }
+                exporter.write(&spectrum)?;
+                frames += 1;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let dropped = capture.dropped_blocks();
+    if dropped > 0 {
+        eprintln!(
+            "warning: {} blocks of audio were dropped because the analyzer fell behind",
+            dropped
+        );
+    }
+    exporter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use selara::analyzer::SpectrumAnalyzer;
+
+    #[test]
+    fn csv_has_columns_for_each_channel() {
+        let config = AnalyzerConfig {
+            bands: 4,
+            ..AnalyzerConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::with_channels(config, 48_000.0, 2).unwrap();
+        // Full scale on the left, silence on the right
+        let samples: Vec<f32> = (0..2048)
+            .flat_map(|i| [if i % 2 == 0 { 1.0 } else { -1.0 }, 0.0])
+            .collect();
+        analyzer.push(&samples);
+        let spectrum = analyzer.pop().unwrap();
+
+        let mut exporter = Exporter::new(Vec::new(), ExportFormat::Csv);
+        exporter.write(&spectrum).unwrap();
+        let text = String::from_utf8(exporter.writer).unwrap();
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(header.len(), row.len());
+        // The mix's 18 columns and 8 bands, then 2 meters and 8 bands for
+        // each of L, R, M and S
+        assert_eq!(header.len(), 26 + 4 * 10);
+
+        let column = |name: &str| row[header.iter().position(|&h| h == name).unwrap()];
+        assert_eq!(column("L_rms"), "1");
+        assert_eq!(column("L_peak"), "1");
+        assert_eq!(column("R_rms"), "0");
+        // Each channel's bands are named like the mix's
+        assert_eq!(header[28], format!("L_{}", header[18]));
+        assert_eq!(header[65], format!("S_{}", header[25]));
+    }
+}
@@ -0,0 +1,165 @@
+use serde::Serialize;
+
+/// Share of the spectral power below the rolloff frequency.
+pub const ROLLOFF_FRACTION: f32 = 0.85;
+
+/// Standard descriptors of one frame of the mix.
+///
+/// The spectral ones come from the frame's unweighted FFT, leaving out the DC
+/// bin; the zero-crossing rate and crest factor from its samples. All are zero
+/// in silence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Features {
+    /// Power-weighted mean frequency, in Hz.
+    pub centroid: f32,
+    /// Power-weighted standard deviation of frequency around the centroid,
+    /// in Hz.
+    pub bandwidth: f32,
+    /// Frequency below which [`ROLLOFF_FRACTION`] of the power lies, in Hz.
+    pub rolloff: f32,
+    /// Wiener entropy: the geometric over the arithmetic mean of the bin
+    /// powers, near 0 for a pure tone and 1 for white noise.
+    pub flatness: f32,
+    /// Mean rise in log magnitude over the bins since the previous frame, the
+    /// onset strength behind the tempo.
+    pub flux: f32,
+    /// Share of successive samples that change sign, 0 to 1.
+    pub zero_crossing_rate: f32,
+    /// Peak over RMS level of the samples, in dB; 3 dB for a sine.
+    pub crest_factor: f32,
+}
+
+impl Features {
+    /// Fills in the spectral descriptors from bin powers `bin_hz` apart.
+    pub(crate) fn measure_spectrum(&mut self, power: &[f32], bin_hz: f32) {
+        let bins = power.get(1..).unwrap_or_default();
+        let total: f32 = bins.iter().sum();
+        if bins.is_empty() || total <= 0.0 {
+            (self.centroid, self.bandwidth, self.rolloff, self.flatness) = (0.0, 0.0, 0.0, 0.0);
+            return;
+        }
+        let frequency = |k: usize| (k + 1) as f32 * bin_hz;
+
+        let centroid = bins
+            .iter()
+            .enumerate()
+            .map(|(k, p)| frequency(k) * p)
+            .sum::<f32>()
+            / total;
+        let variance = bins
+            .iter()
+            .enumerate()
+            .map(|(k, p)| (frequency(k) - centroid).powi(2) * p)
+            .sum::<f32>()
+            / total;
+
+        let mut below = 0.0;
+        let rolloff = bins
+            .iter()
+            .position(|p| {
+                below += p;
+                below >= ROLLOFF_FRACTION * total
+            })
+            .map_or(0.0, frequency);
+
+        // Bins of exactly zero power would make the geometric mean zero
+        let mean_log = bins
+            .iter()
+            .map(|&p| (p.max(f32::MIN_POSITIVE) as f64).ln())
+            .sum::<f64>()
+            / bins.len() as f64;
+        let mean = total as f64 / bins.len() as f64;
+
+        self.centroid = centroid;
+        self.bandwidth = variance.sqrt();
+        self.rolloff = rolloff;
+        self.flatness = (mean_log.exp() / mean).min(1.0) as f32;
+    }
+
+    /// Fills in the zero-crossing rate and crest factor of mono samples.
+    pub(crate) fn measure_samples(&mut self, samples: impl Iterator<Item = f32>) {
+        let (mut count, mut crossings, mut peak, mut sum_sq) = (0usize, 0usize, 0.0f32, 0.0f32);
+        let mut previous: Option<f32> = None;
+        for s in samples {
+            if previous.is_some_and(|p| (p < 0.0) != (s < 0.0)) {
+                crossings += 1;
+            }
+            previous = Some(s);
+            count += 1;
+            peak = peak.max(s.abs());
+            sum_sq += s * s;
+        }
+        let rms = (sum_sq / count.max(1) as f32).sqrt();
+        self.zero_crossing_rate = crossings as f32 / count.saturating_sub(1).max(1) as f32;
+        self.crest_factor = if rms > 0.0 {
+            20.0 * (peak / rms).log10()
+        } else {
+            0.0
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    #[test]
+    fn a_single_bin_has_its_frequency_and_no_spread() {
+        let mut power = vec![0.0; 513];
+        power[100] = 1.0;
+        let mut features = Features::default();
+        features.measure_spectrum(&power, 10.0);
+        assert_eq!(features.centroid, 1_000.0);
+        assert_eq!(features.bandwidth, 0.0);
+        assert_eq!(features.rolloff, 1_000.0);
+        assert!(features.flatness < 1e-6);
+    }
+
+    #[test]
+    fn two_equal_bins_centre_between_them() {
+        let mut power = vec![0.0; 513];
+        power[100] = 1.0;
+        power[300] = 1.0;
+        let mut features = Features::default();
+        features.measure_spectrum(&power, 10.0);
+        assert_eq!(features.centroid, 2_000.0);
+        assert_eq!(features.bandwidth, 1_000.0);
+        // Half the power lies at 1 kHz, so 85% is only reached at 3 kHz
+        assert_eq!(features.rolloff, 3_000.0);
+    }
+
+    #[test]
+    fn flat_power_is_perfectly_flat() {
+        let mut features = Features::default();
+        features.measure_spectrum(&[0.5; 513], 10.0);
+        assert!((features.flatness - 1.0).abs() < 1e-6);
+        // Bins 1 to 512, 10 Hz apart
+        assert!((features.centroid - 2_565.0).abs() < 0.1);
+        assert_eq!(features.rolloff, 4_360.0);
+    }
+
+    #[test]
+    fn silence_measures_zero() {
+        let mut features = Features::default();
+        features.measure_spectrum(&[0.0; 513], 10.0);
+        features.measure_samples(std::iter::repeat_n(0.0, 1024));
+        assert_eq!(features, Features::default());
+    }
+
+    #[test]
+    fn sines_cross_zero_twice_a_period() {
+        let mut features = Features::default();
+        let sine = (0..48_000).map(|i| (std::f32::consts::TAU * 1_000.0 * i as f32 / RATE).sin());
+        features.measure_samples(sine);
+        assert!((features.zero_crossing_rate - 2_000.0 / RATE).abs() < 1e-4);
+        assert!((features.crest_factor - 3.01).abs() < 0.01);
+
+        // A square wave has no crest
+        let square = (0..4800).map(|i| if (i / 24) % 2 == 0 { 0.5 } else { -0.5 });
+        features.measure_samples(square);
+        assert!(features.crest_factor.abs() < 1e-5);
+        assert!((features.zero_crossing_rate - 199.0 / 4799.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+use selara::features::Features;
+use selara::loudness::Loudness;
+
+/// Seconds of audio between history points.
+const INTERVAL_SECS: f64 = 0.1;
+/// Points kept, one minute at the interval above.
+pub const HISTORY_LEN: usize = 600;
+/// Frames of features kept, more than the widest sparkline.
+pub const FEATURE_HISTORY_LEN: usize = 256;
+
+/// Loudness readings at a fixed interval of audio time, oldest first.
+#[derive(Default)]
+pub struct LoudnessHistory {
+    points: VecDeque<Loudness>,
+    next_time: f64,
+}
+
+impl LoudnessHistory {
+    /// Records the reading of a spectrum frame ending at `time` seconds, if the
+    /// next point is due.
+    pub fn push(&mut self, time: f64, loudness: Loudness) {
+        // A seek back in a file restarts the pace from there
+        if time + INTERVAL_SECS < self.next_time {
+            self.next_time = time;
+        }
+        if time < self.next_time {
+            return;
+        }
+        if self.points.len() == HISTORY_LEN {
+            self.points.pop_front();
+        }
+        self.points.push_back(loudness);
+        self.next_time = time + INTERVAL_SECS;
+    }
+
+    /// Forgets the points, keeping the pace of new ones.
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Forgets everything for a new source, whose time starts from zero.
+    pub fn restart(&mut self) {
+        *self = LoudnessHistory::default();
+    }
+
+    pub fn points(&self) -> impl ExactSizeIterator<Item = &Loudness> {
+        self.points.iter()
+    }
+}
+
+/// The features of the latest spectrum frames, oldest first.
+#[derive(Default)]
+pub struct FeatureHistory {
+    frames: VecDeque<Features>,
+}
+
+impl FeatureHistory {
+    pub fn push(&mut self, features: Features) {
+        if self.frames.len() == FEATURE_HISTORY_LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(features);
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// The latest `len` frames at most, oldest first.
+    pub fn latest(&self, len: usize) -> impl Iterator<Item = &Features> {
+        self.frames
+            .iter()
+            .skip(self.frames.len().saturating_sub(len))
+    }
+}
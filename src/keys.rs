@@ -0,0 +1,368 @@
+use anyhow::bail;
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Something the user can trigger from the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    ToggleLinear,
+    SelectDevice,
+    TogglePause,
+    SeekBackward,
+    SeekForward,
+    ToggleLoop,
+    CycleWindow,
+    IncreaseFftSize,
+    DecreaseFftSize,
+    CycleOverlap,
+    CycleScale,
+    RaiseCeiling,
+    LowerCeiling,
+    RaiseFloor,
+    LowerFloor,
+    ToggleWaterfall,
+    CycleColormap,
+    IncreaseHistory,
+    DecreaseHistory,
+    ToggleOscilloscope,
+    ToggleTriggerEdge,
+    RaiseTrigger,
+    LowerTrigger,
+    IncreaseTimebase,
+    DecreaseTimebase,
+    CycleChannels,
+    ToggleSplit,
+    ToggleGoniometer,
+    ToggleCorrelation,
+    ToggleBalance,
+    ToggleLoudness,
+    ResetLoudness,
+    CycleLoudnessTarget,
+    ResetPeaks,
+    CycleBallistics,
+    ToggleMaxHold,
+    ToggleMinHold,
+    ResetHolds,
+    CycleAveraging,
+    ResetAverage,
+    CycleWeighting,
+    ToggleTuner,
+    ToggleNoteLabels,
+    ToggleChroma,
+    ToggleTempo,
+    ToggleFeatures,
+}
+
+impl Action {
+    /// Name of the action in the config file, e.g. `toggle_linear`.
+    fn config_name(self) -> String {
+        let mut name = String::new();
+        for c in format!("{:?}", self).chars() {
+            if c.is_ascii_uppercase() && !name.is_empty() {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "esc"],
+            Action::ToggleLinear => &["l"],
+            Action::SelectDevice => &["d"],
+            Action::TogglePause => &["space"],
+            Action::SeekBackward => &["left"],
+            Action::SeekForward => &["right"],
+            Action::ToggleLoop => &["r"],
+            Action::CycleWindow => &["w"],
+            Action::IncreaseFftSize => &["]"],
+            Action::DecreaseFftSize => &["["],
+            Action::CycleOverlap => &["o"],
+            Action::CycleScale => &["b"],
+            Action::RaiseCeiling => &["up"],
+            Action::LowerCeiling => &["down"],
+            Action::RaiseFloor => &["pageup"],
+            Action::LowerFloor => &["pagedown"],
+            Action::ToggleWaterfall => &["s"],
+            Action::CycleColormap => &["c"],
+            Action::IncreaseHistory => &["."],
+            Action::DecreaseHistory => &[","],
+            Action::ToggleOscilloscope => &["t"],
+            Action::ToggleTriggerEdge => &["e"],
+            Action::RaiseTrigger => &["0"],
+            Action::LowerTrigger => &["9"],
+            Action::IncreaseTimebase => &["="],
+            Action::DecreaseTimebase => &["-"],
+            Action::CycleChannels => &["m"],
+            Action::ToggleSplit => &["p"],
+            Action::ToggleGoniometer => &["g"],
+            Action::ToggleCorrelation => &["x"],
+            Action::ToggleBalance => &["a"],
+            Action::ToggleLoudness => &["u"],
+            Action::ResetLoudness => &["z"],
+            Action::CycleLoudnessTarget => &["n"],
+            Action::ResetPeaks => &["k"],
+            Action::CycleBallistics => &["v"],
+            Action::ToggleMaxHold => &["h"],
+            Action::ToggleMinHold => &["j"],
+            Action::ResetHolds => &["f"],
+            Action::CycleAveraging => &["i"],
+            Action::ResetAverage => &["y"],
+            Action::CycleWeighting => &[";"],
+            Action::ToggleTuner => &["/"],
+            Action::ToggleNoteLabels => &["'"],
+            Action::ToggleChroma => &["\\"],
+            Action::ToggleTempo => &["`"],
+            Action::ToggleFeatures => &["tab"],
+        }
+    }
+
+    const ALL: [Action; 47] = [
+        Action::Quit,
+        Action::ToggleLinear,
+        Action::SelectDevice,
+        Action::TogglePause,
+        Action::SeekBackward,
+        Action::SeekForward,
+        Action::ToggleLoop,
+        Action::CycleWindow,
+        Action::IncreaseFftSize,
+        Action::DecreaseFftSize,
+        Action::CycleOverlap,
+        Action::CycleScale,
+        Action::RaiseCeiling,
+        Action::LowerCeiling,
+        Action::RaiseFloor,
+        Action::LowerFloor,
+        Action::ToggleWaterfall,
+        Action::CycleColormap,
+        Action::IncreaseHistory,
+        Action::DecreaseHistory,
+        Action::ToggleOscilloscope,
+        Action::ToggleTriggerEdge,
+        Action::RaiseTrigger,
+        Action::LowerTrigger,
+        Action::IncreaseTimebase,
+        Action::DecreaseTimebase,
+        Action::CycleChannels,
+        Action::ToggleSplit,
+        Action::ToggleGoniometer,
+        Action::ToggleCorrelation,
+        Action::ToggleBalance,
+        Action::ToggleLoudness,
+        Action::ResetLoudness,
+        Action::CycleLoudnessTarget,
+        Action::ResetPeaks,
+        Action::CycleBallistics,
+        Action::ToggleMaxHold,
+        Action::ToggleMinHold,
+        Action::ResetHolds,
+        Action::CycleAveraging,
+        Action::ResetAverage,
+        Action::CycleWeighting,
+        Action::ToggleTuner,
+        Action::ToggleNoteLabels,
+        Action::ToggleChroma,
+        Action::ToggleTempo,
+        Action::ToggleFeatures,
+    ];
+}
+
+/// One key name or a list of them, as written in the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpec {
+    fn names(&self) -> &[String] {
+        match self {
+            KeySpec::One(name) => std::slice::from_ref(name),
+            KeySpec::Many(names) => names,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyMap {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap::new(&HashMap::new()).expect("default key bindings are valid")
+    }
+}
+
+impl KeyMap {
+    /// Builds the key map from the defaults, replacing the keys of any action
+    /// listed in `overrides`.
+    ///
+    /// Fails if a key ends up bound to two actions, including an override
+    /// taking a key another action keeps by default.
+    pub fn new(overrides: &HashMap<Action, KeySpec>) -> Result<KeyMap, anyhow::Error> {
+        let mut bindings: Vec<(KeyCode, Action)> = Vec::new();
+        for action in Action::ALL {
+            let names: Vec<&str> = match overrides.get(&action) {
+                Some(spec) => spec.names().iter().map(String::as_str).collect(),
+                None => action.default_keys().to_vec(),
+            };
+            for name in names {
+                let code = parse_key(name)?;
+                match bindings.iter().find(|(key, _)| *key == code) {
+                    Some((_, bound)) if *bound == action => {}
+                    Some((_, bound)) => bail!(
+                        "key \"{}\" is bound to both {} and {}",
+                        key_label(code),
+                        bound.config_name(),
+                        action.config_name()
+                    ),
+                    None => bindings.push((code, action)),
+                }
+            }
+        }
+        Ok(KeyMap { bindings })
+    }
+
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        // Letters are matched case-insensitively so Shift doesn't change the action
+        let code = match code {
+            KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+            other => other,
+        };
+        self.bindings
+            .iter()
+            .find(|(key, _)| *key == code)
+            .map(|(_, action)| *action)
+    }
+
+    /// Display label of the keys bound to `action`, e.g. `Q/ESC`.
+    pub fn label(&self, action: Action) -> String {
+        let labels: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(key, _)| key_label(*key))
+            .collect();
+        if labels.is_empty() {
+            "-".to_string()
+        } else {
+            labels.join("/")
+        }
+    }
+}
+
+fn parse_key(name: &str) -> Result<KeyCode, anyhow::Error> {
+    let lower = name.to_lowercase();
+    let code = match lower.as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        _ => {
+            if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                KeyCode::F(n)
+            } else {
+                let mut chars = lower.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => bail!("unknown key name \"{}\"", name),
+                }
+            }
+        }
+    };
+    Ok(code)
+}
+
+fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+        KeyCode::Esc => "ESC".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(toml: &str) -> HashMap<Action, KeySpec> {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(parse_key("esc").unwrap(), KeyCode::Esc);
+        assert_eq!(parse_key("Escape").unwrap(), KeyCode::Esc);
+        assert_eq!(parse_key("space").unwrap(), KeyCode::Char(' '));
+        assert_eq!(parse_key("PageUp").unwrap(), KeyCode::PageUp);
+        assert_eq!(parse_key("F5").unwrap(), KeyCode::F(5));
+        assert_eq!(parse_key("f").unwrap(), KeyCode::Char('f'));
+        assert_eq!(parse_key("Q").unwrap(), KeyCode::Char('q'));
+        assert_eq!(parse_key(";").unwrap(), KeyCode::Char(';'));
+        assert!(parse_key("qq").is_err());
+        assert!(parse_key("").is_err());
+    }
+
+    #[test]
+    fn defaults_bind_every_action() {
+        let keys = KeyMap::default();
+        for action in Action::ALL {
+            assert!(
+                keys.bindings.iter().any(|&(_, bound)| bound == action),
+                "{:?} is unbound",
+                action
+            );
+        }
+        assert_eq!(keys.action(KeyCode::Char('Q')), Some(Action::Quit));
+        assert_eq!(keys.action(KeyCode::Esc), Some(Action::Quit));
+        assert_eq!(keys.label(Action::Quit), "Q/ESC");
+    }
+
+    #[test]
+    fn overrides_replace_the_default_keys() {
+        let keys = KeyMap::new(&overrides(r#"quit = ["F10", "F10"]"#)).unwrap();
+        assert_eq!(keys.action(KeyCode::F(10)), Some(Action::Quit));
+        assert_eq!(keys.action(KeyCode::Char('q')), None);
+        assert_eq!(keys.label(Action::Quit), "F10");
+    }
+
+    #[test]
+    fn rejects_keys_bound_to_two_actions() {
+        // Space stays bound to toggle_pause by default
+        let error = KeyMap::new(&overrides(r#"toggle_linear = "space""#)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "key \"Space\" is bound to both toggle_linear and toggle_pause"
+        );
+
+        let keys = KeyMap::new(&overrides(
+            r#"toggle_linear = "space"
+            toggle_pause = "l""#,
+        ))
+        .unwrap();
+        assert_eq!(keys.action(KeyCode::Char(' ')), Some(Action::ToggleLinear));
+        assert_eq!(keys.action(KeyCode::Char('l')), Some(Action::TogglePause));
+    }
+}
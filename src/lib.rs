@@ -0,0 +1,20 @@
+//! Spectrum analysis behind the `selara` terminal visualizer.
+//!
+//! [`SpectrumAnalyzer`](analyzer::SpectrumAnalyzer) is synchronous: push mono
+//! or interleaved multi-channel samples in, pull [`Spectrum`](types::Spectrum)
+//! frames out. Threads, audio
+//! devices and drawing are left to the caller.
+
+pub mod analyzer;
+pub mod averaging;
+pub mod ballistics;
+pub mod bands;
+pub mod chroma;
+pub mod features;
+pub mod loudness;
+pub mod pitch;
+pub mod tempo;
+pub mod truepeak;
+pub mod types;
+pub mod weighting;
+pub mod window;
@@ -0,0 +1,555 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::weighting::Biquad;
+
+/// Programme loudness to aim for, shown as a reference in the meter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoudnessTarget {
+    /// -23 LUFS, EBU R128 broadcast
+    #[default]
+    Broadcast,
+    /// -16 LUFS, common for podcasts
+    Podcast,
+    /// -14 LUFS, common for music streaming services
+    Streaming,
+}
+
+impl LoudnessTarget {
+    pub const ALL: [LoudnessTarget; 3] = [
+        LoudnessTarget::Broadcast,
+        LoudnessTarget::Podcast,
+        LoudnessTarget::Streaming,
+    ];
+
+    /// The target after this one in [`LoudnessTarget::ALL`], wrapping around.
+    pub fn next(self) -> LoudnessTarget {
+        let i = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn lufs(self) -> f32 {
+        match self {
+            LoudnessTarget::Broadcast => -23.0,
+            LoudnessTarget::Podcast => -16.0,
+            LoudnessTarget::Streaming => -14.0,
+        }
+    }
+}
+
+impl fmt::Display for LoudnessTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LoudnessTarget::Broadcast => "broadcast",
+            LoudnessTarget::Podcast => "podcast",
+            LoudnessTarget::Streaming => "streaming",
+        };
+        write!(f, "{} LUFS {}", self.lufs(), name)
+    }
+}
+
+/// Loudness readings per ITU-R BS.1770-4 and EBU R128, in LUFS and LU.
+///
+/// Each reading is `None` until enough audio has been measured, or while
+/// everything measured is below the absolute gate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Loudness {
+    /// Over the last 400 ms.
+    pub momentary: Option<f32>,
+    /// Over the last 3 s.
+    pub short_term: Option<f32>,
+    /// Gated over everything since the start or the last reset.
+    pub integrated: Option<f32>,
+    /// Loudness range (EBU Tech 3342) of the short-term readings, in LU.
+    pub range: Option<f32>,
+}
+
+/// Length of a gating sub-block; 400 ms blocks overlap by 75%.
+const STEP_SECS: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+const ABSOLUTE_GATE: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+/// Gating blocks are kept in bins this many LU wide, from the absolute gate
+/// up to [`HISTOGRAM_MAX`] LUFS, as in libebur128.
+const HISTOGRAM_STEP: f64 = 0.1;
+const HISTOGRAM_MAX: f64 = 30.0;
+const HISTOGRAM_BINS: usize = ((HISTOGRAM_MAX - ABSOLUTE_GATE) / HISTOGRAM_STEP) as usize;
+
+/// Measures the loudness of interleaved audio.
+///
+/// ```
+/// use selara::loudness::LoudnessMeter;
+///
+/// // A 1 kHz sine at -23 dBFS in both channels reads -23 LUFS
+/// let amplitude = 10f32.powf(-23.0 / 20.0);
+/// let samples: Vec<f32> = (0..48_000 * 4)
+///     .flat_map(|i| {
+///         let s = amplitude * (std::f32::consts::TAU * 1000.0 * i as f32 / 48_000.0).sin();
+///         [s, s]
+///     })
+///     .collect();
+/// let mut meter = LoudnessMeter::new(48_000.0, 2);
+/// meter.push(&samples);
+/// let integrated = meter.loudness().integrated.unwrap();
+/// assert!((integrated + 23.0).abs() < 0.1);
+/// ```
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<KWeighting>,
+    step_len: usize,
+    // weighted sum of squares in the current sub-block
+    step_energy: f64,
+    step_fill: usize,
+    // mean square of the last sub-blocks, newest last
+    recent: VecDeque<f64>,
+    // every 400 ms and 3 s block so far, binned by loudness for gating
+    blocks: BlockHistogram,
+    short_terms: BlockHistogram,
+    // gated readings, updated once per sub-block
+    integrated: Option<f32>,
+    range: Option<f32>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32, channels: usize) -> LoudnessMeter {
+        let sample_rate = sample_rate as f64;
+        LoudnessMeter {
+            channels,
+            weights: (0..channels).map(|c| channel_weight(c, channels)).collect(),
+            filters: (0..channels)
+                .map(|_| KWeighting::new(sample_rate))
+                .collect(),
+            step_len: ((STEP_SECS * sample_rate).round() as usize).max(1),
+            step_energy: 0.0,
+            step_fill: 0,
+            recent: VecDeque::with_capacity(SHORT_TERM_STEPS),
+            blocks: BlockHistogram::default(),
+            short_terms: BlockHistogram::default(),
+            integrated: None,
+            range: None,
+        }
+    }
+
+    /// Appends samples, interleaved by channel in whole frames.
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for ((&x, filter), weight) in frame.iter().zip(&mut self.filters).zip(&self.weights) {
+                let y = filter.process(x as f64);
+                self.step_energy += weight * y * y;
+            }
+            self.step_fill += 1;
+            if self.step_fill == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    fn finish_step(&mut self) {
+        if self.recent.len() == SHORT_TERM_STEPS {
+            self.recent.pop_front();
+        }
+        self.recent
+            .push_back(self.step_energy / self.step_len as f64);
+        self.step_energy = 0.0;
+        self.step_fill = 0;
+
+        if let Some(power) = self.mean_of_last(MOMENTARY_STEPS) {
+            self.blocks.push(power);
+            self.integrated = self
+                .blocks
+                .gated_mean(INTEGRATED_RELATIVE_GATE)
+                .map(|power| lufs(power) as f32);
+        }
+        if let Some(power) = self.mean_of_last(SHORT_TERM_STEPS) {
+            self.short_terms.push(power);
+            self.range = self.short_terms.loudness_range();
+        }
+    }
+
+    fn mean_of_last(&self, steps: usize) -> Option<f64> {
+        if self.recent.len() < steps {
+            return None;
+        }
+        Some(self.recent.iter().rev().take(steps).sum::<f64>() / steps as f64)
+    }
+
+    pub fn loudness(&self) -> Loudness {
+        let reading = |steps| {
+            self.mean_of_last(steps)
+                .map(lufs)
+                .filter(|&l| l > ABSOLUTE_GATE)
+                .map(|l| l as f32)
+        };
+        Loudness {
+            momentary: reading(MOMENTARY_STEPS),
+            short_term: reading(SHORT_TERM_STEPS),
+            integrated: self.integrated,
+            range: self.range,
+        }
+    }
+
+    /// Starts a new measurement, keeping the filter state.
+    pub fn reset(&mut self) {
+        self.step_energy = 0.0;
+        self.step_fill = 0;
+        self.recent.clear();
+        self.blocks.clear();
+        self.short_terms.clear();
+        self.integrated = None;
+        self.range = None;
+    }
+}
+
+/// Loudness of a K-weighted mean square, BS.1770 eq. 2.
+fn lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(f64::MIN_POSITIVE).log10()
+}
+
+/// BS.1770 channel weight: surround channels of a 5.1 layout count 1.41 and
+/// the LFE channel is left out.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (5, 3 | 4) | (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Gating blocks above the absolute gate, counted in bins of loudness along
+/// with their summed power, so a measurement of any length takes the same
+/// memory and time per block.
+struct BlockHistogram {
+    counts: Vec<u64>,
+    powers: Vec<f64>,
+}
+
+impl Default for BlockHistogram {
+    fn default() -> Self {
+        BlockHistogram {
+            counts: vec![0; HISTOGRAM_BINS],
+            powers: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+}
+
+impl BlockHistogram {
+    /// The bin of a loudness in LUFS, clamped to the histogram.
+    fn bin(level: f64) -> usize {
+        (((level - ABSOLUTE_GATE) / HISTOGRAM_STEP).max(0.0) as usize).min(HISTOGRAM_BINS - 1)
+    }
+
+    /// Adds a block's mean square, dropping it if it is below the absolute gate.
+    fn push(&mut self, power: f64) {
+        let level = lufs(power);
+        if level <= ABSOLUTE_GATE {
+            return;
+        }
+        let bin = BlockHistogram::bin(level);
+        self.counts[bin] += 1;
+        self.powers[bin] += power;
+    }
+
+    /// Mean power of the blocks in bin `first` and above.
+    fn mean_from(&self, first: usize) -> Option<f64> {
+        let n: u64 = self.counts[first..].iter().sum();
+        let sum: f64 = self.powers[first..].iter().sum();
+        (n > 0).then(|| sum / n as f64)
+    }
+
+    /// Mean power of the blocks above the absolute gate and the relative gate
+    /// `relative` LU below their own mean.
+    fn gated_mean(&self, relative: f64) -> Option<f64> {
+        let absolute = self.mean_from(0)?;
+        self.mean_from(BlockHistogram::bin(lufs(absolute) + relative))
+    }
+
+    /// Spread between the 10th and 95th percentile of the gated short-term
+    /// loudness, EBU Tech 3342, to the width of a bin.
+    fn loudness_range(&self) -> Option<f32> {
+        let absolute = self.mean_from(0)?;
+        let first = BlockHistogram::bin(lufs(absolute) + RANGE_RELATIVE_GATE);
+        let counts = &self.counts[first..];
+        let n: u64 = counts.iter().sum();
+        if n == 0 {
+            return None;
+        }
+        // Center of the bin holding the block of this rank among the gated ones
+        let percentile = |p: f64| {
+            let rank = ((n - 1) as f64 * p).round() as u64;
+            let mut below = 0;
+            let bin = counts
+                .iter()
+                .position(|&count| {
+                    below += count;
+                    below > rank
+                })
+                .unwrap_or(counts.len() - 1);
+            ABSOLUTE_GATE + (first + bin) as f64 * HISTOGRAM_STEP + HISTOGRAM_STEP / 2.0
+        };
+        Some((percentile(0.95) - percentile(0.10)) as f32)
+    }
+
+    fn clear(&mut self) {
+        self.counts.fill(0);
+        self.powers.fill(0.0);
+    }
+}
+
+/// The BS.1770 K-weighting: a high shelf for the head's acoustic effect, then
+/// the RLB high-pass. Coefficients are derived for any sample rate the same
+/// way libebur128 does, matching the tabulated 48 kHz values.
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> KWeighting {
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+        KWeighting { shelf, high_pass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A 1 kHz sine in both channels at `dbfs`, for `secs` seconds.
+    fn stereo_sine(dbfs: f32, secs: f32) -> Vec<f32> {
+        let amplitude = 10f32.powf(dbfs / 20.0);
+        (0..(secs * RATE) as usize)
+            .flat_map(|i| {
+                let t = i as f64 / RATE as f64;
+                let s = amplitude * (2.0 * PI * 1000.0 * t).sin() as f32;
+                [s, s]
+            })
+            .collect()
+    }
+
+    /// Measures consecutive stereo sine segments of (dBFS, seconds).
+    fn measure(segments: &[(f32, f32)]) -> Loudness {
+        let mut meter = LoudnessMeter::new(RATE, 2);
+        for &(dbfs, secs) in segments {
+            meter.push(&stereo_sine(dbfs, secs));
+        }
+        meter.loudness()
+    }
+
+    fn assert_near(actual: Option<f32>, expected: f32, tolerance: f32) {
+        let actual = actual.expect("no reading");
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn k_weighting_matches_the_48k_coefficients() {
+        // BS.1770-4 table 1 and table 2
+        let k = KWeighting::new(48_000.0);
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-8;
+        assert!(close(k.shelf.b[0], 1.53512485958697));
+        assert!(close(k.shelf.b[1], -2.69169618940638));
+        assert!(close(k.shelf.b[2], 1.19839281085285));
+        assert!(close(k.shelf.a[0], -1.69065929318241));
+        assert!(close(k.shelf.a[1], 0.73248077421585));
+        assert!(close(k.high_pass.a[0], -1.99004745483398));
+        assert!(close(k.high_pass.a[1], 0.99007225036621));
+    }
+
+    // EBU Tech 3341 minimum requirements, cases 1 to 5
+    #[test]
+    fn tech_3341_case_1() {
+        let loudness = measure(&[(-23.0, 20.0)]);
+        assert_near(loudness.momentary, -23.0, 0.1);
+        assert_near(loudness.short_term, -23.0, 0.1);
+        assert_near(loudness.integrated, -23.0, 0.1);
+    }
+
+    #[test]
+    fn tech_3341_case_2() {
+        let loudness = measure(&[(-33.0, 20.0)]);
+        assert_near(loudness.momentary, -33.0, 0.1);
+        assert_near(loudness.short_term, -33.0, 0.1);
+        assert_near(loudness.integrated, -33.0, 0.1);
+    }
+
+    #[test]
+    fn tech_3341_case_3() {
+        let loudness = measure(&[(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)]);
+        assert_near(loudness.integrated, -23.0, 0.1);
+    }
+
+    #[test]
+    fn tech_3341_case_4() {
+        let loudness = measure(&[
+            (-72.0, 10.0),
+            (-36.0, 10.0),
+            (-23.0, 60.0),
+            (-36.0, 10.0),
+            (-72.0, 10.0),
+        ]);
+        assert_near(loudness.integrated, -23.0, 0.1);
+    }
+
+    #[test]
+    fn tech_3341_case_5() {
+        let loudness = measure(&[(-26.0, 20.0), (-20.0, 20.1), (-26.0, 20.0)]);
+        assert_near(loudness.integrated, -23.0, 0.1);
+    }
+
+    // EBU Tech 3342 loudness range, cases 1 to 4
+    #[test]
+    fn tech_3342_case_1() {
+        let loudness = measure(&[(-20.0, 20.0), (-30.0, 20.0)]);
+        assert_near(loudness.range, 10.0, 1.0);
+    }
+
+    #[test]
+    fn tech_3342_case_2() {
+        let loudness = measure(&[(-20.0, 20.0), (-15.0, 20.0)]);
+        assert_near(loudness.range, 5.0, 1.0);
+    }
+
+    #[test]
+    fn tech_3342_case_3() {
+        let loudness = measure(&[(-40.0, 20.0), (-20.0, 20.0)]);
+        assert_near(loudness.range, 20.0, 1.0);
+    }
+
+    #[test]
+    fn tech_3342_case_4() {
+        let loudness = measure(&[
+            (-50.0, 20.0),
+            (-35.0, 20.0),
+            (-20.0, 20.0),
+            (-35.0, 20.0),
+            (-50.0, 20.0),
+        ]);
+        assert_near(loudness.range, 15.0, 1.0);
+    }
+
+    #[test]
+    fn readings_wait_for_their_window() {
+        let mut meter = LoudnessMeter::new(RATE, 2);
+        meter.push(&stereo_sine(-23.0, 0.35));
+        assert_eq!(meter.loudness(), Loudness::default());
+        meter.push(&stereo_sine(-23.0, 1.0));
+        let loudness = meter.loudness();
+        assert!(loudness.momentary.is_some());
+        assert!(loudness.integrated.is_some());
+        assert!(loudness.short_term.is_none());
+    }
+
+    #[test]
+    fn silence_is_gated() {
+        let mut meter = LoudnessMeter::new(RATE, 2);
+        meter.push(&vec![0.0; 2 * 5 * RATE as usize]);
+        assert_eq!(meter.loudness(), Loudness::default());
+    }
+
+    #[test]
+    fn reset_starts_a_new_measurement() {
+        let mut meter = LoudnessMeter::new(RATE, 2);
+        meter.push(&stereo_sine(-33.0, 10.0));
+        meter.reset();
+        meter.push(&stereo_sine(-23.0, 10.0));
+        assert_near(meter.loudness().integrated, -23.0, 0.1);
+    }
+
+    #[test]
+    fn other_sample_rates_read_the_same() {
+        let amplitude = 10f32.powf(-23.0 / 20.0);
+        let samples: Vec<f32> = (0..44_100 * 5)
+            .flat_map(|i| {
+                let s = amplitude * (2.0 * PI * 1000.0 * i as f64 / 44_100.0).sin() as f32;
+                [s, s]
+            })
+            .collect();
+        let mut meter = LoudnessMeter::new(44_100.0, 2);
+        meter.push(&samples);
+        assert_near(meter.loudness().integrated, -23.0, 0.1);
+    }
+
+    #[test]
+    fn histogram_matches_exact_gating() {
+        // Levels from -60 to -10 LUFS, unevenly between bins
+        let levels: Vec<f64> = (0..500).map(|i| -60.0 + i as f64 * 0.1037).collect();
+        let power = |level: f64| 10f64.powf((level + 0.691) / 10.0);
+        let mut histogram = BlockHistogram::default();
+        for &level in &levels {
+            histogram.push(power(level));
+        }
+
+        let mean = |gate: f64| {
+            let gated: Vec<f64> = levels
+                .iter()
+                .filter(|&&l| l > gate)
+                .map(|&l| power(l))
+                .collect();
+            gated.iter().sum::<f64>() / gated.len() as f64
+        };
+        let integrated = lufs(mean(lufs(mean(ABSOLUTE_GATE)) + INTEGRATED_RELATIVE_GATE));
+        let binned = lufs(histogram.gated_mean(INTEGRATED_RELATIVE_GATE).unwrap());
+        assert!(
+            (binned - integrated).abs() < 0.1,
+            "{} vs {}",
+            binned,
+            integrated
+        );
+
+        let gate = lufs(mean(ABSOLUTE_GATE)) + RANGE_RELATIVE_GATE;
+        let gated: Vec<f64> = levels.iter().copied().filter(|&l| l > gate).collect();
+        let percentile = |p: f64| gated[((gated.len() - 1) as f64 * p).round() as usize];
+        let range = percentile(0.95) - percentile(0.10);
+        let binned = histogram.loudness_range().unwrap() as f64;
+        assert!((binned - range).abs() < 0.2, "{} vs {}", binned, range);
+    }
+
+    #[test]
+    fn lfe_is_not_counted() {
+        assert_eq!(channel_weight(3, 6), 0.0);
+        assert_eq!(channel_weight(4, 6), 1.41);
+        assert_eq!(channel_weight(3, 5), 1.41);
+        assert_eq!(channel_weight(1, 2), 1.0);
+    }
+}
@@ -1,16 +1,78 @@
-use cpal::traits::{DeviceTrait, HostTrait};
-use crossbeam_channel as chan;
-use std::time::{Duration, Instant};
+use clap::Parser;
+use std::time::Duration;
 
 mod audio;
-mod types;
+mod cli;
+mod config;
+mod export;
+mod history;
+mod keys;
+mod peaks;
+mod playback;
+mod scope;
+mod stereo;
 mod ui;
+mod waterfall;
 
-use audio::{create_audio_stream, start_spectrum_analyzer};
-use types::{Meter, Spectrum};
+use audio::{Buffering, Capture, list_devices, open_device};
+use cli::Cli;
+use config::Settings;
+use export::run_headless;
 use ui::{App, draw_ui, handle_events, init_terminal, restore_terminal};
 
 fn main() -> Result<(), anyhow::Error> {
+    let cli = Cli::parse();
+
+    if cli.list_devices {
+        list_devices();
+        return Ok(());
+    }
+
+    let settings = Settings::resolve(&cli)?;
+
+    let host = cpal::default_host();
+
+    if cli.headless {
+        let capture = match &cli.file {
+            Some(path) => Capture::open_file(
+                path,
+                &settings.analyzer,
+                cli.playback_options(),
+                Buffering::Lossless,
+            )?,
+            None => {
+                let (device, cfg) = open_device(&host, settings.device.as_deref())?;
+                Capture::start(&device, &cfg, &settings.analyzer, Buffering::Lossless)?
+            }
+        };
+        return run_headless(
+            capture,
+            &settings.analyzer,
+            cli.format,
+            cli.duration,
+            cli.output.as_deref(),
+        );
+    }
+
+    // A file or explicitly requested device must open; without one, fall back to
+    // the device picker if the system has no usable default output.
+    let mut capture = if let Some(path) = &cli.file {
+        Some(Capture::open_file(
+            path,
+            &settings.analyzer,
+            cli.playback_options(),
+            Buffering::Realtime,
+        )?)
+    } else {
+        match open_device(&host, settings.device.as_deref()).and_then(|(device, cfg)| {
+            Capture::start(&device, &cfg, &settings.analyzer, Buffering::Realtime)
+        }) {
+            Ok(capture) => Some(capture),
+            Err(e) if settings.device.is_some() => return Err(e),
+            Err(_) => None,
+        }
+    };
+
     let mut terminal = init_terminal()?;
 
     let cleanup = || {
@@ -23,61 +85,36 @@ fn main() -> Result<(), anyhow::Error> {
     })
     .expect("Error setting Ctrl-C handler");
 
-    let host = cpal::default_host();
-
-    let default_out = host
-        .default_output_device()
-        .expect("Failed to get default output device");
-
-    let device_name = default_out
-        .name()
-        .unwrap_or_else(|_| "Unknown Device".to_string());
-
-    let output_cfg = match default_out.default_output_config() {
-        Ok(f) => f,
-        Err(e) => {
-            panic!("Error getting default output stream: {:?}", e)
+    let mut app = match &capture {
+        Some(capture) => {
+            let mut app = App::new(capture.sample_rate, capture.device_name.clone(), &settings);
+            app.playback = capture.playback.as_ref().map(|playback| playback.control());
+            app
+        }
+        None => {
+            let mut app = App::new(0, "No device".to_string(), &settings);
+            app.open_device_picker();
+            app
         }
     };
-
-    let cfg = output_cfg.config();
-    let channels = cfg.channels as usize;
-    let (tx_meter, rx) = chan::bounded::<Meter>(32);
-    let (tx_spec, rx_spec) = chan::bounded::<Spectrum>(8);
-    let sample_rate = cfg.sample_rate.0 as f32;
-
-    let (tx_frames, rx_frames) = chan::bounded::<Vec<f32>>(16);
-
-    // Start spectrum analyzer thread
-    start_spectrum_analyzer(rx_frames, tx_spec, sample_rate);
-
-    // Create audio stream
-    let _stream = create_audio_stream(
-        &default_out,
-        output_cfg.sample_format(),
-        &cfg,
-        channels,
-        tx_meter.clone(),
-        tx_frames.clone(),
-    )?;
-
-    let mut app = App::new(sample_rate as u32, device_name);
-    let frame_duration = Duration::from_millis(16); // ~60 FPS
-    let mut last_time = Instant::now();
+    let frame_duration = Duration::from_secs_f64(1.0 / settings.fps as f64);
 
     loop {
-        let now = Instant::now();
-        let dt = now.duration_since(last_time).as_secs_f32();
-        last_time = now;
-
-        app.decay_peak(dt);
-
-        if let Ok(spec) = rx_spec.try_recv() {
-            app.update_spectrum(spec);
-        }
-
-        if let Ok(meter) = rx.try_recv() {
-            app.update_rms(meter.rms);
+        if let Some(capture) = &capture {
+            while let Ok(spec) = capture.rx_spec.try_recv() {
+                app.update_spectrum(spec);
+            }
+
+            while let Ok(block) = capture.rx_scope.try_recv() {
+                app.scope.push(&block);
+                app.stereo.push(&block);
+                app.update_meters(&block);
+            }
+
+            while let Ok(levels) = capture.rx_meter.try_recv() {
+                app.update_levels(&levels);
+            }
+            app.dropped_blocks = capture.dropped_blocks();
         }
 
         handle_events(&mut app)?;
@@ -86,6 +123,33 @@ fn main() -> Result<(), anyhow::Error> {
             break;
         }
 
+        for command in app.analyzer_commands.drain(..) {
+            if let Some(capture) = &capture {
+                capture.send(command);
+            }
+        }
+
+        if let Some(entry) = app.requested_device.take() {
+            // Tear down the old stream before opening the new one; some backends
+            // only allow one stream per device.
+            capture = None;
+            match entry.open().and_then(|(device, cfg)| {
+                Capture::start(&device, &cfg, &app.analyzer, Buffering::Realtime)
+            }) {
+                Ok(new_capture) => {
+                    app.set_device(new_capture.device_name.clone(), new_capture.sample_rate);
+                    app.picker = None;
+                    capture = Some(new_capture);
+                }
+                Err(e) => {
+                    app.set_device("No device".to_string(), 0);
+                    if let Some(picker) = app.picker.as_mut() {
+                        picker.error = Some(format!("Failed to open {}: {}", entry.name, e));
+                    }
+                }
+            }
+        }
+
         terminal.draw(|f| draw_ui(f, &app))?;
 
         std::thread::sleep(frame_duration);
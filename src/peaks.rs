@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use selara::types::{Channel, Spectrum};
+
+/// Seconds a cap stays at a peak before it starts to fall.
+pub const DEFAULT_HOLD_SECS: f32 = 1.0;
+pub const MAX_HOLD_SECS: f32 = 10.0;
+/// Fall rate of the caps after the hold time, in dB per second.
+pub const DEFAULT_FALL_DB_PER_SEC: f32 = 20.0;
+pub const MAX_FALL_DB_PER_SEC: f32 = 200.0;
+/// Longest gap between spectra counted towards the hold and fall, so a pause
+/// or a seek does not drop every cap at once.
+const MAX_STEP_SECS: f64 = 0.5;
+
+/// Peak caps and max- and min-hold traces of one spectrum's bands, in dBFS.
+pub struct BandPeaks {
+    pub channel: Channel,
+    /// The latest band levels.
+    levels: Vec<f32>,
+    caps: Vec<f32>,
+    /// Seconds each cap has left at its peak.
+    hold_left: Vec<f32>,
+    max: Vec<f32>,
+    min: Vec<f32>,
+}
+
+impl BandPeaks {
+    fn new(channel: Channel, bands: &[f32]) -> BandPeaks {
+        BandPeaks {
+            channel,
+            levels: bands.to_vec(),
+            caps: bands.to_vec(),
+            hold_left: vec![0.0; bands.len()],
+            max: bands.to_vec(),
+            min: bands.to_vec(),
+        }
+    }
+
+    fn push(&mut self, bands: &[f32], secs: f32, hold_secs: f32, fall_db_per_sec: f32) {
+        for (i, &level) in bands.iter().enumerate() {
+            if level >= self.caps[i] {
+                self.caps[i] = level;
+                self.hold_left[i] = hold_secs;
+            } else {
+                // Any of the step past the hold time counts towards the fall
+                let falling = (secs - self.hold_left[i]).max(0.0);
+                self.hold_left[i] = (self.hold_left[i] - secs).max(0.0);
+                self.caps[i] = (self.caps[i] - fall_db_per_sec * falling).max(level);
+            }
+            self.max[i] = self.max[i].max(level);
+            self.min[i] = self.min[i].min(level);
+        }
+        self.levels.copy_from_slice(bands);
+    }
+
+    fn reset_traces(&mut self) {
+        self.max.clone_from(&self.levels);
+        self.min.clone_from(&self.levels);
+    }
+
+    pub fn caps(&self) -> &[f32] {
+        &self.caps
+    }
+
+    /// The loudest level of each band since the traces were reset.
+    pub fn max(&self) -> &[f32] {
+        &self.max
+    }
+
+    /// The quietest level of each band since the traces were reset.
+    pub fn min(&self) -> &[f32] {
+        &self.min
+    }
+}
+
+/// Peaks of the mix spectrum and of each channel spectrum, following the
+/// analyzer's bands and restarting whenever they change.
+pub struct SpectrumPeaks {
+    hold_secs: f32,
+    fall_db_per_sec: f32,
+    peaks: Vec<BandPeaks>,
+    frequencies: Option<Arc<[f32]>>,
+    last_time: f64,
+}
+
+impl SpectrumPeaks {
+    pub fn new(hold_secs: f32, fall_db_per_sec: f32) -> SpectrumPeaks {
+        SpectrumPeaks {
+            hold_secs,
+            fall_db_per_sec,
+            peaks: Vec::new(),
+            frequencies: None,
+            last_time: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, spectrum: &Spectrum) {
+        let mix = std::iter::once((Channel::Mix, &spectrum.bands));
+        let channels = spectrum.channels.iter().map(|c| (c.channel, &c.bands));
+        let unchanged = self.frequencies.as_deref() == Some(&spectrum.frequencies[..])
+            && self.peaks.len() == spectrum.channels.len() + 1
+            && self
+                .peaks
+                .iter()
+                .zip(mix.clone().chain(channels.clone()))
+                .all(|(peaks, (channel, _))| peaks.channel == channel);
+        if !unchanged {
+            self.peaks = mix
+                .chain(channels)
+                .map(|(channel, bands)| BandPeaks::new(channel, bands))
+                .collect();
+            self.frequencies = Some(spectrum.frequencies.clone());
+            self.last_time = spectrum.time;
+            return;
+        }
+
+        // Time runs backwards after a seek or when a file loops
+        let secs = (spectrum.time - self.last_time).clamp(0.0, MAX_STEP_SECS) as f32;
+        self.last_time = spectrum.time;
+        for (peaks, (_, bands)) in self.peaks.iter_mut().zip(mix.chain(channels)) {
+            peaks.push(bands, secs, self.hold_secs, self.fall_db_per_sec);
+        }
+    }
+
+    pub fn get(&self, channel: Channel) -> Option<&BandPeaks> {
+        self.peaks.iter().find(|peaks| peaks.channel == channel)
+    }
+
+    /// Starts the max- and min-hold traces over from the latest levels.
+    pub fn reset_traces(&mut self) {
+        for peaks in &mut self.peaks {
+            peaks.reset_traces();
+        }
+    }
+
+    /// Forgets everything for a new source.
+    pub fn clear(&mut self) {
+        self.peaks.clear();
+        self.frequencies = None;
+    }
+}
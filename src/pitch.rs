@@ -0,0 +1,307 @@
+use realfft::num_complex::Complex32;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+/// Pitches the detector looks for, in Hz: a little below a bass guitar's low
+/// B up to the top of a piano.
+pub const MIN_PITCH_HZ: f32 = 30.0;
+pub const MAX_PITCH_HZ: f32 = 4_200.0;
+/// Tuning reference for note names.
+pub const DEFAULT_A4_HZ: f32 = 440.0;
+pub const MIN_A4_HZ: f32 = 400.0;
+pub const MAX_A4_HZ: f32 = 480.0;
+/// Share of the highest NSDF peak the chosen period must reach, as in the
+/// McLeod pitch method.
+const PEAK_THRESHOLD: f32 = 0.9;
+/// Least clarity reported as a pitch; noise stays well below it.
+const MIN_CLARITY: f32 = 0.75;
+/// RMS below which the input counts as silence.
+const MIN_RMS: f32 = 0.001;
+
+/// A detected fundamental frequency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pitch {
+    pub frequency: f32,
+    /// Height of the normalized square difference peak, 0 to 1; higher for
+    /// more periodic input.
+    pub clarity: f32,
+}
+
+/// Estimates the fundamental of mono input with the McLeod pitch method: the
+/// normalized square difference function (NSDF) of the latest samples, its
+/// first peak close to the highest, refined by parabolic interpolation.
+///
+/// The window is long enough for two periods of [`MIN_PITCH_HZ`]. The
+/// autocorrelation behind the NSDF comes from an FFT, so each estimate costs
+/// about as much as one spectrum frame.
+///
+/// ```
+/// use selara::pitch::PitchDetector;
+///
+/// let mut detector = PitchDetector::new(48_000.0);
+/// detector.push((0..8192).map(|i| (std::f32::consts::TAU * 110.0 * i as f32 / 48_000.0).sin()));
+/// let pitch = detector.detect().unwrap();
+/// assert!((pitch.frequency - 110.0).abs() < 0.1);
+/// ```
+pub struct PitchDetector {
+    sample_rate: f32,
+    window: usize,
+    /// The latest samples, oldest first.
+    history: VecDeque<f32>,
+    r2c: Arc<dyn RealToComplex<f32>>,
+    c2r: Arc<dyn ComplexToReal<f32>>,
+    input: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    /// Autocorrelation, scaled by the FFT length.
+    correlation: Vec<f32>,
+    nsdf: Vec<f32>,
+}
+
+impl PitchDetector {
+    pub fn new(sample_rate: f32) -> PitchDetector {
+        let window = ((2.0 * sample_rate / MIN_PITCH_HZ).ceil() as usize).next_power_of_two();
+        // Zero padding to twice the window keeps the correlation from wrapping
+        let mut planner = RealFftPlanner::<f32>::new();
+        let r2c = planner.plan_fft_forward(2 * window);
+        let c2r = planner.plan_fft_inverse(2 * window);
+        PitchDetector {
+            sample_rate,
+            window,
+            history: VecDeque::with_capacity(window),
+            input: r2c.make_input_vec(),
+            spectrum: r2c.make_output_vec(),
+            correlation: c2r.make_output_vec(),
+            nsdf: Vec::with_capacity(window / 2),
+            r2c,
+            c2r,
+        }
+    }
+
+    /// Appends mono samples, keeping the latest window.
+    pub fn push(&mut self, samples: impl IntoIterator<Item = f32>) {
+        self.history.extend(samples);
+        let excess = self.history.len().saturating_sub(self.window);
+        self.history.drain(..excess);
+    }
+
+    /// The pitch of the latest window, or `None` until a window has arrived
+    /// and while the input is silent or not periodic enough.
+    pub fn detect(&mut self) -> Option<Pitch> {
+        let n = self.window;
+        if self.history.len() < n {
+            return None;
+        }
+        let energy: f32 = self.history.iter().map(|x| x * x).sum();
+        if energy < MIN_RMS * MIN_RMS * n as f32 {
+            return None;
+        }
+
+        self.input.fill(0.0);
+        for (x, &s) in self.input.iter_mut().zip(&self.history) {
+            *x = s;
+        }
+        self.r2c
+            .process(&mut self.input, &mut self.spectrum)
+            .expect("FFT failed");
+        for c in &mut self.spectrum {
+            *c = Complex32::new(c.norm_sqr(), 0.0);
+        }
+        self.c2r
+            .process(&mut self.spectrum, &mut self.correlation)
+            .expect("FFT failed");
+
+        // m(τ) = Σ x[j]² + x[j+τ]² over the overlap, shrinking one sample
+        // from each end per lag
+        let scale = (2 * n) as f32;
+        let mut m = 2.0 * energy;
+        self.nsdf.clear();
+        for tau in 0..n / 2 {
+            self.nsdf.push(if m > 0.0 {
+                2.0 * self.correlation[tau] / scale / m
+            } else {
+                0.0
+            });
+            let (head, tail) = (self.history[tau], self.history[n - 1 - tau]);
+            m -= head * head + tail * tail;
+        }
+
+        let peaks = key_maxima(&self.nsdf);
+        let highest = peaks.iter().map(|&(_, v)| v).fold(0.0, f32::max);
+        let &(tau, _) = peaks
+            .iter()
+            .find(|&&(_, v)| v >= PEAK_THRESHOLD * highest)?;
+        let (period, clarity) = interpolate_peak(&self.nsdf, tau);
+        let frequency = self.sample_rate / period;
+        (clarity >= MIN_CLARITY && (MIN_PITCH_HZ..=MAX_PITCH_HZ).contains(&frequency))
+            .then_some(Pitch { frequency, clarity })
+    }
+}
+
+/// The highest point of each positive stretch of the NSDF after its first
+/// negative zero crossing, as (lag, value).
+fn key_maxima(nsdf: &[f32]) -> Vec<(usize, f32)> {
+    let mut peaks = Vec::new();
+    let Some(start) = nsdf.iter().position(|&v| v < 0.0) else {
+        return peaks;
+    };
+    let mut current: Option<(usize, f32)> = None;
+    for (tau, &v) in nsdf.iter().enumerate().skip(start) {
+        if v > 0.0 {
+            if current.is_none_or(|(_, best)| v > best) {
+                current = Some((tau, v));
+            }
+        } else if let Some(peak) = current.take() {
+            peaks.push(peak);
+        }
+    }
+    // A stretch still rising at the end has no peak yet
+    peaks
+}
+
+/// Lag and value of the vertex of the parabola through a peak and its neighbours.
+fn interpolate_peak(nsdf: &[f32], tau: usize) -> (f32, f32) {
+    if tau == 0 || tau + 1 >= nsdf.len() {
+        return (tau as f32, nsdf[tau]);
+    }
+    let (a, b, c) = (nsdf[tau - 1], nsdf[tau], nsdf[tau + 1]);
+    let curvature = a - 2.0 * b + c;
+    if curvature >= 0.0 {
+        return (tau as f32, b);
+    }
+    let shift = 0.5 * (a - c) / curvature;
+    (tau as f32 + shift, b - 0.25 * (a - c) * shift)
+}
+
+pub(crate) const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// The equal-tempered note nearest a frequency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Note {
+    /// MIDI note number; A4 is 69.
+    pub midi: i32,
+    /// How far the frequency lies from the note, -50 to +50 cents.
+    pub cents: f32,
+}
+
+impl Note {
+    pub fn from_frequency(frequency: f32, a4: f32) -> Note {
+        let semitones = 69.0 + 12.0 * (frequency / a4).log2();
+        let midi = semitones.round();
+        Note {
+            midi: midi as i32,
+            cents: 100.0 * (semitones - midi),
+        }
+    }
+
+    /// Frequency of a MIDI note in Hz.
+    pub fn frequency(midi: i32, a4: f32) -> f32 {
+        a4 * 2f32.powf((midi - 69) as f32 / 12.0)
+    }
+
+    /// Note name without the octave, such as `C#`.
+    pub fn name(&self) -> &'static str {
+        NOTE_NAMES[self.midi.rem_euclid(12) as usize]
+    }
+
+    /// Scientific pitch octave; middle C is C4.
+    pub fn octave(&self) -> i32 {
+        self.midi.div_euclid(12) - 1
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.name(), self.octave())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A tone with `harmonics` partials falling off as 1/k, like a sawtooth.
+    fn tone(freq: f32, harmonics: usize, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / RATE;
+                (1..=harmonics)
+                    .map(|k| 0.3 * (std::f32::consts::TAU * freq * k as f32 * t).sin() / k as f32)
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn detect(samples: Vec<f32>) -> Option<Pitch> {
+        let mut detector = PitchDetector::new(RATE);
+        detector.push(samples);
+        detector.detect()
+    }
+
+    #[test]
+    fn sines_read_within_a_cent() {
+        for freq in [41.2, 82.41, 261.63, 440.0, 1_000.0, 3_520.0] {
+            let pitch = detect(tone(freq, 1, 8192)).expect("no pitch");
+            let cents = 1200.0 * (pitch.frequency / freq).log2();
+            assert!(cents.abs() < 1.0, "{} Hz read as {}", freq, pitch.frequency);
+            assert!(pitch.clarity > 0.95);
+        }
+    }
+
+    #[test]
+    fn harmonic_tones_read_their_fundamental() {
+        // No octave errors from the strong upper partials
+        for freq in [55.0, 110.0, 196.0, 440.0] {
+            let pitch = detect(tone(freq, 8, 8192)).expect("no pitch");
+            let cents = 1200.0 * (pitch.frequency / freq).log2();
+            assert!(cents.abs() < 2.0, "{} Hz read as {}", freq, pitch.frequency);
+        }
+    }
+
+    #[test]
+    fn silence_and_noise_have_no_pitch() {
+        assert_eq!(detect(vec![0.0; 8192]), None);
+
+        let mut state = 0x2545_f491_u32;
+        let noise = (0..8192)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect();
+        assert_eq!(detect(noise), None);
+    }
+
+    #[test]
+    fn needs_a_full_window() {
+        assert_eq!(detect(tone(440.0, 1, 1024)), None);
+    }
+
+    #[test]
+    fn notes_are_named_with_their_octave() {
+        let note = Note::from_frequency(440.0, DEFAULT_A4_HZ);
+        assert_eq!((note.to_string(), note.cents), ("A4".to_string(), 0.0));
+        assert_eq!(Note::from_frequency(261.63, 440.0).to_string(), "C4");
+        assert_eq!(Note::from_frequency(27.5, 440.0).to_string(), "A0");
+        assert_eq!(Note::from_frequency(4186.0, 440.0).to_string(), "C8");
+
+        // 10 cents sharp of C#3
+        let note = Note::from_frequency(Note::frequency(49, 440.0) * 2f32.powf(0.1 / 12.0), 440.0);
+        assert_eq!(note.to_string(), "C#3");
+        assert!((note.cents - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn reference_pitch_moves_the_notes() {
+        // A4 = 415 Hz, baroque pitch, puts 440 Hz a semitone up
+        let note = Note::from_frequency(440.0, 415.0);
+        assert_eq!(note.to_string(), "A#4");
+        assert!(note.cents.abs() < 2.0);
+        assert!((Note::frequency(69, 432.0) - 432.0).abs() < 1e-3);
+    }
+}
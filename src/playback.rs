@@ -0,0 +1,282 @@
+use anyhow::anyhow;
+use crossbeam_channel::Sender;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use selara::truepeak::TruePeakMeter;
+
+use crate::audio::{AnalyzerCommand, Buffering, FrameSender, Levels, StereoBlock, convert_block};
+
+/// Frames handed to the analyzer per block, about 21 ms at 48 kHz.
+const BLOCK_FRAMES: usize = 1024;
+const NO_SEEK: u64 = u64::MAX;
+
+/// A fully decoded audio file as interleaved f32 samples.
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+}
+
+/// Decodes a WAV, FLAC or Ogg Vorbis file into memory.
+pub fn decode_file(path: &Path) -> Result<DecodedAudio, anyhow::Error> {
+    let file = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow!("{} has no audio track", path.display()))?;
+    let track_id = track.id;
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(0);
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                sample_rate = spec.rate;
+                channels = spec.channels.count();
+                let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buf.copy_interleaved_ref(decoded);
+                samples.extend_from_slice(buf.samples());
+            }
+            // A corrupt packet only loses that packet
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if channels == 0 || sample_rate == 0 {
+        return Err(anyhow!("{} contains no decodable audio", path.display()));
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        channels,
+        sample_rate,
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlaybackOptions {
+    /// Pace playback at this multiple of real time; `None` sends blocks as fast as
+    /// the analyzer accepts them.
+    pub speed: Option<f32>,
+    pub looping: bool,
+    /// End the playback thread at the end of the file, closing the analyzer's input,
+    /// instead of waiting for a seek.
+    pub exit_at_end: bool,
+}
+
+struct Shared {
+    paused: AtomicBool,
+    looping: AtomicBool,
+    stopped: AtomicBool,
+    position: AtomicU64,
+    seek_to: AtomicU64,
+}
+
+/// Feeds a decoded file into the analyzer from a background thread.
+///
+/// The thread stops when this is dropped.
+pub struct Playback {
+    control: PlaybackControl,
+}
+
+impl Playback {
+    /// Starts playing `audio`. Paced realtime playback drops blocks the
+    /// analyzer has no room for; unpaced or lossless playback waits for room.
+    pub fn start(
+        audio: DecodedAudio,
+        options: PlaybackOptions,
+        buffering: Buffering,
+        tx_meter: Sender<Levels>,
+        tx_frames: FrameSender,
+        tx_scope: Sender<StereoBlock>,
+    ) -> Playback {
+        let speed = options.speed;
+        let wait_for_room = speed.is_none() || buffering == Buffering::Lossless;
+        let shared = Arc::new(Shared {
+            paused: AtomicBool::new(false),
+            looping: AtomicBool::new(options.looping),
+            stopped: AtomicBool::new(false),
+            position: AtomicU64::new(0),
+            seek_to: AtomicU64::new(NO_SEEK),
+        });
+        let control = PlaybackControl {
+            shared: shared.clone(),
+            total_frames: audio.frames() as u64,
+            sample_rate: audio.sample_rate,
+        };
+
+        std::thread::spawn(move || {
+            let channels = audio.channels;
+            let total = audio.frames();
+            let frames_per_sec = audio.sample_rate as f64 * speed.unwrap_or(1.0) as f64;
+
+            let mut pos = 0usize;
+            // Pacing restarts after every pause or seek
+            let mut clock = Instant::now();
+            let mut sent = 0usize;
+            let mut true_peak = TruePeakMeter::new(channels);
+
+            while !shared.stopped.load(Ordering::Relaxed) {
+                let mut jumped = false;
+                let seek = shared.seek_to.swap(NO_SEEK, Ordering::Relaxed);
+                if seek != NO_SEEK {
+                    pos = (seek as usize).min(total);
+                    shared.position.store(pos as u64, Ordering::Relaxed);
+                    clock = Instant::now();
+                    sent = 0;
+                    jumped = true;
+                }
+
+                let looping = shared.looping.load(Ordering::Relaxed);
+                if pos >= total && looping && total > 0 {
+                    pos = 0;
+                    jumped = true;
+                }
+                // The analyzer times its frames and measures loudness from here
+                if jumped && !tx_frames.send_command(AnalyzerCommand::Seek { frame: pos as u64 }) {
+                    break;
+                }
+                if pos >= total && options.exit_at_end {
+                    break;
+                }
+                if shared.paused.load(Ordering::Relaxed) || pos >= total {
+                    std::thread::sleep(Duration::from_millis(10));
+                    clock = Instant::now();
+                    sent = 0;
+                    continue;
+                }
+
+                let end = (pos + BLOCK_FRAMES).min(total);
+                let block = &audio.samples[pos * channels..end * channels];
+                if let Some((samples, levels)) = convert_block(block, channels, &mut true_peak) {
+                    let _ = tx_meter.try_send(levels);
+                    let _ = tx_scope.try_send(StereoBlock::from_interleaved(&samples, channels));
+                    let delivered = if wait_for_room {
+                        tx_frames.send(samples)
+                    } else {
+                        tx_frames.try_send(samples)
+                    };
+                    if !delivered {
+                        break;
+                    }
+                }
+
+                sent += end - pos;
+                pos = end;
+                shared.position.store(pos as u64, Ordering::Relaxed);
+
+                if speed.is_some() {
+                    let due = clock + Duration::from_secs_f64(sent as f64 / frames_per_sec);
+                    if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                        std::thread::sleep(wait);
+                    }
+                }
+            }
+        });
+
+        Playback { control }
+    }
+
+    pub fn control(&self) -> PlaybackControl {
+        self.control.clone()
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        self.control.shared.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlaybackStatus {
+    pub position: Duration,
+    pub duration: Duration,
+    pub paused: bool,
+    pub looping: bool,
+}
+
+/// Handle for pausing, seeking and looping a running [`Playback`].
+#[derive(Clone)]
+pub struct PlaybackControl {
+    shared: Arc<Shared>,
+    total_frames: u64,
+    sample_rate: u32,
+}
+
+impl PlaybackControl {
+    pub fn toggle_pause(&self) {
+        self.shared.paused.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn toggle_loop(&self) {
+        self.shared.looping.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    /// Moves the play position by `seconds`, clamped to the file.
+    pub fn seek_by(&self, seconds: f32) {
+        let current = self.shared.position.load(Ordering::Relaxed) as f64;
+        let target = (current + seconds as f64 * self.sample_rate as f64)
+            .clamp(0.0, self.total_frames as f64);
+        self.shared.seek_to.store(target as u64, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        let rate = self.sample_rate as f64;
+        PlaybackStatus {
+            position: Duration::from_secs_f64(
+                self.shared.position.load(Ordering::Relaxed) as f64 / rate,
+            ),
+            duration: Duration::from_secs_f64(self.total_frames as f64 / rate),
+            paused: self.shared.paused.load(Ordering::Relaxed),
+            looping: self.shared.looping.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::audio::StereoBlock;
+
+/// Selectable spans of the oscilloscope, in milliseconds.
+pub const TIMEBASES_MS: [f32; 9] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0];
+const DEFAULT_TIMEBASE: usize = 4;
+pub const TRIGGER_STEP: f32 = 0.05;
+
+/// Which crossing of the trigger level starts the trace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TriggerEdge {
+    #[default]
+    Rising,
+    Falling,
+}
+
+impl TriggerEdge {
+    pub fn toggle(self) -> TriggerEdge {
+        match self {
+            TriggerEdge::Rising => TriggerEdge::Falling,
+            TriggerEdge::Falling => TriggerEdge::Rising,
+        }
+    }
+
+    fn crosses(self, previous: f32, current: f32, level: f32) -> bool {
+        match self {
+            TriggerEdge::Rising => previous < level && current >= level,
+            TriggerEdge::Falling => previous > level && current <= level,
+        }
+    }
+}
+
+impl fmt::Display for TriggerEdge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TriggerEdge::Rising => "↑ rising",
+            TriggerEdge::Falling => "↓ falling",
+        })
+    }
+}
+
+/// The samples to draw for one oscilloscope frame.
+pub struct ScopeView {
+    pub left: Vec<f32>,
+    /// Empty for mono sources.
+    pub right: Vec<f32>,
+    /// False when nothing crossed the trigger level and the newest samples are shown.
+    pub triggered: bool,
+}
+
+/// Recent samples per channel and the trigger and timebase settings.
+pub struct Scope {
+    left: VecDeque<f32>,
+    right: VecDeque<f32>,
+    capacity: usize,
+    sample_rate: u32,
+    timebase: usize,
+    pub edge: TriggerEdge,
+    /// Trigger level on the mono mix, in full scale.
+    pub level: f32,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Scope {
+            left: VecDeque::new(),
+            right: VecDeque::new(),
+            capacity: 0,
+            sample_rate: 0,
+            timebase: DEFAULT_TIMEBASE,
+            edge: TriggerEdge::default(),
+            level: 0.0,
+        }
+    }
+}
+
+impl Scope {
+    /// Clears the samples and sizes the buffer for a new source.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let longest = TIMEBASES_MS[TIMEBASES_MS.len() - 1] / 1000.0;
+        // Room for a full window after any trigger in the older half
+        self.capacity = (2.0 * longest * sample_rate as f32) as usize;
+        self.sample_rate = sample_rate;
+        self.left.clear();
+        self.right.clear();
+    }
+
+    pub fn push(&mut self, block: &StereoBlock) {
+        if !block.right.is_empty() || !self.right.is_empty() {
+            self.right.extend(&block.right);
+        }
+        self.left.extend(&block.left);
+        let excess = self.left.len().saturating_sub(self.capacity);
+        self.left.drain(..excess);
+        let excess = self.right.len().saturating_sub(self.capacity);
+        self.right.drain(..excess);
+    }
+
+    /// Span of the trace in milliseconds.
+    pub fn timebase_ms(&self) -> f32 {
+        TIMEBASES_MS[self.timebase]
+    }
+
+    pub fn longer_timebase(&mut self) {
+        self.timebase = (self.timebase + 1).min(TIMEBASES_MS.len() - 1);
+    }
+
+    pub fn shorter_timebase(&mut self) {
+        self.timebase = self.timebase.saturating_sub(1);
+    }
+
+    /// The most recent window that starts at a trigger crossing, or the newest
+    /// samples if there is none.
+    pub fn view(&self) -> Option<ScopeView> {
+        let window = (self.timebase_ms() / 1000.0 * self.sample_rate as f32) as usize;
+        let stereo = self.right.len() == self.left.len();
+        let len = self.left.len();
+        if window < 2 || len < window {
+            return None;
+        }
+
+        let mono = |i: usize| {
+            if stereo {
+                0.5 * (self.left[i] + self.right[i])
+            } else {
+                self.left[i]
+            }
+        };
+        let trigger = (1..=len - window)
+            .rev()
+            .find(|&i| self.edge.crosses(mono(i - 1), mono(i), self.level));
+        let start = trigger.unwrap_or(len - window);
+        let range = start..start + window;
+        Some(ScopeView {
+            left: self.left.range(range.clone()).copied().collect(),
+            right: if stereo {
+                self.right.range(range).copied().collect()
+            } else {
+                Vec::new()
+            },
+            triggered: trigger.is_some(),
+        })
+    }
+}
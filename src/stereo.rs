@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+
+use crate::audio::StereoBlock;
+
+/// Time constant of the correlation and balance readings, in seconds.
+const INTEGRATION_SECS: f32 = 0.3;
+/// Seconds between points of the correlation history.
+const HISTORY_INTERVAL_SECS: f32 = 0.05;
+/// Points in the correlation history, ten seconds at the interval above.
+pub const HISTORY_LEN: usize = 200;
+/// Sample pairs drawn in the goniometer, about 20 ms at 48 kHz.
+const TRACE_LEN: usize = 1024;
+/// Mean square below which a channel counts as silent.
+const SILENCE: f32 = 1e-10;
+
+/// Stereo image of the first two channels: recent sample pairs for the
+/// goniometer, and the correlation and balance between left and right.
+///
+/// Mono sources are measured as the same signal on both sides.
+pub struct StereoImage {
+    trace: VecDeque<(f32, f32)>,
+    /// Exponentially averaged L², R² and L·R.
+    left_power: f32,
+    right_power: f32,
+    cross: f32,
+    history: VecDeque<Option<f32>>,
+    /// Samples left before the next history point.
+    until_history: usize,
+    sample_rate: u32,
+    mono: bool,
+}
+
+impl Default for StereoImage {
+    fn default() -> Self {
+        StereoImage {
+            trace: VecDeque::with_capacity(TRACE_LEN),
+            left_power: 0.0,
+            right_power: 0.0,
+            cross: 0.0,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            until_history: 0,
+            sample_rate: 0,
+            mono: false,
+        }
+    }
+}
+
+impl StereoImage {
+    /// Clears the readings for a new source.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        *self = StereoImage {
+            sample_rate,
+            ..StereoImage::default()
+        };
+    }
+
+    pub fn push(&mut self, block: &StereoBlock) {
+        if self.sample_rate == 0 {
+            return;
+        }
+        self.mono = block.right.is_empty();
+        let right = if self.mono { &block.left } else { &block.right };
+
+        let rate = self.sample_rate as f32;
+        let decay = (-1.0 / (INTEGRATION_SECS * rate)).exp();
+        let interval = ((HISTORY_INTERVAL_SECS * rate) as usize).max(1);
+        for (&l, &r) in block.left.iter().zip(right) {
+            self.left_power = decay * self.left_power + (1.0 - decay) * l * l;
+            self.right_power = decay * self.right_power + (1.0 - decay) * r * r;
+            self.cross = decay * self.cross + (1.0 - decay) * l * r;
+            self.trace.push_back((l, r));
+
+            if self.until_history == 0 {
+                self.history.push_back(self.correlation());
+                self.until_history = interval;
+            }
+            self.until_history -= 1;
+        }
+
+        let excess = self.trace.len().saturating_sub(TRACE_LEN);
+        self.trace.drain(..excess);
+        let excess = self.history.len().saturating_sub(HISTORY_LEN);
+        self.history.drain(..excess);
+    }
+
+    /// True when the last block had a single channel.
+    pub fn is_mono(&self) -> bool {
+        self.mono
+    }
+
+    /// The newest (left, right) sample pairs, oldest first.
+    pub fn trace(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.trace.iter().copied()
+    }
+
+    /// Phase correlation from -1 (opposite) through 0 (unrelated) to +1 (mono),
+    /// or `None` while either side is silent.
+    pub fn correlation(&self) -> Option<f32> {
+        if self.left_power < SILENCE || self.right_power < SILENCE {
+            return None;
+        }
+        Some((self.cross / (self.left_power * self.right_power).sqrt()).clamp(-1.0, 1.0))
+    }
+
+    /// Correlation readings, oldest first, `None` where the signal was silent.
+    pub fn history(&self) -> impl ExactSizeIterator<Item = Option<f32>> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// Level of the right side relative to the left in dB, or `None` in silence.
+    pub fn balance_db(&self) -> Option<f32> {
+        if self.left_power < SILENCE && self.right_power < SILENCE {
+            return None;
+        }
+        let ratio = (self.right_power + SILENCE) / (self.left_power + SILENCE);
+        Some(10.0 * ratio.log10())
+    }
+
+    /// Where the power sits between left (-1) and right (+1).
+    pub fn balance_position(&self) -> Option<f32> {
+        let total = self.left_power + self.right_power;
+        if total < SILENCE {
+            return None;
+        }
+        Some((self.right_power - self.left_power) / total)
+    }
+}
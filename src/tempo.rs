@@ -0,0 +1,390 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Tempi the tracker looks for, in beats per minute.
+pub const MIN_BPM: f32 = 60.0;
+pub const MAX_BPM: f32 = 200.0;
+/// Samples per second of the onset envelope. Shorter hops give frames
+/// faster than this, which are summed into each sample, so the cost of
+/// tracking stays the same at any hop.
+const ENVELOPE_RATE: f32 = 100.0;
+/// Seconds between tempo estimates once there is a tempo.
+const ESTIMATE_INTERVAL_SECS: f32 = 0.25;
+/// Seconds of onset strength the tempo is estimated from, and the least of
+/// them before a tempo is reported.
+const HISTORY_SECS: f32 = 8.0;
+const MIN_HISTORY_SECS: f32 = 4.0;
+/// An onset is a frame whose flux exceeds the mean of this many seconds
+/// before it by [`ONSET_RATIO`].
+const ONSET_WINDOW_SECS: f32 = 0.5;
+const ONSET_RATIO: f32 = 1.5;
+/// Least flux counted as an onset, so noise in near silence is not.
+const MIN_ONSET_FLUX: f32 = 1e-3;
+/// Shortest time between onsets, in seconds.
+const MIN_ONSET_GAP_SECS: f32 = 0.1;
+/// Tempo most music sits near, and the spread around it in octaves, that
+/// weigh the autocorrelation against double and half tempo errors.
+const PRIOR_BPM: f32 = 120.0;
+const PRIOR_OCTAVES: f32 = 1.0;
+/// Past beats the beat phase is matched against.
+const BEAT_COMB: usize = 4;
+/// Scales amplitudes before log compression, so quiet partials count
+/// towards the flux as well as loud ones.
+const FLUX_COMPRESSION: f32 = 1_000.0;
+
+/// An estimated tempo.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Tempo {
+    pub bpm: f32,
+    /// Autocorrelation of the onset strength at the beat period, 0 to 1;
+    /// higher for a steadier beat.
+    pub confidence: f32,
+}
+
+/// Half-wave rectified spectral flux of log-compressed magnitudes, the onset
+/// strength of successive spectrum frames.
+#[derive(Clone, Default)]
+pub(crate) struct SpectralFlux {
+    previous: Vec<f32>,
+}
+
+impl SpectralFlux {
+    /// The mean rise in log magnitude from the previous frame over all bins,
+    /// given the amplitudes of this frame relative to full scale. The first
+    /// frame, and the first after the bin count changes, has no flux.
+    pub(crate) fn push(&mut self, amplitudes: impl ExactSizeIterator<Item = f32>) -> f32 {
+        let bins = amplitudes.len();
+        let current = amplitudes.map(|a| (FLUX_COMPRESSION * a).ln_1p());
+        if self.previous.len() != bins {
+            self.previous = current.collect();
+            return 0.0;
+        }
+        let mut rise = 0.0;
+        for (previous, magnitude) in self.previous.iter_mut().zip(current) {
+            rise += (magnitude - *previous).max(0.0);
+            *previous = magnitude;
+        }
+        rise / bins.max(1) as f32
+    }
+}
+
+/// Finds onsets in a stream of onset strengths, one per frame, estimates the
+/// tempo from their autocorrelation and places beats on it.
+///
+/// Frames faster than [`ENVELOPE_RATE`] are summed in groups into an onset
+/// envelope, and onsets and beats are reported on the last frame of each
+/// group.
+///
+/// ```
+/// use selara::tempo::TempoTracker;
+///
+/// // Clicks at 120 BPM, a frame every 10 ms
+/// let mut tracker = TempoTracker::new(100.0);
+/// for frame in 0..1_000 {
+///     tracker.push(if frame % 50 == 0 { 1.0 } else { 0.0 });
+/// }
+/// assert!((tracker.tempo().unwrap().bpm - 120.0).abs() < 1.0);
+/// ```
+pub struct TempoTracker {
+    /// Frames summed into each envelope sample, and the envelope's rate.
+    group: usize,
+    rate: f32,
+    group_strength: f32,
+    group_fill: usize,
+    /// The latest envelope samples, oldest first.
+    history: VecDeque<f32>,
+    onset: bool,
+    beat: bool,
+    tempo: Option<Tempo>,
+    // counted in envelope samples
+    since_onset: usize,
+    since_beat: usize,
+    since_estimate: usize,
+}
+
+impl TempoTracker {
+    /// A tracker for `frame_rate` frames per second.
+    pub fn new(frame_rate: f32) -> TempoTracker {
+        let group = ((frame_rate / ENVELOPE_RATE).round() as usize).max(1);
+        TempoTracker {
+            group,
+            rate: frame_rate / group as f32,
+            group_strength: 0.0,
+            group_fill: 0,
+            history: VecDeque::new(),
+            onset: false,
+            beat: false,
+            tempo: None,
+            since_onset: usize::MAX,
+            since_beat: usize::MAX,
+            since_estimate: 0,
+        }
+    }
+
+    /// Takes the onset strength of the next frame.
+    pub fn push(&mut self, strength: f32) {
+        self.onset = false;
+        self.beat = false;
+        self.group_strength += strength;
+        self.group_fill += 1;
+        if self.group_fill == self.group {
+            let strength = std::mem::take(&mut self.group_strength);
+            self.group_fill = 0;
+            self.push_envelope(strength);
+        }
+    }
+
+    /// Takes the next sample of the onset envelope.
+    fn push_envelope(&mut self, strength: f32) {
+        let window = ((ONSET_WINDOW_SECS * self.rate) as usize).max(1);
+        let recent = self.history.iter().rev().take(window);
+        let count = recent.len().max(1);
+        let mean = recent.sum::<f32>() / count as f32;
+        let gap = (MIN_ONSET_GAP_SECS * self.rate) as usize;
+        self.onset = !self.history.is_empty()
+            && strength > MIN_ONSET_FLUX
+            && strength > ONSET_RATIO * mean
+            && self.since_onset >= gap;
+        self.since_onset = if self.onset {
+            0
+        } else {
+            self.since_onset.saturating_add(1)
+        };
+
+        self.history.push_back(strength);
+        let capacity = (HISTORY_SECS * self.rate) as usize;
+        let excess = self.history.len().saturating_sub(capacity);
+        self.history.drain(..excess);
+
+        // A tempo is looked for on every sample until one is found, then
+        // refreshed a few times a second
+        let enough = (MIN_HISTORY_SECS * self.rate) as usize;
+        let interval = ((ESTIMATE_INTERVAL_SECS * self.rate) as usize).max(1);
+        self.since_estimate = self.since_estimate.saturating_add(1);
+        if self.history.len() < enough {
+            self.tempo = None;
+        } else if self.tempo.is_none() || self.since_estimate >= interval {
+            self.tempo = estimate_tempo(self.history.make_contiguous(), self.rate);
+            self.since_estimate = 0;
+        }
+        self.beat = self.tempo.is_some_and(|tempo| self.on_beat(tempo));
+        self.since_beat = if self.beat {
+            0
+        } else {
+            self.since_beat.saturating_add(1)
+        };
+    }
+
+    /// Whether the latest envelope sample lines up with past onsets whole
+    /// beat periods back better than any later phase does. Past onsets may be
+    /// a sample off the period, as samples rarely divide it.
+    fn on_beat(&self, tempo: Tempo) -> bool {
+        let period = self.rate * 60.0 / tempo.bpm;
+        let whole = period.round() as usize;
+        if whole == 0 || (self.since_beat as f32) < 0.5 * period {
+            return false;
+        }
+        let last = self.history.len() - 1;
+        let comb = |phase: usize| -> f32 {
+            (0..BEAT_COMB)
+                .filter_map(|k| {
+                    let back = phase + (k as f32 * period).round() as usize;
+                    let i = last.checked_sub(back)?;
+                    let near = if k == 0 {
+                        i..=i
+                    } else {
+                        i.saturating_sub(1)..=(i + 1).min(last)
+                    };
+                    near.map(|i| self.history[i]).reduce(f32::max)
+                })
+                .sum()
+        };
+        let now = comb(0);
+        // A phase a period back ties with this one; the gap since the last
+        // beat keeps that from doubling it
+        now > 0.0 && (1..whole).all(|phase| comb(phase) <= now)
+    }
+
+    /// Whether the latest frame starts a new note or hit.
+    pub fn onset(&self) -> bool {
+        self.onset
+    }
+
+    /// Whether the latest frame falls on a beat.
+    pub fn beat(&self) -> bool {
+        self.beat
+    }
+
+    /// The tempo of the last several seconds, once enough has been heard
+    /// and while it has any onsets.
+    pub fn tempo(&self) -> Option<Tempo> {
+        self.tempo
+    }
+}
+
+/// The tempo whose beat period best autocorrelates the onset envelope, with
+/// `rate` samples a second, weighted towards [`PRIOR_BPM`].
+fn estimate_tempo(strength: &[f32], rate: f32) -> Option<Tempo> {
+    // A light blur lets onsets a frame off the period still line up
+    let n = strength.len();
+    let blurred: Vec<f32> = (0..n)
+        .map(|i| {
+            let before = strength[i.saturating_sub(1)];
+            let after = strength[(i + 1).min(n - 1)];
+            0.25 * before + 0.5 * strength[i] + 0.25 * after
+        })
+        .collect();
+    let mean = blurred.iter().sum::<f32>() / n as f32;
+    let x: Vec<f32> = blurred.iter().map(|s| s - mean).collect();
+    let energy: f32 = x.iter().map(|v| v * v).sum();
+    if energy <= 1e-12 {
+        return None;
+    }
+    let shortest = (rate * 60.0 / MAX_BPM).ceil() as usize;
+    let longest = ((rate * 60.0 / MIN_BPM) as usize).min(n / 2);
+    if shortest < 2 || longest <= shortest {
+        return None;
+    }
+
+    // Biased towards shorter lags by their longer overlap, which settles
+    // ties between a tempo and its half in favour of the faster
+    let autocorrelation = |lag: usize| -> f32 {
+        let sum: f32 = x[..n - lag].iter().zip(&x[lag..]).map(|(a, b)| a * b).sum();
+        sum / energy
+    };
+    let prior = |lag: f32| {
+        let octaves = (rate * 60.0 / lag / PRIOR_BPM).log2() / PRIOR_OCTAVES;
+        (-0.5 * octaves * octaves).exp()
+    };
+    let r: Vec<f32> = (shortest - 1..=longest + 1).map(autocorrelation).collect();
+    let best = (1..r.len() - 1).max_by(|&a, &b| {
+        let weigh = |i: usize| r[i] * prior((shortest - 1 + i) as f32);
+        weigh(a).total_cmp(&weigh(b))
+    })?;
+    if r[best] <= 0.0 {
+        return None;
+    }
+
+    // Parabolic interpolation between lags
+    let (a, b, c) = (r[best - 1], r[best], r[best + 1]);
+    let curvature = a - 2.0 * b + c;
+    let shift = if curvature < 0.0 {
+        (0.5 * (a - c) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let lag = (shortest - 1 + best) as f32 + shift;
+    // The confidence makes up for the bias, so a steady beat reads near 1 at
+    // any tempo
+    let overlap = (n - shortest + 1 - best) as f32 / n as f32;
+    Some(Tempo {
+        bpm: rate * 60.0 / lag,
+        confidence: (b / overlap).clamp(0.0, 1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_RATE: f32 = 93.75;
+
+    /// Onset strength with a click every `60 / bpm` seconds, starting at `offset`.
+    fn clicks(bpm: f32, offset: f32, frames: usize) -> Vec<f32> {
+        let period = FRAME_RATE * 60.0 / bpm;
+        let mut strength = vec![0.01; frames];
+        let mut t = offset;
+        while (t as usize) < frames {
+            strength[t as usize] = 1.0;
+            t += period;
+        }
+        strength
+    }
+
+    fn track(strength: &[f32]) -> (TempoTracker, Vec<usize>, Vec<usize>) {
+        let mut tracker = TempoTracker::new(FRAME_RATE);
+        let (mut onsets, mut beats) = (Vec::new(), Vec::new());
+        for (frame, &s) in strength.iter().enumerate() {
+            tracker.push(s);
+            if tracker.onset() {
+                onsets.push(frame);
+            }
+            if tracker.beat() {
+                beats.push(frame);
+            }
+        }
+        (tracker, onsets, beats)
+    }
+
+    #[test]
+    fn steady_clicks_give_their_tempo() {
+        for bpm in [72.0, 98.0, 120.0, 128.0, 150.0] {
+            let (tracker, _, _) = track(&clicks(bpm, 3.0, 1_000));
+            let tempo = tracker.tempo().expect("no tempo");
+            assert!(
+                (tempo.bpm - bpm).abs() < 1.0,
+                "{} BPM read as {}",
+                bpm,
+                tempo.bpm
+            );
+            assert!(tempo.confidence > 0.8);
+        }
+    }
+
+    #[test]
+    fn onsets_are_the_clicks() {
+        let strength = clicks(120.0, 10.0, 500);
+        let (_, onsets, _) = track(&strength);
+        let expected: Vec<usize> = (0..500).filter(|&i| strength[i] == 1.0).collect();
+        assert_eq!(onsets, expected);
+    }
+
+    #[test]
+    fn beats_fall_on_the_clicks() {
+        let strength = clicks(120.0, 10.0, 1_500);
+        let (_, _, beats) = track(&strength);
+        // Beats start once the tempo is known, then land on every click
+        let expected: Vec<usize> = (0..1_500)
+            .filter(|&i| strength[i] == 1.0 && i as f32 >= MIN_HISTORY_SECS * FRAME_RATE)
+            .collect();
+        assert_eq!(beats, expected);
+    }
+
+    #[test]
+    fn short_hops_keep_the_envelope_rate() {
+        // 48 kHz with a hop of 32, clicks at 120 BPM
+        let frame_rate = 1_500.0;
+        let mut tracker = TempoTracker::new(frame_rate);
+        let mut onsets = 0;
+        for frame in 0..20 * 1_500 {
+            tracker.push(if frame % 750 == 100 { 1.0 } else { 0.001 });
+            onsets += tracker.onset() as usize;
+        }
+        // The history, and so the autocorrelation, is as long as at the
+        // envelope rate
+        assert_eq!(tracker.rate, 100.0);
+        assert!(tracker.history.len() <= (HISTORY_SECS * ENVELOPE_RATE) as usize);
+        assert_eq!(onsets, 40);
+        let tempo = tracker.tempo().expect("no tempo");
+        assert!((tempo.bpm - 120.0).abs() < 1.0, "{} BPM", tempo.bpm);
+    }
+
+    #[test]
+    fn silence_has_no_tempo_onsets_or_beats() {
+        let (tracker, onsets, beats) = track(&[0.0; 1_000]);
+        assert_eq!(tracker.tempo(), None);
+        assert!(onsets.is_empty() && beats.is_empty());
+    }
+
+    #[test]
+    fn flux_counts_rising_magnitudes_only() {
+        let mut flux = SpectralFlux::default();
+        assert_eq!(flux.push([0.0, 0.5].into_iter()), 0.0);
+        let rise = flux.push([0.5, 0.5].into_iter());
+        assert!((rise - (1.0 + 500.0f32).ln() / 2.0).abs() < 1e-5);
+        // Falling back adds nothing
+        assert_eq!(flux.push([0.0, 0.5].into_iter()), 0.0);
+        // A new bin count starts over
+        assert_eq!(flux.push([1.0; 3].into_iter()), 0.0);
+    }
+}
@@ -0,0 +1,195 @@
+use std::f64::consts::PI;
+
+use crate::window::bessel_i0;
+
+/// Oversampling factor, as ITU-R BS.1770-4 Annex 2 recommends for 48 kHz.
+const OVERSAMPLING: usize = 4;
+/// Taps in each polyphase branch of the interpolator, 48 in total like the
+/// example filter in Annex 2.
+const PHASE_TAPS: usize = 12;
+/// Shape of the Kaiser window on the interpolator's sinc.
+const KAISER_BETA: f64 = 6.0;
+/// Level at or above which an oversampled value counts as clipping.
+pub const CLIP_LEVEL: f32 = 1.0;
+/// Samples under the clip level that end a clip, so the interpolator's ringing
+/// around a run of full-scale samples does not count as several clips.
+const CLIP_RELEASE: usize = PHASE_TAPS;
+
+/// Measures the true peak of each channel of interleaved audio by 4x
+/// oversampling, so peaks between samples are caught, and counts clips.
+///
+/// ```
+/// use selara::truepeak::TruePeakMeter;
+///
+/// // A quarter-rate sine sampled 45° off its crests: every sample reads
+/// // 0.707, but the waveform between them reaches 1.0
+/// let samples: Vec<f32> = (0..4800)
+///     .map(|i| (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin())
+///     .collect();
+/// let mut meter = TruePeakMeter::new(1);
+/// let peaks = meter.process(&samples);
+/// assert!(peaks[0] > 0.95);
+/// ```
+pub struct TruePeakMeter {
+    channels: usize,
+    /// Interpolator taps for each output phase.
+    phases: [[f32; PHASE_TAPS]; OVERSAMPLING],
+    /// The last samples of each channel, newest first.
+    history: Vec<[f32; PHASE_TAPS]>,
+    /// Samples of each channel since one was at or above [`CLIP_LEVEL`].
+    since_clip: Vec<usize>,
+    clips: Vec<u64>,
+}
+
+impl TruePeakMeter {
+    pub fn new(channels: usize) -> TruePeakMeter {
+        TruePeakMeter {
+            channels,
+            phases: interpolator(),
+            history: vec![[0.0; PHASE_TAPS]; channels],
+            since_clip: vec![CLIP_RELEASE; channels],
+            clips: vec![0; channels],
+        }
+    }
+
+    /// Runs a block of interleaved frames through the interpolator and returns
+    /// the highest absolute value of each channel, linear.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let mut peaks = vec![0.0f32; self.channels];
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &x) in frame.iter().enumerate() {
+                let history = &mut self.history[channel];
+                history.copy_within(..PHASE_TAPS - 1, 1);
+                history[0] = x;
+
+                let peak = self
+                    .phases
+                    .iter()
+                    .map(|taps| {
+                        taps.iter()
+                            .zip(history.iter())
+                            .map(|(h, x)| h * x)
+                            .sum::<f32>()
+                    })
+                    .fold(0.0f32, |peak, y| peak.max(y.abs()));
+                peaks[channel] = peaks[channel].max(peak);
+
+                // A run of values over the clip level counts once
+                if peak >= CLIP_LEVEL {
+                    if self.since_clip[channel] >= CLIP_RELEASE {
+                        self.clips[channel] += 1;
+                    }
+                    self.since_clip[channel] = 0;
+                } else {
+                    self.since_clip[channel] = self.since_clip[channel].saturating_add(1);
+                }
+            }
+        }
+        peaks
+    }
+
+    /// Clips counted on each channel since the meter was created.
+    pub fn clips(&self) -> &[u64] {
+        &self.clips
+    }
+}
+
+/// Kaiser-windowed sinc interpolator split into its polyphase branches, each
+/// normalized to unity gain at DC.
+fn interpolator() -> [[f32; PHASE_TAPS]; OVERSAMPLING] {
+    let len = OVERSAMPLING * PHASE_TAPS;
+    let center = (len - 1) as f64 / 2.0;
+    let taps: Vec<f64> = (0..len)
+        .map(|n| {
+            let t = (n as f64 - center) / OVERSAMPLING as f64;
+            let sinc = if t == 0.0 {
+                1.0
+            } else {
+                (PI * t).sin() / (PI * t)
+            };
+            let r = (n as f64 - center) / center;
+            let window = bessel_i0(KAISER_BETA * (1.0 - r * r).sqrt()) / bessel_i0(KAISER_BETA);
+            sinc * window
+        })
+        .collect();
+
+    let mut phases = [[0.0; PHASE_TAPS]; OVERSAMPLING];
+    for (phase, branch) in phases.iter_mut().enumerate() {
+        let gain: f64 = (0..PHASE_TAPS)
+            .map(|k| taps[phase + k * OVERSAMPLING])
+            .sum();
+        for (k, tap) in branch.iter_mut().enumerate() {
+            *tap = (taps[phase + k * OVERSAMPLING] / gain) as f32;
+        }
+    }
+    phases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    fn sine(freq: f32, amplitude: f32, phase: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / RATE + phase).sin()
+            })
+            .collect()
+    }
+
+    fn dbtp(samples: &[f32]) -> f32 {
+        20.0 * TruePeakMeter::new(1).process(samples)[0].log10()
+    }
+
+    #[test]
+    fn low_frequency_sine_reads_its_amplitude() {
+        let level = dbtp(&sine(997.0, 0.5, 0.3, 4800));
+        assert!((level + 6.02).abs() < 0.05, "{}", level);
+    }
+
+    #[test]
+    fn finds_peaks_between_samples() {
+        // Samples at ±0.5 (-6 dBFS) of a sine whose crests reach 0.707 (-3 dBFS)
+        let samples = sine(RATE / 4.0, 0.5f32.sqrt(), std::f32::consts::FRAC_PI_4, 4800);
+        let sample_peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((20.0 * sample_peak.log10() + 6.02).abs() < 0.01);
+        let level = dbtp(&samples);
+        assert!((level + 3.01).abs() < 0.5, "{}", level);
+    }
+
+    #[test]
+    fn channels_are_measured_separately() {
+        let left = sine(1_000.0, 0.25, 0.0, 4800);
+        let samples: Vec<f32> = left.iter().flat_map(|&l| [l, 0.0]).collect();
+        let peaks = TruePeakMeter::new(2).process(&samples);
+        assert!((peaks[0] - 0.25).abs() < 0.01);
+        assert_eq!(peaks[1], 0.0);
+    }
+
+    #[test]
+    fn counts_each_run_of_clipping_once() {
+        let mut meter = TruePeakMeter::new(1);
+        let mut samples = vec![0.0; 100];
+        samples.extend([1.0; 10]);
+        samples.extend([0.0; 100]);
+        samples.extend([-1.0; 10]);
+        samples.extend([0.0; 100]);
+        meter.process(&samples);
+        assert_eq!(meter.clips(), &[2]);
+
+        meter.process(&sine(1_000.0, 0.5, 0.0, 4800));
+        assert_eq!(meter.clips(), &[2]);
+    }
+
+    #[test]
+    fn intersample_overs_clip() {
+        // Every sample is below full scale, the waveform between them is not
+        let samples = sine(RATE / 4.0, 1.2, std::f32::consts::FRAC_PI_4, 4800);
+        assert!(samples.iter().all(|s| s.abs() < CLIP_LEVEL));
+        let mut meter = TruePeakMeter::new(1);
+        meter.process(&samples);
+        assert!(meter.clips()[0] > 0);
+    }
+}
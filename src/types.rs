@@ -1,12 +1,202 @@
-#[allow(dead_code)]
+use anyhow::ensure;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::averaging::Averaging;
+use crate::bands::BandScale;
+use crate::chroma::{Key, PITCH_CLASSES};
+use crate::features::Features;
+use crate::loudness::Loudness;
+use crate::pitch::Pitch;
+use crate::tempo::Tempo;
+use crate::weighting::Weighting;
+use crate::window::WindowFunction;
+
+/// Linear level of the samples added since the previous frame, after the
+/// configured weighting.
 #[derive(Clone, Copy, Debug)]
 pub struct Meter {
     pub rms: f32,
     pub peak: f32,
 }
 
+/// Lowest level reported in [`Spectrum::bands`], standing in for silence.
+pub const MIN_DBFS: f32 = -120.0;
+
 #[derive(Clone, Debug)]
 pub struct Spectrum {
+    /// Seconds of audio analyzed up to the end of this frame's FFT window.
+    pub time: f64,
+    /// Center frequency of each band in Hz.
+    pub frequencies: Arc<[f32]>,
+    /// Rounded center of each band for labels, e.g. the ISO 266 nominal frequency.
+    pub nominal_frequencies: Arc<[f32]>,
+    /// Band edges in Hz, one more than there are bands; band `i` spans
+    /// `edges[i]..edges[i + 1]`.
+    pub edges: Arc<[f32]>,
+    /// Smoothed band levels in dBFS, where a full-scale sine reads 0.
+    pub bands: Vec<f32>,
+    /// Smoothed band amplitudes relative to full scale, clamped to 0.0-1.0.
+    pub bands_linear: Vec<f32>,
+    /// Level of the samples that entered the window since the previous frame.
+    pub meter: Meter,
+    /// Loudness of all channels up to the end of this frame's FFT window.
+    pub loudness: Loudness,
+    /// Fundamental of the mix in the latest samples up to the end of this
+    /// frame's FFT window, if it has one.
+    pub pitch: Option<Pitch>,
+    /// Smoothed share of the mix's tonal energy in each pitch class from C
+    /// to B, relative to the strongest; all zero in silence.
+    pub chroma: [f32; PITCH_CLASSES],
+    /// Key of the last several seconds of the mix, if the chroma suggests one.
+    pub key: Option<Key>,
+    /// Whether a note or hit starts in the mix at this frame.
+    pub onset: bool,
+    /// Whether this frame falls on a beat of the tracked tempo.
+    pub beat: bool,
+    /// Tempo of the last several seconds of the mix, once it can be estimated.
+    pub tempo: Option<Tempo>,
+    /// Spectral and waveform descriptors of the mix in this frame.
+    pub features: Features,
+    /// Spectra of the individual channels, then Mid and Side, for
+    /// multi-channel input; empty for mono. The fields above describe the mix.
+    pub channels: Vec<ChannelSpectrum>,
+}
+
+impl Spectrum {
+    /// The spectrum of one channel, if the input has it.
+    pub fn channel(&self, channel: Channel) -> Option<&ChannelSpectrum> {
+        self.channels.iter().find(|c| c.channel == channel)
+    }
+}
+
+/// A signal taken from the input channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    /// The average of all channels.
+    Mix,
+    /// One input channel, counting from zero.
+    Input(usize),
+    /// Half the sum of the first two channels.
+    Mid,
+    /// Half the difference of the first two channels.
+    Side,
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Channel::Mix => f.write_str("Mix"),
+            Channel::Input(0) => f.write_str("L"),
+            Channel::Input(1) => f.write_str("R"),
+            Channel::Input(i) => write!(f, "Ch{}", i + 1),
+            Channel::Mid => f.write_str("M"),
+            Channel::Side => f.write_str("S"),
+        }
+    }
+}
+
+/// Band levels of one channel, as in [`Spectrum`].
+#[derive(Clone, Debug)]
+pub struct ChannelSpectrum {
+    pub channel: Channel,
     pub bands: Vec<f32>,
     pub bands_linear: Vec<f32>,
+    pub meter: Meter,
+}
+
+/// Smallest and largest FFT sizes, in samples.
+pub const MIN_FFT_SIZE: usize = 256;
+pub const MAX_FFT_SIZE: usize = 32_768;
+/// Longest attack or release time constant, in ms.
+pub const MAX_TIME_CONSTANT_MS: f32 = 10_000.0;
+/// Most frames in a linear or exponential average.
+pub const MAX_AVERAGE_FRAMES: usize = 256;
+
+#[derive(Clone, Debug)]
+pub struct AnalyzerConfig {
+    pub fft_size: usize,
+    pub hop: usize,
+    pub bands: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    /// Time constants in ms of the band levels rising and falling; 0 follows
+    /// every frame.
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub averaging: Averaging,
+    /// Frames in a linear or exponential average.
+    pub average_frames: usize,
+    pub scale: BandScale,
+    pub window: WindowFunction,
+    /// Shape of the Kaiser window; larger values trade resolution for lower sidelobes.
+    pub kaiser_beta: f32,
+    /// Frequency weighting of the band levels.
+    pub weighting: Weighting,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        AnalyzerConfig {
+            fft_size: 1024,
+            hop: 512,
+            bands: 96,
+            min_freq: 20.0,
+            max_freq: 20_000.0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+            averaging: Averaging::Off,
+            average_frames: 8,
+            scale: BandScale::Log,
+            window: WindowFunction::Hann,
+            kaiser_beta: 8.6,
+            weighting: Weighting::Z,
+        }
+    }
+}
+
+impl AnalyzerConfig {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        ensure!(
+            self.fft_size.is_power_of_two()
+                && (MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&self.fft_size),
+            "FFT size must be a power of two from {} to {}, got {}",
+            MIN_FFT_SIZE,
+            MAX_FFT_SIZE,
+            self.fft_size
+        );
+        ensure!(
+            (1..=self.fft_size).contains(&self.hop),
+            "hop must be between 1 and the FFT size ({}), got {}",
+            self.fft_size,
+            self.hop
+        );
+        ensure!(self.bands >= 2, "at least 2 bands are required");
+        ensure!(
+            self.min_freq > 0.0 && self.min_freq < self.max_freq,
+            "frequency range must satisfy 0 < min < max, got {}..{} Hz",
+            self.min_freq,
+            self.max_freq
+        );
+        ensure!(
+            (0.0..=MAX_TIME_CONSTANT_MS).contains(&self.attack_ms)
+                && (0.0..=MAX_TIME_CONSTANT_MS).contains(&self.release_ms),
+            "attack and release must be between 0 and {} ms, got {} and {}",
+            MAX_TIME_CONSTANT_MS,
+            self.attack_ms,
+            self.release_ms
+        );
+        ensure!(
+            (1..=MAX_AVERAGE_FRAMES).contains(&self.average_frames),
+            "average frames must be between 1 and {}, got {}",
+            MAX_AVERAGE_FRAMES,
+            self.average_frames
+        );
+        ensure!(
+            (0.0..=50.0).contains(&self.kaiser_beta),
+            "Kaiser beta must be between 0 and 50, got {}",
+            self.kaiser_beta
+        );
+        Ok(())
+    }
 }
@@ -10,54 +10,373 @@ use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols::Marker,
     text::{Line, Span},
-    widgets::{Bar, BarChart, BarGroup, Block, Borders, Gauge, Paragraph},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Clear, Gauge, List, ListItem, ListState,
+        Paragraph, Sparkline,
+        canvas::{Canvas, Line as CanvasLine, Points},
+    },
 };
 use std::{
+    fmt,
     io::{self, Stdout},
     time::Duration,
 };
 
-use crate::types::Spectrum;
+use selara::averaging::Averaging;
+use selara::ballistics::{BallisticMeter, Ballistics, DIGITAL_RETURN_DB_PER_SEC, MeterScale};
+use selara::bands::BandScale;
+use selara::chroma::PITCH_CLASSES;
+use selara::features::Features;
+use selara::loudness::LoudnessTarget;
+use selara::pitch::{Note, Pitch};
+use selara::truepeak::CLIP_LEVEL;
+use selara::types::{
+    AnalyzerConfig, Channel, ChannelSpectrum, MAX_FFT_SIZE, MIN_DBFS, MIN_FFT_SIZE, Spectrum,
+};
+use selara::weighting::{Weighting, WeightingFilter};
+
+use crate::audio::{
+    AnalyzerCommand, DeviceEntry, DeviceKind, Levels, StereoBlock, describe_config,
+    describe_config_range, enumerate_devices,
+};
+use crate::config::{LayoutConfig, MAX_DB_CEILING, Settings, Theme};
+use crate::history::{FeatureHistory, HISTORY_LEN as LOUDNESS_HISTORY_LEN, LoudnessHistory};
+use crate::keys::{Action, KeyMap};
+use crate::peaks::{BandPeaks, SpectrumPeaks};
+use crate::playback::PlaybackControl;
+use crate::scope::{Scope, TRIGGER_STEP};
+use crate::stereo::{HISTORY_LEN, StereoImage};
+use crate::waterfall::{Colormap, MAX_HISTORY, MIN_HISTORY, Waterfall};
+
+/// State of the device selection popup.
+pub struct DevicePicker {
+    pub entries: Vec<DeviceEntry>,
+    pub state: ListState,
+    /// Why the last selected device could not be opened.
+    pub error: Option<String>,
+}
+
+impl DevicePicker {
+    pub fn new(current_device: &str) -> DevicePicker {
+        let entries = enumerate_devices();
+        let selected = entries
+            .iter()
+            .position(|entry| entry.name == current_device)
+            .or(if entries.is_empty() { None } else { Some(0) });
+        DevicePicker {
+            entries,
+            state: ListState::default().with_selected(selected),
+            error: None,
+        }
+    }
+
+    pub fn selected(&self) -> Option<&DeviceEntry> {
+        self.state.selected().and_then(|i| self.entries.get(i))
+    }
+}
+
+/// Which channels the spectrum shows for multi-channel sources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelView {
+    #[default]
+    Mix,
+    LeftRight,
+    MidSide,
+}
+
+impl ChannelView {
+    const ALL: [ChannelView; 3] = [
+        ChannelView::Mix,
+        ChannelView::LeftRight,
+        ChannelView::MidSide,
+    ];
+
+    fn next(self) -> ChannelView {
+        let i = Self::ALL.iter().position(|&v| v == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// The two channels drawn together, or `None` for the mix alone.
+    fn pair(self) -> Option<[Channel; 2]> {
+        match self {
+            ChannelView::Mix => None,
+            ChannelView::LeftRight => Some([Channel::Input(0), Channel::Input(1)]),
+            ChannelView::MidSide => Some([Channel::Mid, Channel::Side]),
+        }
+    }
+}
+
+impl fmt::Display for ChannelView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ChannelView::Mix => "Mix",
+            ChannelView::LeftRight => "L/R",
+            ChannelView::MidSide => "M/S",
+        })
+    }
+}
 
 pub struct App {
     pub should_quit: bool,
-    pub last_rms: f32,
-    pub peak_hold: f32,
+    /// Weighted RMS of the latest block of the first two channels; empty
+    /// until samples arrive.
+    pub channel_rms: Vec<f32>,
+    pub ballistics: Ballistics,
+    /// Level meters of the first two channels, one for mono sources.
+    pub level_meters: Vec<BallisticMeter>,
+    /// Weighting in front of each level meter, following the analyzer's.
+    pub meter_weighting: Vec<WeightingFilter>,
+    /// Held and highest true peak of each input channel since the last reset,
+    /// linear.
+    pub true_peak_hold: Vec<f32>,
+    pub true_peak_max: Vec<f32>,
+    /// Clip counts of each channel as the source reports them, and the counts
+    /// at the last reset.
+    pub clips: Vec<u64>,
+    pub clip_base: Vec<u64>,
     pub last_spectrum: Option<Spectrum>,
     pub sample_rate: u32,
     pub device_name: String,
     pub linear_mode: bool,
+    /// Levels in dBFS at the bottom and top of the dB spectrum.
+    pub db_floor: f32,
+    pub db_ceiling: f32,
+    /// Analyzer settings, including changes made at runtime.
+    pub analyzer: AnalyzerConfig,
+    pub profile: Option<String>,
+    pub theme: Theme,
+    pub layout: LayoutConfig,
+    pub keys: KeyMap,
+    pub picker: Option<DevicePicker>,
+    /// Device chosen in the picker, waiting for the main loop to switch to it.
+    pub requested_device: Option<DeviceEntry>,
+    /// Set while analyzing a file rather than a live device.
+    pub playback: Option<PlaybackControl>,
+    /// Analyzer changes made at runtime, waiting for the main loop to pass them on.
+    pub analyzer_commands: Vec<AnalyzerCommand>,
+    pub waterfall: Waterfall,
+    pub colormap: Colormap,
+    pub scope: Scope,
+    pub stereo: StereoImage,
+    pub loudness_target: LoudnessTarget,
+    pub loudness_history: LoudnessHistory,
+    pub peaks: SpectrumPeaks,
+    /// Frequency of A4 in Hz, the reference for note names.
+    pub a4: f32,
+    /// The latest detected pitch and the time of its frame, held briefly
+    /// in the tuner when the note fades.
+    pub last_pitch: Option<(Pitch, f64)>,
+    /// Time of the frame of the latest beat, for the flash in the title.
+    pub last_beat: Option<f64>,
+    pub feature_history: FeatureHistory,
+    /// Blocks of samples the analyzer fell too far behind to take.
+    pub dropped_blocks: u64,
+    pub channel_view: ChannelView,
+    /// Draw the two channels of the view in separate charts instead of overlaid.
+    pub split_channels: bool,
 }
 
 impl App {
-    pub fn new(sample_rate: u32, device_name: String) -> App {
+    pub fn new(sample_rate: u32, device_name: String, settings: &Settings) -> App {
+        let mut scope = Scope::default();
+        scope.set_sample_rate(sample_rate);
+        let mut stereo = StereoImage::default();
+        stereo.set_sample_rate(sample_rate);
         App {
             should_quit: false,
-            last_rms: 0.0,
-            peak_hold: 0.0,
+            channel_rms: Vec::new(),
+            ballistics: settings.ballistics,
+            level_meters: Vec::new(),
+            meter_weighting: Vec::new(),
+            true_peak_hold: Vec::new(),
+            true_peak_max: Vec::new(),
+            clips: Vec::new(),
+            clip_base: Vec::new(),
             last_spectrum: None,
             sample_rate,
             device_name,
-            linear_mode: false, // Start with dB mode
+            linear_mode: settings.linear,
+            db_floor: settings.db_floor,
+            db_ceiling: settings.db_ceiling,
+            analyzer: settings.analyzer.clone(),
+            profile: settings.profile.clone(),
+            theme: settings.theme.clone(),
+            layout: settings.layout.clone(),
+            keys: settings.keys.clone(),
+            picker: None,
+            requested_device: None,
+            playback: None,
+            analyzer_commands: Vec::new(),
+            waterfall: Waterfall::new(settings.waterfall_history),
+            colormap: settings.colormap,
+            scope,
+            stereo,
+            loudness_target: settings.loudness_target,
+            loudness_history: LoudnessHistory::default(),
+            peaks: SpectrumPeaks::new(settings.peak_hold, settings.peak_fall),
+            a4: settings.a4,
+            last_pitch: None,
+            last_beat: None,
+            feature_history: FeatureHistory::default(),
+            dropped_blocks: 0,
+            channel_view: ChannelView::default(),
+            split_channels: false,
         }
     }
 
-    pub fn update_rms(&mut self, rms: f32) {
-        self.last_rms = rms;
-        self.peak_hold = self.peak_hold.max(rms);
+    /// Resets the displayed levels for a newly opened device.
+    pub fn set_device(&mut self, device_name: String, sample_rate: u32) {
+        self.device_name = device_name;
+        self.sample_rate = sample_rate;
+        self.channel_rms.clear();
+        self.level_meters.clear();
+        self.meter_weighting.clear();
+        self.true_peak_hold.clear();
+        self.true_peak_max.clear();
+        self.clips.clear();
+        self.clip_base.clear();
+        self.last_spectrum = None;
+        self.playback = None;
+        self.waterfall.clear();
+        self.scope.set_sample_rate(sample_rate);
+        self.stereo.set_sample_rate(sample_rate);
+        self.loudness_history.restart();
+        self.peaks.clear();
+        self.last_pitch = None;
+        self.last_beat = None;
+        self.feature_history.clear();
+        self.dropped_blocks = 0;
     }
 
-    pub fn update_spectrum(&mut self, spectrum: Spectrum) {
-        self.last_spectrum = Some(spectrum);
+    pub fn open_device_picker(&mut self) {
+        self.picker = Some(DevicePicker::new(&self.device_name));
+    }
+
+    fn set_resolution(&mut self, fft_size: usize, hop: usize) {
+        self.analyzer.fft_size = fft_size;
+        self.analyzer.hop = hop;
+        self.analyzer_commands
+            .push(AnalyzerCommand::Resolution { fft_size, hop });
+    }
+
+    /// Maps a band value to 0.0-1.0 of the spectrum height for the current mode.
+    fn display_level(&self, value: f32) -> f32 {
+        if self.linear_mode {
+            value.clamp(0.0, 1.0)
+        } else {
+            ((value - self.db_floor) / (self.db_ceiling - self.db_floor)).clamp(0.0, 1.0)
+        }
+    }
+
+    /// Maps a band level in dBFS to 0.0-1.0 of the spectrum height for the
+    /// current mode.
+    fn display_db(&self, db: f32) -> f32 {
+        if self.linear_mode {
+            self.display_level(10f32.powf(db / 20.0))
+        } else {
+            self.display_level(db)
+        }
+    }
+
+    pub fn update_levels(&mut self, levels: &Levels) {
+        // The held true peak falls like a digital peak meter
+        let secs = levels.frames as f32 / self.sample_rate.max(1) as f32;
+        let release = 10f32.powf(-DIGITAL_RETURN_DB_PER_SEC * secs / 20.0);
+        let channels = levels.true_peak.len();
+        self.true_peak_hold.resize(channels, 0.0);
+        self.true_peak_max.resize(channels, 0.0);
+        for (i, &peak) in levels.true_peak.iter().enumerate() {
+            self.true_peak_hold[i] = (self.true_peak_hold[i] * release).max(peak);
+            self.true_peak_max[i] = self.true_peak_max[i].max(peak);
+        }
+        self.clips.clone_from(&levels.clips);
+        self.clip_base.resize(self.clips.len(), 0);
+    }
+
+    /// Runs the first two channels through the weighting and the level
+    /// meters, and measures their weighted RMS.
+    pub fn update_meters(&mut self, block: &StereoBlock) {
+        if self.sample_rate == 0 {
+            return;
+        }
+        let channels = if block.right.is_empty() { 1 } else { 2 };
+        if self.level_meters.len() != channels {
+            self.reset_meters(channels);
+        }
+        self.channel_rms.clear();
+        for ((meter, weighting), samples) in self
+            .level_meters
+            .iter_mut()
+            .zip(&mut self.meter_weighting)
+            .zip([&block.left, &block.right])
+        {
+            let mut samples = samples.clone();
+            weighting.process(&mut samples);
+            meter.push(&samples);
+            let sum_sq: f32 = samples.iter().map(|s| s * s).sum();
+            self.channel_rms
+                .push((sum_sq / samples.len().max(1) as f32).sqrt());
+        }
+    }
+
+    /// Starts the level meters over with the current ballistics and weighting.
+    fn reset_meters(&mut self, channels: usize) {
+        let sample_rate = self.sample_rate as f32;
+        self.level_meters = (0..channels)
+            .map(|_| BallisticMeter::new(self.ballistics, sample_rate))
+            .collect();
+        self.meter_weighting = (0..channels)
+            .map(|_| WeightingFilter::new(self.analyzer.weighting, sample_rate))
+            .collect();
+    }
+
+    /// Clips on each channel since the last reset.
+    fn clips_since_reset(&self) -> impl Iterator<Item = u64> + '_ {
+        self.clips
+            .iter()
+            .zip(&self.clip_base)
+            .map(|(&clips, &base)| clips - base)
     }
 
-    pub fn decay_peak(&mut self, dt: f32) {
-        let decay_per_sec = 0.90f32;
-        self.peak_hold *= decay_per_sec.powf(dt);
+    /// Clears the highest true peaks and the clip indicator.
+    fn reset_peaks(&mut self) {
+        self.true_peak_max.fill(0.0);
+        self.clip_base.clone_from(&self.clips);
+    }
+
+    /// A channel's band values for the current mode.
+    fn bands<'a>(&self, channel: &'a ChannelSpectrum) -> &'a [f32] {
+        if self.linear_mode {
+            &channel.bands_linear
+        } else {
+            &channel.bands
+        }
+    }
+
+    pub fn update_spectrum(&mut self, spectrum: Spectrum) {
+        self.waterfall.push(&spectrum);
+        self.loudness_history.push(spectrum.time, spectrum.loudness);
+        self.peaks.push(&spectrum);
+        if let Some(pitch) = spectrum.pitch {
+            self.last_pitch = Some((pitch, spectrum.time));
+        }
+        if spectrum.beat {
+            self.last_beat = Some(spectrum.time);
+        }
+        self.feature_history.push(spectrum.features);
+        self.last_spectrum = Some(spectrum);
     }
 }
 
+const SEEK_STEP_SECS: f32 = 5.0;
+const DB_STEP: f32 = 5.0;
+/// Smallest span between the dB floor and ceiling.
+const MIN_DB_RANGE: f32 = 10.0;
+/// FFT size / hop for 0%, 50%, 75% and 87.5% overlap.
+const OVERLAP_DIVISORS: [usize; 4] = [1, 2, 4, 8];
+
 pub type TerminalType = Terminal<CrosstermBackend<Stdout>>;
 
 pub fn init_terminal() -> Result<TerminalType, anyhow::Error> {
@@ -76,48 +395,243 @@ pub fn restore_terminal() -> Result<(), anyhow::Error> {
 }
 
 pub fn handle_events(app: &mut App) -> Result<(), anyhow::Error> {
-    if event::poll(Duration::from_millis(0))? {
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        app.should_quit = true;
-                    }
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        app.should_quit = true;
-                    }
-                    KeyCode::Char('l') | KeyCode::Char('L') => {
-                        app.linear_mode = !app.linear_mode;
-                    }
-                    _ => {}
+    if event::poll(Duration::from_millis(0))?
+        && let Event::Key(key) = event::read()?
+        && key.kind == KeyEventKind::Press
+    {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            app.should_quit = true;
+            return Ok(());
+        }
+        if app.picker.is_some() {
+            handle_picker_key(app, key.code);
+            return Ok(());
+        }
+        match app.keys.action(key.code) {
+            Some(Action::Quit) => {
+                app.should_quit = true;
+            }
+            Some(Action::ToggleLinear) => {
+                app.linear_mode = !app.linear_mode;
+            }
+            Some(Action::SelectDevice) => {
+                app.open_device_picker();
+            }
+            Some(Action::TogglePause) => {
+                if let Some(playback) = &app.playback {
+                    playback.toggle_pause();
+                }
+            }
+            Some(Action::SeekBackward) => {
+                if let Some(playback) = &app.playback {
+                    playback.seek_by(-SEEK_STEP_SECS);
+                    // The analyzer restarts its loudness at the new position
+                    app.loudness_history.clear();
+                }
+            }
+            Some(Action::SeekForward) => {
+                if let Some(playback) = &app.playback {
+                    playback.seek_by(SEEK_STEP_SECS);
+                    app.loudness_history.clear();
+                }
+            }
+            Some(Action::ToggleLoop) => {
+                if let Some(playback) = &app.playback {
+                    playback.toggle_loop();
                 }
             }
+            Some(Action::CycleWindow) => {
+                app.analyzer.window = app.analyzer.window.next();
+                app.analyzer_commands
+                    .push(AnalyzerCommand::Window(app.analyzer.window));
+            }
+            // Resizing keeps the overlap
+            Some(Action::IncreaseFftSize) => {
+                let AnalyzerConfig { fft_size, hop, .. } = app.analyzer;
+                if fft_size * 2 <= MAX_FFT_SIZE {
+                    app.set_resolution(fft_size * 2, hop * 2);
+                }
+            }
+            Some(Action::DecreaseFftSize) => {
+                let AnalyzerConfig { fft_size, hop, .. } = app.analyzer;
+                if fft_size / 2 >= MIN_FFT_SIZE {
+                    app.set_resolution(fft_size / 2, (hop / 2).max(1));
+                }
+            }
+            Some(Action::RaiseCeiling) => {
+                app.db_ceiling = (app.db_ceiling + DB_STEP).min(MAX_DB_CEILING);
+            }
+            Some(Action::LowerCeiling) => {
+                app.db_ceiling = (app.db_ceiling - DB_STEP).max(app.db_floor + MIN_DB_RANGE);
+            }
+            Some(Action::RaiseFloor) => {
+                app.db_floor = (app.db_floor + DB_STEP).min(app.db_ceiling - MIN_DB_RANGE);
+            }
+            Some(Action::LowerFloor) => {
+                app.db_floor = (app.db_floor - DB_STEP).max(MIN_DBFS);
+            }
+            Some(Action::CycleOverlap) => {
+                let AnalyzerConfig { fft_size, hop, .. } = app.analyzer;
+                let divisor = OVERLAP_DIVISORS
+                    .into_iter()
+                    .find(|&d| d > fft_size / hop)
+                    .unwrap_or(OVERLAP_DIVISORS[0]);
+                app.set_resolution(fft_size, fft_size / divisor);
+            }
+            Some(Action::CycleScale) => {
+                app.analyzer.scale = app.analyzer.scale.next();
+                app.analyzer_commands
+                    .push(AnalyzerCommand::Scale(app.analyzer.scale));
+            }
+            Some(Action::ToggleWaterfall) => {
+                app.layout.waterfall = !app.layout.waterfall;
+            }
+            Some(Action::CycleColormap) => {
+                app.colormap = app.colormap.next();
+            }
+            Some(Action::IncreaseHistory) => {
+                let history = (app.waterfall.capacity() * 2).min(MAX_HISTORY);
+                app.waterfall.set_capacity(history);
+            }
+            Some(Action::DecreaseHistory) => {
+                let history = (app.waterfall.capacity() / 2).max(MIN_HISTORY);
+                app.waterfall.set_capacity(history);
+            }
+            Some(Action::ToggleOscilloscope) => {
+                app.layout.oscilloscope = !app.layout.oscilloscope;
+            }
+            Some(Action::ToggleTriggerEdge) => {
+                app.scope.edge = app.scope.edge.toggle();
+            }
+            Some(Action::RaiseTrigger) => {
+                app.scope.level = (app.scope.level + TRIGGER_STEP).min(1.0);
+            }
+            Some(Action::LowerTrigger) => {
+                app.scope.level = (app.scope.level - TRIGGER_STEP).max(-1.0);
+            }
+            Some(Action::IncreaseTimebase) => app.scope.longer_timebase(),
+            Some(Action::DecreaseTimebase) => app.scope.shorter_timebase(),
+            Some(Action::CycleChannels) => {
+                app.channel_view = app.channel_view.next();
+            }
+            Some(Action::ToggleSplit) => {
+                app.split_channels = !app.split_channels;
+            }
+            Some(Action::ToggleGoniometer) => {
+                app.layout.goniometer = !app.layout.goniometer;
+            }
+            Some(Action::ToggleCorrelation) => {
+                app.layout.correlation = !app.layout.correlation;
+            }
+            Some(Action::ToggleBalance) => {
+                app.layout.balance = !app.layout.balance;
+            }
+            Some(Action::ToggleLoudness) => {
+                app.layout.loudness = !app.layout.loudness;
+            }
+            Some(Action::ResetLoudness) => {
+                app.loudness_history.clear();
+                app.analyzer_commands.push(AnalyzerCommand::ResetLoudness);
+            }
+            Some(Action::CycleLoudnessTarget) => {
+                app.loudness_target = app.loudness_target.next();
+            }
+            Some(Action::ResetPeaks) => {
+                app.reset_peaks();
+            }
+            Some(Action::ToggleMaxHold) => {
+                app.layout.max_hold = !app.layout.max_hold;
+            }
+            Some(Action::ToggleMinHold) => {
+                app.layout.min_hold = !app.layout.min_hold;
+            }
+            Some(Action::ResetHolds) => {
+                app.peaks.reset_traces();
+            }
+            Some(Action::CycleBallistics) => {
+                app.ballistics = app.ballistics.next();
+                app.reset_meters(app.level_meters.len());
+            }
+            Some(Action::CycleAveraging) => {
+                app.analyzer.averaging = app.analyzer.averaging.next();
+                app.analyzer_commands
+                    .push(AnalyzerCommand::Averaging(app.analyzer.averaging));
+            }
+            Some(Action::ResetAverage) => {
+                app.analyzer_commands.push(AnalyzerCommand::ResetAverage);
+            }
+            Some(Action::CycleWeighting) => {
+                app.analyzer.weighting = app.analyzer.weighting.next();
+                app.analyzer_commands
+                    .push(AnalyzerCommand::Weighting(app.analyzer.weighting));
+                app.reset_meters(app.level_meters.len());
+            }
+            Some(Action::ToggleTuner) => {
+                app.layout.tuner = !app.layout.tuner;
+            }
+            Some(Action::ToggleNoteLabels) => {
+                app.layout.note_labels = !app.layout.note_labels;
+            }
+            Some(Action::ToggleChroma) => {
+                app.layout.chroma = !app.layout.chroma;
+            }
+            Some(Action::ToggleTempo) => {
+                app.layout.tempo = !app.layout.tempo;
+            }
+            Some(Action::ToggleFeatures) => {
+                app.layout.features = !app.layout.features;
+            }
+            None => {}
         }
     }
     Ok(())
 }
 
-fn create_color_gradient(position: f32) -> Color {
+fn handle_picker_key(app: &mut App, code: KeyCode) {
+    let close_key = app.keys.action(code) == Some(Action::SelectDevice);
+    let Some(picker) = app.picker.as_mut() else {
+        return;
+    };
+    match code {
+        KeyCode::Esc => app.picker = None,
+        _ if close_key => app.picker = None,
+        KeyCode::Up | KeyCode::Char('k') => picker.state.select_previous(),
+        KeyCode::Down | KeyCode::Char('j') => picker.state.select_next(),
+        KeyCode::Home => picker.state.select_first(),
+        KeyCode::End => picker.state.select_last(),
+        KeyCode::Enter => {
+            app.requested_device = picker.selected().cloned();
+        }
+        _ => {}
+    }
+}
+
+fn create_color_gradient(stops: &[Color], position: f32) -> Color {
     let pos = position.clamp(0.0, 1.0);
 
-    if pos < 0.33 {
-        let t = pos / 0.33;
-        let r = (64.0 + (128.0 - 64.0) * t) as u8;
-        let g = (224.0 + (160.0 - 224.0) * t) as u8;
-        let b = (208.0 + (128.0 - 208.0) * t) as u8;
-        Color::Rgb(r, g, b)
-    } else if pos < 0.66 {
-        let t = (pos - 0.33) / 0.33;
-        let r = (128.0 + (64.0 - 128.0) * t) as u8;
-        let g = (160.0 + (224.0 - 160.0) * t) as u8;
-        let b = (128.0 + (224.0 - 128.0) * t) as u8;
-        Color::Rgb(r, g, b)
-    } else {
-        let t = (pos - 0.66) / 0.34;
-        let r = (64.0 + (128.0 - 64.0) * t) as u8;
-        let g = (224.0 + (96.0 - 224.0) * t) as u8;
-        let b = (224.0 + (160.0 - 224.0) * t) as u8;
-        Color::Rgb(r, g, b)
+    match stops {
+        [] => Color::Reset,
+        [only] => *only,
+        _ => {
+            // Stops are spread evenly over 0.0-1.0
+            let scaled = pos * (stops.len() - 1) as f32;
+            let i = (scaled.floor() as usize).min(stops.len() - 2);
+            let t = scaled - i as f32;
+            match (stops[i], stops[i + 1]) {
+                (Color::Rgb(r0, g0, b0), Color::Rgb(r1, g1, b1)) => {
+                    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+                    Color::Rgb(lerp(r0, r1), lerp(g0, g1), lerp(b0, b1))
+                }
+                // Named colors can't be blended, switch halfway instead
+                (a, b) => {
+                    if t < 0.5 {
+                        a
+                    } else {
+                        b
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -134,296 +648,2027 @@ pub fn draw_ui(f: &mut Frame, app: &App) {
         return;
     }
 
+    let meters_height = meters_height(app);
+    let mut constraints = vec![Constraint::Length(3)]; // Title
+    if meters_height > 0 {
+        constraints.push(Constraint::Length(meters_height));
+    }
+    constraints.push(Constraint::Min(10)); // EQ spectrum
+    if app.layout.waterfall {
+        constraints.push(Constraint::Percentage(40));
+    }
+    if app.layout.frequency_labels {
+        constraints.push(Constraint::Length(if app.layout.note_labels {
+            4
+        } else {
+            3
+        }));
+    }
+    if app.layout.oscilloscope {
+        constraints.push(Constraint::Percentage(30));
+    }
+    if app.layout.status_bar {
+        constraints.push(Constraint::Length(5));
+    }
+
     let main_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3), // Title
-            Constraint::Length(4), // RMS meter
-            Constraint::Min(10),   // EQ spectrum
-            Constraint::Length(3), // Frequency labels
-            Constraint::Length(5), // Status bar
-        ])
+        .constraints(constraints)
         .split(size);
+    let mut areas = main_layout.iter().copied();
+
+    draw_title(f, areas.next().unwrap(), app);
+    if meters_height > 0 {
+        draw_meters(f, areas.next().unwrap(), app);
+    }
+    draw_eq_spectrum(f, areas.next().unwrap(), app);
+    if app.layout.waterfall {
+        draw_waterfall(f, areas.next().unwrap(), app);
+    }
+    if app.layout.frequency_labels {
+        draw_frequency_labels(f, areas.next().unwrap(), app);
+    }
+    if app.layout.oscilloscope {
+        draw_oscilloscope(f, areas.next().unwrap(), app);
+    }
+    if app.layout.status_bar {
+        draw_status_bar(f, areas.next().unwrap(), app);
+    }
 
-    draw_title(f, main_layout[0]);
-    draw_rms_meter(f, main_layout[1], app);
-    draw_eq_spectrum(f, main_layout[2], app);
-    draw_frequency_labels(f, main_layout[3], app);
-    draw_status_bar(f, main_layout[4], app);
+    if let Some(picker) = &app.picker {
+        draw_device_picker(f, size, app, picker);
+    }
 }
 
-fn draw_title(f: &mut Frame, area: Rect) {
-    let title = Paragraph::new("🎵 Selara Audio Visualizer")
-        .style(
-            Style::default()
-                .fg(Color::Rgb(128, 224, 208))
-                .add_modifier(Modifier::BOLD),
+fn draw_device_picker(f: &mut Frame, size: Rect, app: &App, picker: &DevicePicker) {
+    let area = Rect {
+        x: size.x + size.width / 10,
+        y: size.y + size.height / 8,
+        width: size.width - size.width / 5,
+        height: size.height - size.height / 4,
+    };
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Select Device (Enter to switch, ESC to close) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(inner);
+
+    let items: Vec<ListItem> = picker
+        .entries
+        .iter()
+        .map(|entry| {
+            let kind = match entry.kind {
+                DeviceKind::Output => "out",
+                DeviceKind::Input => "in ",
+            };
+            let current = if entry.name == app.device_name {
+                "*"
+            } else {
+                " "
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}{} ", current, kind),
+                    Style::default().fg(app.theme.key),
+                ),
+                Span::styled(
+                    format!("[{}] ", entry.host.name()),
+                    Style::default().fg(app.theme.label),
+                ),
+                Span::styled(entry.name.clone(), Style::default().fg(app.theme.value)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::RIGHT)
+                .border_style(Style::default().fg(app.theme.border)),
         )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = picker.state.clone();
+    f.render_stateful_widget(list, columns[0], &mut state);
+
+    let label = Style::default().fg(app.theme.label);
+    let value = Style::default().fg(app.theme.value);
+    let mut details = Vec::new();
+    if let Some(error) = &picker.error {
+        details.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+        details.push(Line::from(""));
+    }
+    match picker.selected() {
+        Some(entry) => {
+            details.push(Line::from(vec![
+                Span::styled("Host: ", label),
+                Span::styled(entry.host.name(), value),
+            ]));
+            details.push(Line::from(vec![
+                Span::styled("Device: ", label),
+                Span::styled(entry.name.clone(), value),
+            ]));
+            let default = entry
+                .default_config
+                .as_ref()
+                .map(describe_config)
+                .unwrap_or_else(|| "none".to_string());
+            details.push(Line::from(vec![
+                Span::styled("Default: ", label),
+                Span::styled(default, value),
+            ]));
+            details.push(Line::from(Span::styled("Supported configs:", label)));
+            for range in &entry.supported_configs {
+                details.push(Line::from(Span::styled(
+                    format!("  {}", describe_config_range(range)),
+                    Style::default().fg(app.theme.text),
+                )));
+            }
+        }
+        None => details.push(Line::from(Span::styled(
+            "No audio devices found",
+            Style::default().fg(app.theme.dim),
+        ))),
+    }
+    let details_area = Rect {
+        x: columns[1].x + 1,
+        width: columns[1].width.saturating_sub(1),
+        ..columns[1]
+    };
+    f.render_widget(Paragraph::new(details), details_area);
+}
+
+/// Seconds the beat indicator stays lit after a beat.
+const BEAT_FLASH_SECS: f64 = 0.1;
+
+fn draw_title(f: &mut Frame, area: Rect, app: &App) {
+    let mut spans = vec![Span::styled(
+        "🎵 Selara Audio Visualizer",
+        Style::default()
+            .fg(app.theme.title)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if app.layout.tempo {
+        spans.extend(tempo_spans(app));
+    }
+    let title = Paragraph::new(Line::from(spans))
         .alignment(Alignment::Center)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Rgb(96, 160, 192))),
+                .border_style(Style::default().fg(app.theme.border)),
         );
     f.render_widget(title, area);
 }
 
+/// The beat indicator, lit on each beat, and the tempo with its confidence.
+fn tempo_spans(app: &App) -> Vec<Span<'static>> {
+    let label = Style::default().fg(app.theme.label);
+    let spectrum = app.last_spectrum.as_ref();
+    let lit = spectrum
+        .zip(app.last_beat)
+        .is_some_and(|(s, beat)| (0.0..BEAT_FLASH_SECS).contains(&(s.time - beat)));
+    let indicator = if lit {
+        Span::styled(
+            "●",
+            Style::default()
+                .fg(app.theme.key)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::styled("○", Style::default().fg(app.theme.dim))
+    };
+    let mut spans = vec![Span::styled("  ·  ", label), indicator, Span::raw(" ")];
+    match spectrum.and_then(|s| s.tempo) {
+        Some(tempo) => spans.extend([
+            Span::styled(
+                format!("{:.1}", tempo.bpm),
+                Style::default().fg(app.theme.value),
+            ),
+            Span::styled(format!(" BPM ({:.0}%)", 100.0 * tempo.confidence), label),
+        ]),
+        None => spans.push(Span::styled("-- BPM", Style::default().fg(app.theme.dim))),
+    }
+    spans
+}
+
+/// A meter panel and the rows it needs.
+type MeterPanel = (fn(&mut Frame, Rect, &App), u16);
+
+/// Rows of the loudness panel: the readings and their history.
+const LOUDNESS_HEIGHT: u16 = 8;
+/// Loudness shown in the history graph, relative to the target.
+const LOUDNESS_GRAPH_RANGE: [f32; 2] = [-30.0, 10.0];
+/// Rows of the correlation panel: the meter and its history.
+const CORRELATION_HEIGHT: u16 = 7;
+const BALANCE_HEIGHT: u16 = 3;
+/// Rows of the tuner: the reading and the cents gauge.
+const TUNER_HEIGHT: u16 = 4;
+/// Seconds the tuner keeps showing a note after it is last detected.
+const TUNER_HOLD_SECS: f64 = 1.0;
+/// Cents from a note that count as in tune.
+const IN_TUNE_CENTS: f32 = 5.0;
+/// Rows of the chroma panel: the bars and the note names under them.
+const CHROMA_HEIGHT: u16 = 8;
+/// Rows of the features panel: one per feature.
+const FEATURES_HEIGHT: u16 = 9;
+/// Columns of a feature's name and of its reading.
+const FEATURE_LABEL_WIDTH: u16 = 10;
+const FEATURE_VALUE_WIDTH: u16 = 10;
+/// Smallest goniometer, in rows.
+const GONIOMETER_HEIGHT: u16 = 12;
+
+/// The visible panels stacked left of the goniometer, top to bottom.
+fn meter_panels(app: &App) -> Vec<MeterPanel> {
+    let mut panels: Vec<MeterPanel> = Vec::new();
+    if app.layout.rms_meter {
+        // Text, true peak, the gauges and their scale
+        panels.push((draw_rms_meter, 5 + meter_gauges(app) as u16));
+    }
+    if app.layout.loudness {
+        panels.push((draw_loudness, LOUDNESS_HEIGHT));
+    }
+    if app.layout.correlation {
+        panels.push((draw_correlation, CORRELATION_HEIGHT));
+    }
+    if app.layout.balance {
+        panels.push((draw_balance, BALANCE_HEIGHT));
+    }
+    if app.layout.tuner {
+        panels.push((draw_tuner, TUNER_HEIGHT));
+    }
+    if app.layout.chroma {
+        panels.push((draw_chroma, CHROMA_HEIGHT));
+    }
+    if app.layout.features {
+        panels.push((draw_features, FEATURES_HEIGHT));
+    }
+    panels
+}
+
+fn meters_height(app: &App) -> u16 {
+    let stacked = meter_panels(app).iter().map(|&(_, height)| height).sum();
+    if app.layout.goniometer {
+        GONIOMETER_HEIGHT.max(stacked)
+    } else {
+        stacked
+    }
+}
+
+/// Draws the level and stereo meters, with the goniometer on the right.
+fn draw_meters(f: &mut Frame, area: Rect, app: &App) {
+    let panels = meter_panels(app);
+    let mut column = area;
+    if app.layout.goniometer {
+        // Braille dots are about square: two per column, four per row
+        let width = (2 * area.height.saturating_sub(2) + 2).min(area.width);
+        let x = if panels.is_empty() {
+            area.x + (area.width - width) / 2
+        } else {
+            area.x + area.width - width
+        };
+        draw_goniometer(f, Rect { x, width, ..area }, app);
+        column.width -= width;
+    }
+    if panels.is_empty() {
+        return;
+    }
+
+    // The last panel takes any rows the goniometer adds
+    let constraints: Vec<Constraint> = panels
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, height))| {
+            if i + 1 == panels.len() {
+                Constraint::Min(height)
+            } else {
+                Constraint::Length(height)
+            }
+        })
+        .collect();
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(column);
+    for ((draw, _), area) in panels.iter().zip(areas.iter()) {
+        draw(f, *area, app);
+    }
+}
+
 fn draw_rms_meter(f: &mut Frame, area: Rect, app: &App) {
+    let scale = app.ballistics.scale();
+    let mut title = format!(" Level · {} ", app.ballistics);
+    if app.analyzer.weighting != Weighting::Z {
+        title.push_str(&format!("· {}-weighted ", app.analyzer.weighting));
+    }
     let rms_block = Block::default()
-        .title(" RMS Level ")
+        .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Rgb(96, 160, 192)));
+        .border_style(Style::default().fg(app.theme.border));
 
     let inner = rms_block.inner(area);
     f.render_widget(rms_block, area);
 
-    let gain = 2.0f32;
-    let level = (app.last_rms * gain).clamp(0.0, 1.0);
-    let peak_level = (app.peak_hold * gain).clamp(0.0, 1.0);
-
-    let gauge_color = create_color_gradient(level);
-
-    let rms_layout = Layout::default()
+    let gauges = meter_gauges(app);
+    let rows = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(1), Constraint::Length(1)])
+        .constraints(vec![Constraint::Length(1); 3 + gauges])
         .split(inner);
 
-    // Text above the gauge
-    let rms_text = Paragraph::new(format!(
-        "RMS: {:.3} | Peak: {:.3}",
-        app.last_rms, app.peak_hold
-    ))
-    .style(Style::default().fg(Color::Rgb(200, 200, 200)))
-    .alignment(Alignment::Center);
-    f.render_widget(rms_text, rms_layout[0]);
-
-    // Gauge without label or percentage
-    let gauge = Gauge::default()
-        .block(Block::default())
-        .gauge_style(Style::default().fg(gauge_color))
-        .ratio(level as f64)
-        .label("");
-
-    f.render_widget(gauge, rms_layout[1]);
+    // Text above the gauges
+    let rms = match app.channel_rms[..] {
+        [left, right] => format!("RMS: L {:.3}  R {:.3}", left, right),
+        [rms] => format!("RMS: {:.3}", rms),
+        _ => "RMS: 0.000".to_string(),
+    };
+    let reading = |meter: &BallisticMeter| {
+        let units = scale.units(meter.level());
+        if units < scale.min {
+            "--".to_string()
+        } else {
+            format!("{:+.1}", units)
+        }
+    };
+    let text = match &app.level_meters[..] {
+        [left, right] => format!(
+            "{} | Level: L {}  R {} {}",
+            rms,
+            reading(left),
+            reading(right),
+            scale.unit
+        ),
+        [meter] => format!("{} | Level: {} {}", rms, reading(meter), scale.unit),
+        _ => rms,
+    };
+    let rms_text = Paragraph::new(text)
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center);
+    f.render_widget(rms_text, rows[0]);
+    draw_true_peak(f, rows[1], app);
 
-    if peak_level > 0.01 && rms_layout[1].width > 4 {
-        let peak_pos = ((peak_level * (rms_layout[1].width - 4) as f32) as u16 + 1)
-            .min(rms_layout[1].width - 2);
-        let peak_area = Rect {
-            x: rms_layout[1].x + peak_pos,
-            y: rms_layout[1].y,
-            width: 1,
-            height: 1,
-        };
-        let peak_indicator = Paragraph::new("│").style(
-            Style::default()
-                .fg(Color::Rgb(255, 255, 255))
-                .add_modifier(Modifier::BOLD),
+    // Gauges and the scale leave a column for the channel labels
+    let gauge_column = |row: Rect| Rect {
+        x: row.x + 2,
+        width: row.width.saturating_sub(2),
+        ..row
+    };
+    let labels: &[(&str, Color)] = if gauges > 1 {
+        &[("L", app.theme.left), ("R", app.theme.right)]
+    } else {
+        &[("", app.theme.text)]
+    };
+    for (i, &(label, color)) in labels.iter().enumerate() {
+        let row = rows[i + 2];
+        f.render_widget(
+            Paragraph::new(label).style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Rect { width: 2, ..row },
+        );
+        let (level, hold) = app
+            .level_meters
+            .get(i)
+            .map_or((0.0, 0.0), |meter| (meter.level(), meter.hold()));
+        draw_level_gauge(
+            f,
+            gauge_column(row),
+            scale.ratio(level),
+            scale.ratio(hold),
+            app,
         );
-        f.render_widget(peak_indicator, peak_area);
     }
+    draw_meter_scale(f, gauge_column(rows[2 + gauges]), scale, app);
 }
 
-fn draw_eq_spectrum(f: &mut Frame, area: Rect, app: &App) {
-    let mode_str = if app.linear_mode { "Linear" } else { "dB" };
-    let title = format!(" EQ Spectrum ({}) ", mode_str);
-    let eq_block = Block::default()
-        .title(title)
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Rgb(96, 160, 192)));
-
-    if let Some(ref spectrum) = app.last_spectrum {
-        let inner = eq_block.inner(area);
-        f.render_widget(eq_block, area);
+/// Gauges in the level meter: one per side for stereo sources.
+fn meter_gauges(app: &App) -> usize {
+    app.level_meters.len().clamp(1, 2)
+}
 
-        let max_bars = (inner.width as usize - 2) / 2;
-
-        let bars: Vec<Bar> = (0..max_bars)
-            .map(|i| {
-                // Use logarithmic mapping to match frequency distribution
-                let t = i as f32 / (max_bars - 1) as f32;
-                let band_idx_f = t * (spectrum.bands.len() - 1) as f32;
-                
-                // Interpolate between adjacent bands for smoother display
-                let band_idx_low = band_idx_f.floor() as usize;
-                let band_idx_high = (band_idx_low + 1).min(spectrum.bands.len() - 1);
-                let frac = band_idx_f - band_idx_low as f32;
-
-                // Use appropriate data based on mode
-                let (bands_data, _linear_data) = if app.linear_mode {
-                    (&spectrum.bands_linear, &spectrum.bands_linear)
-                } else {
-                    (&spectrum.bands, &spectrum.bands_linear)
-                };
+/// Column of a gauge `width` cells wide where a 0.0-1.0 ratio falls.
+fn gauge_column(ratio: f32, width: u16) -> u16 {
+    ((ratio * width as f32) as u16).min(width.saturating_sub(1))
+}
 
-                let level_low = bands_data[band_idx_low];
-                let level_high = bands_data[band_idx_high];
-                let level = level_low + frac * (level_high - level_low);
+/// Labels the marks of a meter scale under its gauges, leaving out labels
+/// that would overlap their neighbours.
+fn draw_meter_scale(f: &mut Frame, area: Rect, scale: &MeterScale, app: &App) {
+    let width = area.width as usize;
+    if width == 0 {
+        return;
+    }
+    let mut line = vec![' '; width];
+    let mut free_from = 0;
+    for &(position, label) in scale.marks {
+        let ratio = (position - scale.min) / (scale.max - scale.min);
+        let column = gauge_column(ratio, area.width) as usize;
+        let len = label.chars().count();
+        let start = column
+            .saturating_sub(len / 2)
+            .min(width.saturating_sub(len));
+        if start < free_from || start + len > width {
+            continue;
+        }
+        for (cell, c) in line[start..].iter_mut().zip(label.chars()) {
+            *cell = c;
+        }
+        free_from = start + len + 1;
+    }
+    f.render_widget(
+        Paragraph::new(line.into_iter().collect::<String>())
+            .style(Style::default().fg(app.theme.dim)),
+        area,
+    );
+}
 
-                let height = (level * 100.0) as u64;
-                Bar::default()
-                    .value(height)
-                    .text_value(String::new())
-                    .style(Style::default().fg(create_color_gradient(level)))
-            })
-            .collect();
+/// Draws the held and highest true peak of the first two channels, and the
+/// clip indicator, lit from the first clip until the peaks are reset.
+fn draw_true_peak(f: &mut Frame, area: Rect, app: &App) {
+    let label = Style::default().fg(app.theme.dim);
+    let dbtp = |linear: f32| {
+        let db = 20.0 * linear.log10();
+        let color = if linear >= CLIP_LEVEL {
+            app.theme.peak
+        } else {
+            app.theme.value
+        };
+        if db > MIN_DBFS {
+            Span::styled(format!("{:.1}", db), Style::default().fg(color))
+        } else {
+            Span::styled("--", Style::default().fg(app.theme.dim))
+        }
+    };
 
-        let barchart = BarChart::default()
-            .block(Block::default())
-            .data(BarGroup::default().bars(&bars))
-            .bar_width(1)
-            .bar_gap(1);
+    let mut spans = vec![Span::styled("True peak ", label)];
+    let names: &[&str] = if app.true_peak_hold.len() > 1 {
+        &["L ", "R "]
+    } else {
+        &[""]
+    };
+    for (i, name) in names.iter().enumerate() {
+        let (Some(&hold), Some(&max)) = (app.true_peak_hold.get(i), app.true_peak_max.get(i))
+        else {
+            break;
+        };
+        if i > 0 {
+            spans.push(Span::styled("  ", label));
+        }
+        spans.push(Span::styled(*name, label));
+        spans.push(dbtp(hold));
+        spans.push(Span::styled(" max ", label));
+        spans.push(dbtp(max));
+    }
+    spans.push(Span::styled(" dBTP  ", label));
 
-        f.render_widget(barchart, inner);
+    let clips: u64 = app.clips_since_reset().sum();
+    if clips > 0 {
+        spans.push(Span::styled(
+            format!("CLIP {}", clips),
+            Style::default()
+                .fg(app.theme.peak)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        ));
+    } else {
+        spans.push(Span::styled("no clips", label));
+    }
+    f.render_widget(
+        Paragraph::new(Line::from(spans)).alignment(Alignment::Center),
+        area,
+    );
+}
+
+/// Draws the EBU R128 readings against the target, above a graph of the
+/// momentary and short-term loudness.
+fn draw_loudness(f: &mut Frame, area: Rect, app: &App) {
+    let target = app.loudness_target.lufs();
+    let block = Block::default()
+        .title(format!(" Loudness · target {} ", app.loudness_target))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height == 0 {
+        return;
+    }
+
+    let label = Style::default().fg(app.theme.label);
+    // Louder than the target by more than 1 LU stands out
+    let reading = |value: Option<f32>| match value {
+        Some(lufs) => {
+            let color = if lufs > target + 1.0 {
+                app.theme.peak
+            } else {
+                app.theme.value
+            };
+            Span::styled(format!("{:.1}", lufs), Style::default().fg(color))
+        }
+        None => Span::styled("--", Style::default().fg(app.theme.dim)),
+    };
+    let loudness = app
+        .last_spectrum
+        .as_ref()
+        .map(|spectrum| spectrum.loudness)
+        .unwrap_or_default();
+    let mut spans = vec![
+        Span::styled("M ", label),
+        reading(loudness.momentary),
+        Span::styled("  S ", label),
+        reading(loudness.short_term),
+        Span::styled("  I ", label),
+        reading(loudness.integrated),
+        Span::styled(" LUFS  LRA ", label),
+        match loudness.range {
+            Some(lu) => Span::styled(format!("{:.1}", lu), Style::default().fg(app.theme.value)),
+            None => Span::styled("--", Style::default().fg(app.theme.dim)),
+        },
+        Span::styled(" LU", label),
+    ];
+    if let Some(integrated) = loudness.integrated {
+        spans.push(Span::styled(
+            format!("  ({:+.1} LU to target)", integrated - target),
+            Style::default().fg(app.theme.text),
+        ));
+    }
+    f.render_widget(
+        Paragraph::new(Line::from(spans)).alignment(Alignment::Center),
+        Rect { height: 1, ..inner },
+    );
+
+    let graph_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    if graph_area.height == 0 {
+        return;
+    }
+    // Newest on the right; gaps where a reading is gated or not yet available
+    let history = &app.loudness_history;
+    let offset = LOUDNESS_HISTORY_LEN - history.points().len();
+    let traces = [
+        (
+            history.points().map(|l| l.momentary).collect::<Vec<_>>(),
+            app.theme.axis,
+        ),
+        (
+            history.points().map(|l| l.short_term).collect(),
+            app.theme.value,
+        ),
+    ];
+    let last = (LOUDNESS_HISTORY_LEN - 1) as f64;
+    let [low, high] = LOUDNESS_GRAPH_RANGE.map(|lu| (target + lu) as f64);
+    let canvas = Canvas::default()
+        .marker(Marker::Braille)
+        .x_bounds([0.0, last])
+        .y_bounds([low, high])
+        .paint(|ctx| {
+            ctx.draw(&CanvasLine {
+                x1: 0.0,
+                y1: target as f64,
+                x2: last,
+                y2: target as f64,
+                color: app.theme.dim,
+            });
+            ctx.print(
+                0.0,
+                high,
+                Span::styled(format!("{:.0}", high), Style::default().fg(app.theme.axis)),
+            );
+            ctx.layer();
+            for (trace, color) in &traces {
+                for (i, pair) in trace.windows(2).enumerate() {
+                    if let [Some(a), Some(b)] = *pair {
+                        let x = (offset + i) as f64;
+                        ctx.draw(&CanvasLine {
+                            x1: x,
+                            y1: (a as f64).clamp(low, high),
+                            x2: x + 1.0,
+                            y2: (b as f64).clamp(low, high),
+                            color: *color,
+                        });
+                    }
+                }
+                ctx.layer();
+            }
+        });
+    f.render_widget(canvas, graph_area);
+}
+
+/// The detected note, its frequency and how far it is from the note, with a
+/// gauge from -50 to +50 cents.
+fn draw_tuner(f: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .title(format!(" Tuner · A4 = {} Hz ", app.a4))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height < 2 || inner.width < 8 {
+        return;
+    }
+
+    let label = Style::default().fg(app.theme.label);
+    let time = app.last_spectrum.as_ref().map_or(0.0, |s| s.time);
+    let held = app
+        .last_pitch
+        .filter(|&(_, detected)| (time - detected).abs() <= TUNER_HOLD_SECS);
+    let Some((pitch, detected)) = held else {
+        f.render_widget(
+            Paragraph::new(Span::styled("--", Style::default().fg(app.theme.dim)))
+                .alignment(Alignment::Center),
+            inner,
+        );
+        return;
+    };
+    let note = Note::from_frequency(pitch.frequency, app.a4);
+    let in_tune = note.cents.abs() <= IN_TUNE_CENTS;
+    // A held note is dimmed once the current frame no longer has it
+    let current = detected == time;
+    let color = if !current {
+        app.theme.dim
+    } else if in_tune {
+        app.theme.key
+    } else {
+        app.theme.value
+    };
+    let highlight = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    let reading = Line::from(vec![
+        Span::styled(note.to_string(), highlight),
+        Span::styled("  ", label),
+        Span::styled(
+            format!("{:.1}", pitch.frequency),
+            Style::default().fg(app.theme.value),
+        ),
+        Span::styled(" Hz  ", label),
+        Span::styled(format!("{:+.0}", note.cents), highlight),
+        Span::styled(" cents", label),
+    ]);
+    f.render_widget(
+        Paragraph::new(reading).alignment(Alignment::Center),
+        Rect { height: 1, ..inner },
+    );
+
+    // Flat on the left, sharp on the right, the note in the middle
+    let gauge = Rect {
+        y: inner.y + 1,
+        height: 1,
+        ..inner
+    };
+    let marker = Some((note.cents / 50.0, color));
+    draw_position_bar(f, gauge, ["-50", "+50"], marker, app);
+}
+
+/// Bars of the energy in each pitch class, with the estimated key in the
+/// title and its tonic highlighted.
+fn draw_chroma(f: &mut Frame, area: Rect, app: &App) {
+    let key = app.last_spectrum.as_ref().and_then(|s| s.key);
+    let title = match key {
+        Some(key) => format!(
+            " Chroma · {} ({:.0}% confidence) ",
+            key,
+            100.0 * key.confidence
+        ),
+        None => " Chroma ".to_string(),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let classes = PITCH_CLASSES as u16;
+    let bar_width = (inner.width + 1) / classes;
+    if inner.height < 2 || bar_width < 2 {
+        return;
+    }
+    let chroma = app
+        .last_spectrum
+        .as_ref()
+        .map_or([0.0; PITCH_CLASSES], |s| s.chroma);
+    let bars: Vec<Bar> = chroma
+        .iter()
+        .enumerate()
+        .map(|(class, &level)| {
+            let tonic = key.is_some_and(|key| key.tonic == class);
+            let name = Note {
+                midi: class as i32,
+                cents: 0.0,
+            }
+            .name();
+            let label_style = if tonic {
+                Style::default()
+                    .fg(app.theme.key)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(app.theme.axis)
+            };
+            Bar::default()
+                .value((level * BAR_RESOLUTION as f32) as u64)
+                .text_value(String::new())
+                .label(Line::styled(name, label_style))
+                .style(Style::default().fg(create_color_gradient(&app.theme.gradient, level)))
+        })
+        .collect();
+
+    // Centered, with one column between bars
+    let width = classes * bar_width - 1;
+    let chart = Rect {
+        x: inner.x + (inner.width - width) / 2,
+        width,
+        ..inner
+    };
+    let barchart = BarChart::default()
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width - 1)
+        .bar_gap(1)
+        .max(BAR_RESOLUTION);
+    f.render_widget(barchart, chart);
+}
+
+/// A row of the features panel: its name, the feature and its reading.
+type FeatureRow = (&'static str, fn(&Features) -> f32, fn(f32) -> String);
+
+const FEATURE_ROWS: [FeatureRow; 7] = [
+    ("Centroid", |f| f.centroid, |v| format!("{:.0} Hz", v)),
+    ("Bandwidth", |f| f.bandwidth, |v| format!("{:.0} Hz", v)),
+    ("Rolloff", |f| f.rolloff, |v| format!("{:.0} Hz", v)),
+    ("Flatness", |f| f.flatness, |v| format!("{:.3}", v)),
+    ("Flux", |f| f.flux, |v| format!("{:.3}", v)),
+    ("ZCR", |f| f.zero_crossing_rate, |v| format!("{:.3}", v)),
+    ("Crest", |f| f.crest_factor, |v| format!("{:.1} dB", v)),
+];
+
+/// The features of the latest frame, each beside a sparkline of its recent
+/// frames scaled to their highest value.
+fn draw_features(f: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .title(" Features ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.width < FEATURE_LABEL_WIDTH + FEATURE_VALUE_WIDTH {
+        return;
+    }
+
+    let label = Style::default().fg(app.theme.label);
+    let value = Style::default().fg(app.theme.value);
+    let latest = app.last_spectrum.as_ref().map(|s| s.features);
+    // The sparklines take what is left after a column of space, if anything
+    let spark_width = inner
+        .width
+        .saturating_sub(FEATURE_LABEL_WIDTH + FEATURE_VALUE_WIDTH + 1);
+    for (row, &(name, feature, format)) in FEATURE_ROWS.iter().enumerate() {
+        if row as u16 >= inner.height {
+            break;
+        }
+        let line = Rect {
+            y: inner.y + row as u16,
+            height: 1,
+            ..inner
+        };
+        let reading = match latest {
+            Some(features) => Span::styled(
+                format!(
+                    "{:>width$}",
+                    format(feature(&features)),
+                    width = FEATURE_VALUE_WIDTH as usize
+                ),
+                value,
+            ),
+            None => Span::styled(
+                format!("{:>width$}", "--", width = FEATURE_VALUE_WIDTH as usize),
+                Style::default().fg(app.theme.dim),
+            ),
+        };
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}", name, width = FEATURE_LABEL_WIDTH as usize),
+                    label,
+                ),
+                reading,
+            ])),
+            line,
+        );
+
+        if spark_width == 0 {
+            continue;
+        }
+        let values: Vec<f32> = app
+            .feature_history
+            .latest(spark_width as usize)
+            .map(feature)
+            .collect();
+        let highest = values.iter().copied().fold(0.0, f32::max);
+        let data: Vec<u64> = values
+            .iter()
+            .map(|&v| {
+                if highest > 0.0 {
+                    (v.max(0.0) / highest * BAR_RESOLUTION as f32) as u64
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let sparkline = Sparkline::default()
+            .data(&data)
+            .max(BAR_RESOLUTION)
+            .style(value);
+        f.render_widget(
+            sparkline,
+            Rect {
+                x: line.x + FEATURE_LABEL_WIDTH + FEATURE_VALUE_WIDTH + 1,
+                width: spark_width,
+                ..line
+            },
+        );
+    }
+}
+
+/// Plots recent sample pairs with mid (L+R) up and side (R-L) across, so mono
+/// is a vertical line and a single channel lies on its diagonal.
+fn draw_goniometer(f: &mut Frame, area: Rect, app: &App) {
+    let title = if app.stereo.is_mono() {
+        " Goniometer · mono "
+    } else {
+        " Goniometer "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let points: Vec<(f64, f64)> = app
+        .stereo
+        .trace()
+        .map(|(l, r)| (((r - l) / 2.0) as f64, ((l + r) / 2.0) as f64))
+        .collect();
+    let axis = Style::default().fg(app.theme.axis);
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds([-1.0, 1.0])
+        .y_bounds([-1.0, 1.0])
+        .paint(|ctx| {
+            // The left and right channel axes and the mid axis
+            for (x1, y1, x2, y2) in [
+                (-1.0, 1.0, 1.0, -1.0),
+                (-1.0, -1.0, 1.0, 1.0),
+                (0.0, -1.0, 0.0, 1.0),
+            ] {
+                ctx.draw(&CanvasLine {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    color: app.theme.dim,
+                });
+            }
+            ctx.print(-1.0, 1.0, Span::styled("L", axis));
+            ctx.print(0.95, 1.0, Span::styled("R", axis));
+            ctx.layer();
+            ctx.draw(&Points {
+                coords: &points,
+                color: app.theme.value,
+            });
+        });
+    f.render_widget(canvas, area);
+}
+
+/// Draws the phase correlation on a -1 to +1 scale above a graph of its history.
+fn draw_correlation(f: &mut Frame, area: Rect, app: &App) {
+    let correlation = app.stereo.correlation();
+    let title = match correlation {
+        Some(c) => format!(" Correlation {:+.2} ", c),
+        None => " Correlation ".to_string(),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height == 0 {
+        return;
+    }
+
+    let color = |c: f32| {
+        // Out of phase material cancels when summed to mono
+        if c < 0.0 {
+            app.theme.peak
+        } else {
+            app.theme.value
+        }
+    };
+    draw_position_bar(
+        f,
+        Rect { height: 1, ..inner },
+        ["-1", "+1"],
+        correlation.map(|c| (c, color(c))),
+        app,
+    );
+
+    let history_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    if history_area.height == 0 {
+        return;
+    }
+    // Newest on the right
+    let history: Vec<Option<f32>> = app.stereo.history().collect();
+    let offset = HISTORY_LEN - history.len();
+    let canvas = Canvas::default()
+        .marker(Marker::Braille)
+        .x_bounds([0.0, (HISTORY_LEN - 1) as f64])
+        .y_bounds([-1.0, 1.0])
+        .paint(|ctx| {
+            ctx.draw(&CanvasLine {
+                x1: 0.0,
+                y1: 0.0,
+                x2: (HISTORY_LEN - 1) as f64,
+                y2: 0.0,
+                color: app.theme.dim,
+            });
+            ctx.layer();
+            for (i, pair) in history.windows(2).enumerate() {
+                if let [Some(a), Some(b)] = *pair {
+                    let x = (offset + i) as f64;
+                    ctx.draw(&CanvasLine {
+                        x1: x,
+                        y1: a as f64,
+                        x2: x + 1.0,
+                        y2: b as f64,
+                        color: color(a.min(b)),
+                    });
+                }
+            }
+        });
+    f.render_widget(canvas, history_area);
+}
+
+/// Shows where the level sits between the left and right channels.
+fn draw_balance(f: &mut Frame, area: Rect, app: &App) {
+    let title = match app.stereo.balance_db() {
+        Some(db) if db.abs() < 0.05 => " Balance · centered ".to_string(),
+        Some(db) if db.abs() >= 60.0 => {
+            format!(" Balance · {} only ", if db > 0.0 { "R" } else { "L" })
+        }
+        Some(db) => format!(
+            " Balance · {} +{:.1} dB ",
+            if db > 0.0 { "R" } else { "L" },
+            db.abs()
+        ),
+        None => " Balance ".to_string(),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height == 0 {
+        return;
+    }
+    let position = app.stereo.balance_position().map(|p| (p, app.theme.value));
+    draw_position_bar(f, Rect { height: 1, ..inner }, ["L", "R"], position, app);
+}
+
+/// Draws a scale between two end labels with a center tick and a marker at
+/// `marker`'s position, from -1 at the left end to +1 at the right.
+fn draw_position_bar(
+    f: &mut Frame,
+    area: Rect,
+    labels: [&str; 2],
+    marker: Option<(f32, Color)>,
+    app: &App,
+) {
+    let [left, right] = labels;
+    let Some(track) = (area.width as usize).checked_sub(left.len() + right.len() + 2) else {
+        return;
+    };
+    if track < 3 {
+        return;
+    }
+    let mut cells: Vec<Span> = (0..track)
+        .map(|i| {
+            let symbol = if i == track / 2 { "┼" } else { "─" };
+            Span::styled(symbol, Style::default().fg(app.theme.dim))
+        })
+        .collect();
+    if let Some((position, color)) = marker {
+        let i = ((position.clamp(-1.0, 1.0) + 1.0) / 2.0 * (track - 1) as f32).round() as usize;
+        cells[i] = Span::styled("●", Style::default().fg(color).add_modifier(Modifier::BOLD));
+    }
+
+    let axis = Style::default().fg(app.theme.axis);
+    let mut spans = vec![Span::styled(format!("{} ", left), axis)];
+    spans.extend(cells);
+    spans.push(Span::styled(format!(" {}", right), axis));
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Draws one level gauge filled to `level`, with a marker at `hold`, both
+/// as 0.0-1.0 of the scale.
+fn draw_level_gauge(f: &mut Frame, area: Rect, level: f32, hold: f32, app: &App) {
+    let gauge_color = create_color_gradient(&app.theme.gradient, level);
+
+    // Gauge without label or percentage
+    let gauge = Gauge::default()
+        .block(Block::default())
+        .gauge_style(Style::default().fg(gauge_color))
+        .ratio(level as f64)
+        .label("");
+
+    f.render_widget(gauge, area);
+
+    if hold > 0.0 && area.width > 0 {
+        let peak_area = Rect {
+            x: area.x + gauge_column(hold, area.width),
+            y: area.y,
+            width: 1,
+            height: 1,
+        };
+        let peak_indicator = Paragraph::new("│").style(
+            Style::default()
+                .fg(app.theme.peak)
+                .add_modifier(Modifier::BOLD),
+        );
+        f.render_widget(peak_indicator, peak_area);
+    }
+}
+
+fn draw_eq_spectrum(f: &mut Frame, area: Rect, app: &App) {
+    let mode_str = if app.linear_mode { "Linear" } else { "dBFS" };
+    let analyzer = &app.analyzer;
+    let overlap = 100.0 * (1.0 - analyzer.hop as f32 / analyzer.fft_size as f32);
+    let mut title = format!(
+        " EQ Spectrum ({}) · {} bands · {} window · FFT {}",
+        mode_str,
+        analyzer.scale,
+        analyzer.window.label(analyzer.kaiser_beta),
+        analyzer.fft_size
+    );
+    if app.sample_rate > 0 {
+        let bin_hz = app.sample_rate as f32 / analyzer.fft_size as f32;
+        title.push_str(&format!(" ({:.1} Hz bins)", bin_hz));
+    }
+    title.push_str(&format!(" · {}% overlap ", overlap));
+    if analyzer.weighting != Weighting::Z {
+        title.push_str(&format!("· {}-weighted ", analyzer.weighting));
+    }
+    match analyzer.averaging {
+        Averaging::Off => {}
+        Averaging::Linear | Averaging::Exponential => title.push_str(&format!(
+            "· {} avg of {} ",
+            analyzer.averaging, analyzer.average_frames
+        )),
+        Averaging::Infinite => title.push_str("· infinite avg "),
+    }
+    if let Some(spectrum) = &app.last_spectrum
+        && channel_pair(spectrum, app.channel_view).is_some()
+    {
+        let arrangement = if app.split_channels {
+            "split"
+        } else {
+            "overlaid"
+        };
+        title.push_str(&format!("· {} {} ", app.channel_view, arrangement));
+    }
+    match (app.layout.max_hold, app.layout.min_hold) {
+        (true, true) => title.push_str("· max/min hold "),
+        (true, false) => title.push_str("· max hold "),
+        (false, true) => title.push_str("· min hold "),
+        (false, false) => {}
+    }
+    let eq_block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+
+    if let Some(ref spectrum) = app.last_spectrum {
+        let inner = eq_block.inner(area);
+        f.render_widget(eq_block, area);
+
+        let colors = [app.theme.left, app.theme.right];
+        match channel_pair(spectrum, app.channel_view) {
+            Some(pair) if app.split_channels => {
+                let halves = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Ratio(1, 2); 2])
+                    .split(inner);
+                for ((half, channel), color) in halves.iter().zip(pair).zip(colors) {
+                    let peaks = app.peaks.get(channel.channel);
+                    let chart_area = draw_bars(f, *half, app.bands(channel), peaks, app);
+                    draw_channel_legend(f, chart_area, &[(channel.channel, color)]);
+                }
+            }
+            Some(pair) => {
+                let (axis_area, chart_area) = split_axis(inner);
+                draw_level_axis(f, axis_area, chart_area, app);
+                draw_channel_curves(f, chart_area, pair, app);
+                draw_channel_legend(
+                    f,
+                    chart_area,
+                    &[(pair[0].channel, colors[0]), (pair[1].channel, colors[1])],
+                );
+            }
+            None => {
+                let bands_data = if app.linear_mode {
+                    &spectrum.bands_linear
+                } else {
+                    &spectrum.bands
+                };
+                draw_bars(f, inner, bands_data, app.peaks.get(Channel::Mix), app);
+            }
+        }
     } else {
         let waiting = Paragraph::new("Waiting for audio data...")
-            .style(Style::default().fg(Color::Rgb(128, 128, 128)))
+            .style(Style::default().fg(app.theme.dim))
             .alignment(Alignment::Center)
             .block(eq_block);
         f.render_widget(waiting, area);
     }
 }
 
+/// The two channel spectra a view shows, if the source has them.
+fn channel_pair(spectrum: &Spectrum, view: ChannelView) -> Option<[&ChannelSpectrum; 2]> {
+    let [a, b] = view.pair()?;
+    Some([spectrum.channel(a)?, spectrum.channel(b)?])
+}
+
+/// Draws a level axis and `bands` as bars in `area`, with the peak caps and
+/// hold traces of `peaks`, returning the chart area right of the axis.
+fn draw_bars(
+    f: &mut Frame,
+    area: Rect,
+    bands: &[f32],
+    peaks: Option<&BandPeaks>,
+    app: &App,
+) -> Rect {
+    let (axis_area, chart_area) = split_axis(area);
+    draw_level_axis(f, axis_area, chart_area, app);
+
+    let Some(layout) = BarLayout::new(bands.len(), chart_area.width, app.analyzer.scale) else {
+        return chart_area;
+    };
+
+    let bars: Vec<Bar> = (0..layout.count)
+        .map(|i| {
+            let level = app.display_level(layout.value(i, bands));
+            let height = (level * BAR_RESOLUTION as f32) as u64;
+            Bar::default()
+                .value(height)
+                .text_value(String::new())
+                .style(Style::default().fg(create_color_gradient(&app.theme.gradient, level)))
+        })
+        .collect();
+
+    let barchart = BarChart::default()
+        .block(Block::default())
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(layout.bar_width)
+        .bar_gap(1)
+        .max(BAR_RESOLUTION);
+
+    f.render_widget(barchart, chart_area);
+    if let Some(peaks) = peaks.filter(|peaks| peaks.caps().len() == bands.len()) {
+        draw_peak_holds(f, chart_area, &layout, peaks, app);
+    }
+    chart_area
+}
+
+/// Draws a cap over each bar at its held peak, and the max- and min-hold
+/// traces through the bar centers.
+fn draw_peak_holds(f: &mut Frame, area: Rect, layout: &BarLayout, peaks: &BandPeaks, app: &App) {
+    if app.layout.peak_caps && area.height > 0 {
+        let pitch = layout.bar_width + 1;
+        let buf = f.buffer_mut();
+        for i in 0..layout.count {
+            let level = app.display_db(layout.value(i, peaks.caps()));
+            if level <= 0.0 {
+                continue;
+            }
+            let row = ((level * area.height as f32) as u16).min(area.height - 1);
+            let y = area.y + area.height - 1 - row;
+            for dx in 0..layout.bar_width {
+                if let Some(cell) = buf.cell_mut((area.x + i as u16 * pitch + dx, y)) {
+                    cell.set_char('▔').set_fg(app.theme.peak);
+                }
+            }
+        }
+    }
+
+    let traces: Vec<(&[f32], Color)> = [
+        (app.layout.max_hold, peaks.max(), app.theme.peak),
+        (app.layout.min_hold, peaks.min(), app.theme.dim),
+    ]
+    .into_iter()
+    .filter(|&(shown, _, _)| shown)
+    .map(|(_, bands, color)| (bands, color))
+    .collect();
+    if traces.is_empty() {
+        return;
+    }
+    let curves: Vec<(Vec<(f64, f64)>, Color)> = traces
+        .into_iter()
+        .map(|(bands, color)| {
+            let points = (0..layout.count)
+                .map(|i| {
+                    let x = layout.column(layout.band(i)) as f64;
+                    (x, app.display_db(layout.value(i, bands)) as f64)
+                })
+                .collect();
+            (points, color)
+        })
+        .collect();
+    let canvas = Canvas::default()
+        .marker(Marker::Braille)
+        .x_bounds([0.0, area.width.saturating_sub(1) as f64])
+        .y_bounds([0.0, 1.0])
+        .paint(|ctx| {
+            for (points, color) in &curves {
+                for pair in points.windows(2) {
+                    ctx.draw(&CanvasLine {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color: *color,
+                    });
+                }
+                ctx.layer();
+            }
+        });
+    f.render_widget(canvas, area);
+}
+
+/// Draws each channel's spectrum as a line through the tops of where its bars
+/// would be, so both can share one chart.
+fn draw_channel_curves(f: &mut Frame, area: Rect, pair: [&ChannelSpectrum; 2], app: &App) {
+    let Some(layout) = BarLayout::new(pair[0].bands.len(), area.width, app.analyzer.scale) else {
+        return;
+    };
+    let curves: Vec<(Vec<(f64, f64)>, Color)> = pair
+        .iter()
+        .zip([app.theme.left, app.theme.right])
+        .map(|(channel, color)| {
+            let bands = app.bands(channel);
+            let points = (0..layout.count)
+                .map(|i| {
+                    let x = layout.column(layout.band(i)) as f64;
+                    (x, app.display_level(layout.value(i, bands)) as f64)
+                })
+                .collect();
+            (points, color)
+        })
+        .collect();
+
+    let canvas = Canvas::default()
+        .marker(Marker::Braille)
+        .x_bounds([0.0, area.width.saturating_sub(1) as f64])
+        .y_bounds([0.0, 1.0])
+        .paint(|ctx| {
+            for (points, color) in &curves {
+                for pair in points.windows(2) {
+                    ctx.draw(&CanvasLine {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color: *color,
+                    });
+                }
+                ctx.layer();
+            }
+        });
+    f.render_widget(canvas, area);
+}
+
+/// Names the channels in their colors at the top right of a chart.
+fn draw_channel_legend(f: &mut Frame, area: Rect, channels: &[(Channel, Color)]) {
+    let spans: Vec<Span> = channels
+        .iter()
+        .map(|(channel, color)| {
+            Span::styled(
+                format!(" {}", channel),
+                Style::default().fg(*color).add_modifier(Modifier::BOLD),
+            )
+        })
+        .collect();
+    let legend = Paragraph::new(Line::from(spans)).alignment(Alignment::Right);
+    f.render_widget(legend, Rect { height: 1, ..area });
+}
+
+/// Draws recent spectra as rows of colored half blocks, newest at the top, in
+/// the same columns as the spectrum bars above.
+fn draw_waterfall(f: &mut Frame, area: Rect, app: &App) {
+    let capacity = app.waterfall.capacity();
+    let mut title = format!(" Waterfall · {} · {} spectra", app.colormap, capacity);
+    if app.sample_rate > 0 {
+        let secs = (capacity * app.analyzer.hop) as f32 / app.sample_rate as f32;
+        title.push_str(&format!(" ({:.1} s)", secs));
+    }
+    title.push(' ');
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let (axis_area, chart_area) = split_axis(inner);
+    let rows: Vec<&Spectrum> = app.waterfall.rows().collect();
+    let Some(newest) = rows.first() else {
+        return;
+    };
+    let Some(layout) = BarLayout::new(newest.bands.len(), chart_area.width, app.analyzer.scale)
+    else {
+        return;
+    };
+
+    // Each cell shows two time slots; each slot covers `per_slot` spectra
+    let slots = chart_area.height as usize * 2;
+    let per_slot = capacity as f32 / slots as f32;
+    let slot_levels = |slot: usize| -> Option<Vec<f32>> {
+        let first = (slot as f32 * per_slot) as usize;
+        if first >= rows.len() {
+            return None;
+        }
+        let end = (((slot + 1) as f32 * per_slot) as usize).clamp(first + 1, rows.len());
+        // Loudest band level over the slot's spectra
+        let mut bands = vec![f32::NEG_INFINITY; newest.bands.len()];
+        for spectrum in &rows[first..end] {
+            let values = if app.linear_mode {
+                &spectrum.bands_linear
+            } else {
+                &spectrum.bands
+            };
+            for (band, &value) in bands.iter_mut().zip(values) {
+                *band = band.max(value);
+            }
+        }
+        Some(
+            (0..layout.count)
+                .map(|bar| app.display_level(layout.value(bar, &bands)))
+                .collect(),
+        )
+    };
+
+    let stops = app.colormap.stops();
+    // Gaps between bars take the color of the bar to their left
+    let bar_pitch = layout.bar_width + 1;
+    let columns = (layout.count as u16 * bar_pitch).min(chart_area.width);
+    let color = |levels: &[f32], column: u16| {
+        create_color_gradient(stops, levels[(column / bar_pitch) as usize])
+    };
+    let buf = f.buffer_mut();
+    for row in 0..chart_area.height {
+        let Some(upper) = slot_levels(row as usize * 2) else {
+            break;
+        };
+        let lower = slot_levels(row as usize * 2 + 1);
+        for column in 0..columns {
+            if let Some(cell) = buf.cell_mut((chart_area.x + column, chart_area.y + row)) {
+                cell.set_char('▀').set_fg(color(&upper, column));
+                if let Some(lower) = &lower {
+                    cell.set_bg(color(lower, column));
+                }
+            }
+        }
+    }
+
+    // Time axis: now at the top, the oldest spectrum shown at the bottom
+    if axis_area.height >= 2 && app.sample_rate > 0 {
+        let secs = (capacity * app.analyzer.hop) as f32 / app.sample_rate as f32;
+        let style = Style::default().fg(app.theme.axis);
+        let top = Rect {
+            height: 1,
+            ..axis_area
+        };
+        let bottom = Rect {
+            y: axis_area.y + axis_area.height - 1,
+            height: 1,
+            ..axis_area
+        };
+        f.render_widget(Paragraph::new(" now").style(style), top);
+        let oldest = if secs < 10.0 {
+            format!("-{:.1}s", secs)
+        } else {
+            format!("-{:.0}s", secs)
+        };
+        f.render_widget(Paragraph::new(oldest).style(style), bottom);
+    }
+}
+
+/// Draws the triggered waveform of each channel, with the trigger level dimmed
+/// behind it.
+fn draw_oscilloscope(f: &mut Frame, area: Rect, app: &App) {
+    let scope = &app.scope;
+    let view = scope.view();
+    let state = match &view {
+        Some(view) if view.triggered => "triggered",
+        Some(_) => "free run",
+        None => "waiting",
+    };
+    let title = format!(
+        " Oscilloscope · {} ms · {} at {:+.2} · {} ",
+        scope.timebase_ms(),
+        scope.edge,
+        scope.level,
+        state
+    );
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    let Some(view) = view else {
+        f.render_widget(block, area);
+        return;
+    };
+
+    let samples = view.left.len();
+    let columns = block.inner(area).width as usize * 2; // Braille dots per row
+    let traces = [(&view.left, app.theme.left), (&view.right, app.theme.right)];
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds([0.0, (samples - 1) as f64])
+        .y_bounds([-1.0, 1.0])
+        .paint(|ctx| {
+            ctx.draw(&CanvasLine {
+                x1: 0.0,
+                y1: scope.level as f64,
+                x2: (samples - 1) as f64,
+                y2: scope.level as f64,
+                color: app.theme.dim,
+            });
+            ctx.layer();
+            for (trace, color) in traces {
+                for (x1, y1, x2, y2) in trace_segments(trace, columns) {
+                    ctx.draw(&CanvasLine {
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        color,
+                    });
+                }
+            }
+        });
+    f.render_widget(canvas, area);
+}
+
+/// Line segments tracing `samples` across `columns` dots. Dense traces are
+/// drawn as the range each column covers, joined to the column before.
+fn trace_segments(samples: &[f32], columns: usize) -> Vec<(f64, f64, f64, f64)> {
+    let n = samples.len();
+    if n <= columns {
+        return samples
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| (i as f64, pair[0] as f64, (i + 1) as f64, pair[1] as f64))
+            .collect();
+    }
+    (0..columns)
+        .map(|c| {
+            let start = (c * n / columns).saturating_sub(1);
+            let end = ((c + 1) * n / columns).max(start + 1);
+            let range = &samples[start..end];
+            let low = range.iter().copied().fold(f32::INFINITY, f32::min);
+            let high = range.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let x = ((c * n + n / 2) / columns) as f64;
+            (x, low as f64, x, high as f64)
+        })
+        .collect()
+}
+
+/// How spectrum bands map onto the bars of the chart.
+struct BarLayout {
+    count: usize,
+    bar_width: u16,
+    /// Bands per bar
+    span: f32,
+    last_band: usize,
+}
+
+impl BarLayout {
+    /// Fits `bands` into `width` columns with one column between bars.
+    ///
+    /// Fractional-octave bands get one bar each, widened to fill the chart, as
+    /// long as they fit. Otherwise bars sample the bands at even spacing.
+    fn new(bands: usize, width: u16, scale: BandScale) -> Option<BarLayout> {
+        let max_bars = (width as usize).div_ceil(2);
+        if bands == 0 || max_bars < 2 {
+            return None;
+        }
+        let last_band = bands - 1;
+        if scale != BandScale::Log && bands <= max_bars {
+            let bar_width = ((width as usize + 1) / bands - 1) as u16;
+            return Some(BarLayout {
+                count: bands,
+                bar_width,
+                span: 1.0,
+                last_band,
+            });
+        }
+        Some(BarLayout {
+            count: max_bars,
+            bar_width: 1,
+            span: last_band as f32 / (max_bars - 1) as f32,
+            last_band,
+        })
+    }
+
+    /// Fractional band index shown by bar `i`.
+    fn band(&self, i: usize) -> f32 {
+        i as f32 * self.span
+    }
+
+    /// Column showing fractional band index `band`, relative to the chart.
+    fn column(&self, band: f32) -> u16 {
+        let bar = (band / self.span).min((self.count - 1) as f32);
+        (bar * (self.bar_width + 1) as f32).round() as u16 + self.bar_width / 2
+    }
+
+    fn value(&self, i: usize, bands: &[f32]) -> f32 {
+        let band_idx_f = self.band(i);
+        if self.span > 1.0 {
+            // Several bands per bar: show the loudest so peaks keep their level
+            let lo = (band_idx_f - self.span / 2.0).ceil().max(0.0) as usize;
+            let hi = ((band_idx_f + self.span / 2.0).floor() as usize).min(self.last_band);
+            bands[lo..=hi.max(lo)]
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max)
+        } else {
+            // Interpolate between adjacent bands for smoother display
+            let band_idx_low = band_idx_f.floor() as usize;
+            let band_idx_high = (band_idx_low + 1).min(self.last_band);
+            let frac = band_idx_f - band_idx_low as f32;
+            let value_low = bands[band_idx_low];
+            let value_high = bands[band_idx_high];
+            value_low + frac * (value_high - value_low)
+        }
+    }
+}
+
+/// Bar values per full-height bar, so the chart keeps a fixed scale.
+const BAR_RESOLUTION: u64 = 1000;
+/// Columns taken by the level axis left of the spectrum.
+const AXIS_WIDTH: u16 = 5;
+
+/// Splits a plot area into the level axis on the left and the chart beside it.
+fn split_axis(area: Rect) -> (Rect, Rect) {
+    let width = AXIS_WIDTH.min(area.width);
+    (
+        Rect { width, ..area },
+        Rect {
+            x: area.x + width,
+            width: area.width - width,
+            ..area
+        },
+    )
+}
+
+/// Draws level labels beside the spectrum and dotted gridlines behind its bars.
+fn draw_level_axis(f: &mut Frame, axis_area: Rect, chart_area: Rect, app: &App) {
+    let rows = axis_area.height as usize;
+    if rows == 0 {
+        return;
+    }
+
+    // Label every `step` units, keeping at least two rows between labels
+    let (min, max, steps): (f32, f32, &[f32]) = if app.linear_mode {
+        (0.0, 1.0, &[0.1, 0.25, 0.5, 1.0])
+    } else {
+        (
+            app.db_floor,
+            app.db_ceiling,
+            &[5.0, 10.0, 20.0, 30.0, 40.0, 60.0],
+        )
+    };
+    let max_labels = rows.div_ceil(2).max(2);
+    let step = steps
+        .iter()
+        .copied()
+        .find(|step| (((max - min) / step).floor() as usize) < max_labels)
+        .unwrap_or(max - min);
+
+    let mut axis: Vec<String> = vec![format!("{:>w$}", "│", w = AXIS_WIDTH as usize); rows];
+    let mut grid_rows = Vec::new();
+    let mut value = (min / step).ceil() * step;
+    while value <= max + f32::EPSILON {
+        let level = (value - min) / (max - min);
+        // The row where a bar of this level ends
+        let row = (((1.0 - level) * rows as f32) as usize).min(rows - 1);
+        let label = if app.linear_mode {
+            format!("{:.2}", value)
+        } else {
+            format!("{:.0}", value)
+        };
+        axis[row] = format!("{:>w$}┤", label, w = AXIS_WIDTH.saturating_sub(1) as usize);
+        grid_rows.push(row);
+        value += step;
+    }
+
+    let lines: Vec<Line> = axis
+        .into_iter()
+        .map(|text| Line::from(Span::styled(text, Style::default().fg(app.theme.axis))))
+        .collect();
+    f.render_widget(Paragraph::new(lines), axis_area);
+
+    let grid = "·".repeat(chart_area.width as usize);
+    for row in grid_rows {
+        let line_area = Rect {
+            y: chart_area.y + row as u16,
+            height: 1,
+            ..chart_area
+        };
+        f.render_widget(
+            Paragraph::new(grid.clone()).style(Style::default().fg(app.theme.dim)),
+            line_area,
+        );
+    }
+}
+
 fn draw_status_bar(f: &mut Frame, area: Rect, app: &App) {
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Rgb(96, 160, 192)));
-    
+        .border_style(Style::default().fg(app.theme.border));
+
     let inner = block.inner(area);
     f.render_widget(block, area);
-    
+
+    let label = Style::default().fg(app.theme.label);
+    let value = Style::default().fg(app.theme.value);
+    let key = Style::default()
+        .fg(app.theme.key)
+        .add_modifier(Modifier::BOLD);
+
+    let quit_key = app.keys.label(Action::Quit);
+    let linear_key = app.keys.label(Action::ToggleLinear);
+    let device_key = app.keys.label(Action::SelectDevice);
+
     // Calculate content width to determine layout
-    let device_text = format!("Device: {}", app.device_name);
+    let source_label = if app.playback.is_some() {
+        "File: "
+    } else {
+        "Device: "
+    };
+    let playback_text = app.playback.as_ref().map(|playback| {
+        let status = playback.status();
+        format!(
+            "{} {} / {}{}",
+            if status.paused { "⏸" } else { "▶" },
+            format_time(status.position),
+            format_time(status.duration),
+            if status.looping { " (loop)" } else { "" }
+        )
+    });
+    let mut device_text = format!("{}{}", source_label, app.device_name);
+    if let Some(playback) = &playback_text {
+        device_text.push_str(&format!(" {}", playback));
+    }
+    if let Some(profile) = &app.profile {
+        device_text.push_str(&format!(" | Profile: {}", profile));
+    }
+    if app.dropped_blocks > 0 {
+        device_text.push_str(&format!(" | Dropped: {} blocks", app.dropped_blocks));
+    }
     let sample_rate_text = format!("Sample Rate: {} Hz", app.sample_rate);
-    let controls_text = "Controls: Q/ESC to quit, L to toggle Linear/dB";
-    
+    let controls_text = format!(
+        "Controls: {} to quit, {} to toggle Linear/dB, {} to select device",
+        quit_key, linear_key, device_key
+    );
+
     let total_content_width = device_text.len() + sample_rate_text.len() + controls_text.len() + 6; // Add separators
     let device_and_rate_width = device_text.len() + sample_rate_text.len() + 3; // Add separator
-    
-    let status_text = if total_content_width <= inner.width as usize {
+
+    let mut device_spans = vec![
+        Span::styled(source_label, label),
+        Span::styled(app.device_name.clone(), value),
+    ];
+    if let Some(playback) = playback_text {
+        device_spans.push(Span::styled(format!(" {}", playback), key));
+    }
+    if let Some(profile) = &app.profile {
+        device_spans.push(Span::styled(" | Profile: ", label));
+        device_spans.push(Span::styled(profile.clone(), value));
+    }
+    if app.dropped_blocks > 0 {
+        device_spans.push(Span::styled(" | Dropped: ", label));
+        device_spans.push(Span::styled(
+            format!("{} blocks", app.dropped_blocks),
+            Style::default().fg(app.theme.peak),
+        ));
+    }
+    let rate_spans = vec![
+        Span::styled("Sample Rate: ", label),
+        Span::styled(format!("{} Hz", app.sample_rate), value),
+    ];
+    let control_spans = vec![
+        Span::styled(quit_key.clone(), key),
+        Span::styled(" to quit, ", value),
+        Span::styled(linear_key.clone(), key),
+        Span::styled(" to toggle Linear/dB, ", value),
+        Span::styled(device_key.clone(), key),
+        Span::styled(" to select device", value),
+    ];
+
+    let mut status_text = if total_content_width <= inner.width as usize {
         // Single line if everything fits
-        vec![Line::from(vec![
-            Span::styled("Device: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-            Span::styled(app.device_name.clone(), Style::default().fg(Color::White)),
-            Span::styled(" | Sample Rate: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-            Span::styled(format!("{} Hz", app.sample_rate), Style::default().fg(Color::White)),
-            Span::styled(" | Controls: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-            Span::styled("Q", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-            Span::styled("/", Style::default().fg(Color::Rgb(128, 160, 192))),
-            Span::styled("ESC", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-            Span::styled(" to quit, ", Style::default().fg(Color::White)),
-            Span::styled("L", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-            Span::styled(" to toggle Linear/dB", Style::default().fg(Color::White)),
-        ])]
+        let mut spans = device_spans;
+        spans.push(Span::styled(" | ", label));
+        spans.extend(rate_spans);
+        spans.push(Span::styled(" | Controls: ", label));
+        spans.extend(control_spans);
+        vec![Line::from(spans)]
     } else if device_and_rate_width <= inner.width as usize {
         // Two lines: device+sample rate on first line, controls on second
-        vec![
-            Line::from(vec![
-                Span::styled("Device: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled(app.device_name.clone(), Style::default().fg(Color::White)),
-                Span::styled(" | Sample Rate: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled(format!("{} Hz", app.sample_rate), Style::default().fg(Color::White)),
-            ]),
-            Line::from(vec![
-                Span::styled("Controls: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled("Q", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-                Span::styled("/", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled("ESC", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-                Span::styled(" to quit, ", Style::default().fg(Color::White)),
-                Span::styled("L", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-                Span::styled(" to toggle Linear/dB", Style::default().fg(Color::White)),
-            ])
-        ]
+        let mut first = device_spans;
+        first.push(Span::styled(" | ", label));
+        first.extend(rate_spans);
+        let mut second = vec![Span::styled("Controls: ", label)];
+        second.extend(control_spans);
+        vec![Line::from(first), Line::from(second)]
     } else {
         // Three lines for very narrow terminals
         vec![
+            Line::from(device_spans),
+            Line::from(rate_spans),
             Line::from(vec![
-                Span::styled("Device: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled(app.device_name.clone(), Style::default().fg(Color::White)),
+                Span::styled(quit_key, key),
+                Span::styled(" quit, ", value),
+                Span::styled(linear_key, key),
+                Span::styled(" toggle, ", value),
+                Span::styled(device_key, key),
+                Span::styled(" device", value),
             ]),
-            Line::from(vec![
-                Span::styled("Sample Rate: ", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled(format!("{} Hz", app.sample_rate), Style::default().fg(Color::White)),
-            ]),
-            Line::from(vec![
-                Span::styled("Q", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-                Span::styled("/", Style::default().fg(Color::Rgb(128, 160, 192))),
-                Span::styled("ESC", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-                Span::styled(" quit, ", Style::default().fg(Color::White)),
-                Span::styled("L", Style::default().fg(Color::Rgb(255, 255, 0)).add_modifier(Modifier::BOLD)),
-                Span::styled(" toggle", Style::default().fg(Color::White)),
-            ])
         ]
     };
 
-    let status = Paragraph::new(status_text)
-        .alignment(Alignment::Center);
+    // File playback controls get their own line when there is room
+    if app.playback.is_some() && status_text.len() < 3 {
+        status_text.push(Line::from(vec![
+            Span::styled(app.keys.label(Action::TogglePause), key),
+            Span::styled(" pause, ", value),
+            Span::styled(app.keys.label(Action::SeekBackward), key),
+            Span::styled("/", label),
+            Span::styled(app.keys.label(Action::SeekForward), key),
+            Span::styled(" seek, ", value),
+            Span::styled(app.keys.label(Action::ToggleLoop), key),
+            Span::styled(" loop", value),
+        ]));
+    }
+
+    let status = Paragraph::new(status_text).alignment(Alignment::Center);
 
     f.render_widget(status, inner);
 }
 
+fn format_time(t: Duration) -> String {
+    let secs = t.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
 fn draw_frequency_labels(f: &mut Frame, area: Rect, app: &App) {
-    // Frequency range matches the FFT analysis (20 Hz to 20 kHz)
-    let f_lo = 20.0f32;
-    let f_hi = (app.sample_rate as f32 / 2.0).min(20_000.0);
-    
     let label_block = Block::default()
         .borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM)
-        .border_style(Style::default().fg(Color::Rgb(96, 160, 192)));
-    
+        .border_style(Style::default().fg(app.theme.border));
+
     let inner = label_block.inner(area);
     f.render_widget(label_block, area);
-    
-    // Split area for frequency values and label
+
+    // Split area for frequency values, note names and label
+    let mut rows = vec![Constraint::Length(1)]; // Frequency values
+    if app.layout.note_labels {
+        rows.push(Constraint::Length(1)); // Note names
+    }
+    rows.push(Constraint::Length(1)); // "Frequency (Hz)" label
     let freq_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1), // Frequency values
-            Constraint::Length(1), // "Frequency (Hz)" label
-        ])
+        .constraints(rows)
         .split(inner);
-    
-    // Calculate how many labels we can fit
-    let label_spacing = 10; // Minimum characters between labels
-    let max_labels = (inner.width as usize) / label_spacing;
-    let num_labels = max_labels.min(5); // Limit to reasonable number
-    
-    if num_labels > 1 {
-        let mut freq_positions = Vec::new();
-        
-        // Calculate positions and frequencies
-        for i in 0..num_labels {
-            let t = i as f32 / (num_labels - 1) as f32;
-            let freq = f_lo * (f_hi / f_lo).powf(t);
-            let pos = (t * (freq_layout[0].width - 1) as f32) as u16;
-            
-            let freq_str = if freq >= 1000.0 {
-                format!("{:.0}k", freq / 1000.0)
-            } else {
-                format!("{:.0}", freq)
-            };
-            
-            freq_positions.push((pos, freq_str));
-        }
-        
-        // Render frequency values at calculated positions
-        for (pos, freq_str) in freq_positions {
+    // Values line up with the bars, right of the level axis
+    let (_, values_area) = split_axis(freq_layout[0]);
+
+    if let Some(spectrum) = &app.last_spectrum {
+        for (pos, label) in frequency_labels(spectrum, values_area.width, app.analyzer.scale) {
+            let width = label.len() as u16;
             let label_area = Rect {
-                x: freq_layout[0].x + pos.min(freq_layout[0].width.saturating_sub(freq_str.len() as u16)),
-                y: freq_layout[0].y,
-                width: freq_str.len() as u16,
+                x: values_area.x + pos.min(values_area.width.saturating_sub(width)),
+                y: values_area.y,
+                width,
                 height: 1,
             };
-            
-            let freq_label = Paragraph::new(freq_str)
-                .style(Style::default().fg(Color::Rgb(160, 160, 160)));
+            let freq_label = Paragraph::new(label).style(Style::default().fg(app.theme.axis));
             f.render_widget(freq_label, label_area);
         }
+        if app.layout.note_labels {
+            let (_, notes_area) = split_axis(freq_layout[1]);
+            draw_note_labels(f, notes_area, spectrum, app);
+        }
     }
-    
+
     // Add "Frequency (Hz)" subtitle
     let subtitle = Paragraph::new("Frequency (Hz)")
-        .style(Style::default().fg(Color::Rgb(128, 128, 128)))
+        .style(Style::default().fg(app.theme.dim))
         .alignment(Alignment::Center);
-    f.render_widget(subtitle, freq_layout[1]);
+    f.render_widget(subtitle, freq_layout[freq_layout.len() - 1]);
+}
+
+/// Lowest and highest octave labeled on the piano-key row.
+const NOTE_LABEL_OCTAVES: std::ops::RangeInclusive<i32> = 0..=9;
+
+/// Piano-key labels under the bars: the C of every octave, and the detected
+/// note where the tuner would show it.
+fn draw_note_labels(f: &mut Frame, area: Rect, spectrum: &Spectrum, app: &App) {
+    let Some(layout) = BarLayout::new(spectrum.bands.len(), area.width, app.analyzer.scale) else {
+        return;
+    };
+    let place = |midi: i32| {
+        let band = band_position(&spectrum.edges, Note::frequency(midi, app.a4))?;
+        let label = Note { midi, cents: 0.0 }.to_string();
+        let len = label.len() as u16;
+        let pos = layout
+            .column(band)
+            .saturating_sub(len / 2)
+            .min(area.width.saturating_sub(len));
+        Some((pos, label))
+    };
+
+    let detected = spectrum
+        .pitch
+        .and_then(|pitch| place(Note::from_frequency(pitch.frequency, app.a4).midi));
+    let overlaps = |pos: u16, len: u16| {
+        detected
+            .as_ref()
+            .is_some_and(|(p, l)| pos < p + l.len() as u16 + 1 && *p < pos + len + 1)
+    };
+    let mut labels: Vec<(u16, String, Color)> = NOTE_LABEL_OCTAVES
+        .filter_map(|octave| place(12 * (octave + 1)))
+        .filter(|(pos, label)| !overlaps(*pos, label.len() as u16))
+        .map(|(pos, label)| (pos, label, app.theme.axis))
+        .collect();
+    if let Some((pos, label)) = detected {
+        labels.push((pos, label, app.theme.key));
+    }
+    for (pos, label, color) in labels {
+        let label_area = Rect {
+            x: area.x + pos,
+            y: area.y,
+            width: label.len() as u16,
+            height: 1,
+        };
+        f.render_widget(
+            Paragraph::new(label).style(Style::default().fg(color)),
+            label_area,
+        );
+    }
+}
+
+/// Labels under the bars as (column, text), spaced so they never touch.
+///
+/// Fractional-octave bands are labeled with their nominal centers, in steps
+/// that keep 1 kHz labeled. Other scales get round frequencies placed by the
+/// band edges they fall between.
+fn frequency_labels(spectrum: &Spectrum, width: u16, scale: BandScale) -> Vec<(u16, String)> {
+    let bands = spectrum.bands.len();
+    let Some(layout) = BarLayout::new(bands, width, scale) else {
+        return Vec::new();
+    };
+    let Some(per_octave) = scale.bands_per_octave() else {
+        return round_frequency_labels(&spectrum.edges, &layout, width);
+    };
+
+    let labels: Vec<String> = spectrum
+        .nominal_frequencies
+        .iter()
+        .map(|&f| format_frequency(f))
+        .collect();
+    let widest = labels.iter().map(|l| l.len()).max().unwrap_or(0) as f32;
+    let bar_pitch = (layout.bar_width + 1) as f32 / layout.span;
+    let Some(step) = label_steps(per_octave).find(|&step| step as f32 * bar_pitch > widest + 1.0)
+    else {
+        return Vec::new();
+    };
+    let anchor = nearest_band(&spectrum.nominal_frequencies, 1000.0) % step;
+    (anchor..bands)
+        .step_by(step)
+        .map(|band| {
+            let label = &labels[band];
+            let pos = layout.column(band as f32);
+            (pos.saturating_sub(label.len() as u16 / 2), label.clone())
+        })
+        .collect()
+}
+
+/// Band steps between labels: divisors of the bands per octave, then whole octaves.
+fn label_steps(per_octave: u32) -> impl Iterator<Item = usize> {
+    let per_octave = per_octave as usize;
+    (1..per_octave)
+        .filter(move |&d| per_octave.is_multiple_of(d))
+        .chain((0..16).map(move |k| per_octave << k))
+}
+
+fn nearest_band(frequencies: &[f32], target: f32) -> usize {
+    (0..frequencies.len())
+        .min_by(|&a, &b| {
+            let distance = |i: usize| (frequencies[i] / target).ln().abs();
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap_or(0)
+}
+
+/// Labels at 1-2-5 steps of each decade, left to right, skipping any that would
+/// crowd the previous one.
+fn round_frequency_labels(edges: &[f32], layout: &BarLayout, width: u16) -> Vec<(u16, String)> {
+    let mut labels = Vec::new();
+    let mut next_free = 0;
+    for decade in [1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0] {
+        for hz in [decade, 2.0 * decade, 5.0 * decade] {
+            let Some(band) = band_position(edges, hz) else {
+                continue;
+            };
+            let label = format_frequency(hz);
+            let len = label.len() as u16;
+            let pos = layout
+                .column(band)
+                .saturating_sub(len / 2)
+                .min(width.saturating_sub(len));
+            if pos < next_free {
+                continue;
+            }
+            next_free = pos + len + 2;
+            labels.push((pos, label));
+        }
+    }
+    labels
+}
+
+/// Fractional band index of a frequency, found from the band edges; band `i`'s
+/// center sits at `i`.
+fn band_position(edges: &[f32], hz: f32) -> Option<f32> {
+    let i = edges.windows(2).position(|e| e[0] <= hz && hz <= e[1])?;
+    let frac = (hz / edges[i]).ln() / (edges[i + 1] / edges[i]).ln();
+    Some((i as f32 - 0.5 + frac).max(0.0))
+}
+
+/// Formats a frequency with three significant digits, such as 31.5, 630 or 1.25k.
+fn format_frequency(hz: f32) -> String {
+    let (value, suffix) = if hz >= 1000.0 {
+        (hz / 1000.0, "k")
+    } else {
+        (hz, "")
+    };
+    let decimals = if value < 10.0 {
+        2
+    } else if value < 100.0 {
+        1
+    } else {
+        0
+    };
+    let text = format!("{:.*}", decimals, value);
+    let text = if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &text
+    };
+    format!("{}{}", text, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use selara::analyzer::SpectrumAnalyzer;
+
+    #[test]
+    fn features_panel_fits_narrow_widths() {
+        let settings = Settings::default();
+        let mut app = App::new(48_000, "test".to_string(), &settings);
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerConfig::default(), 48_000.0).unwrap();
+        analyzer.push(&vec![0.25; 4096]);
+        while let Some(spectrum) = analyzer.pop() {
+            app.update_spectrum(spectrum);
+        }
+
+        // Around the width that leaves no room for the sparklines
+        for inner_width in [19, 20, 21] {
+            let area = Rect::new(0, 0, inner_width + 2, FEATURES_HEIGHT);
+            let mut terminal = Terminal::new(TestBackend::new(area.width, area.height)).unwrap();
+            terminal.draw(|f| draw_features(f, area, &app)).unwrap();
+        }
+    }
 }
@@ -0,0 +1,119 @@
+use clap::ValueEnum;
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use selara::types::Spectrum;
+
+pub const MIN_HISTORY: usize = 64;
+pub const MAX_HISTORY: usize = 4096;
+pub const DEFAULT_HISTORY: usize = 512;
+
+/// Colors the waterfall maps levels through, from quiet to loud.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Magma,
+    Grayscale,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 3] = [Colormap::Viridis, Colormap::Magma, Colormap::Grayscale];
+
+    /// The colormap after this one in [`Colormap::ALL`], wrapping around.
+    pub fn next(self) -> Colormap {
+        let i = Self::ALL.iter().position(|&c| c == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Evenly spaced stops, sampled from matplotlib's maps of the same names.
+    pub fn stops(self) -> &'static [Color] {
+        match self {
+            Colormap::Viridis => &[
+                Color::Rgb(0x44, 0x01, 0x54),
+                Color::Rgb(0x47, 0x2d, 0x7b),
+                Color::Rgb(0x3b, 0x52, 0x8b),
+                Color::Rgb(0x2c, 0x72, 0x8e),
+                Color::Rgb(0x21, 0x91, 0x8c),
+                Color::Rgb(0x28, 0xae, 0x80),
+                Color::Rgb(0x5e, 0xc9, 0x62),
+                Color::Rgb(0xad, 0xdc, 0x30),
+                Color::Rgb(0xfd, 0xe7, 0x25),
+            ],
+            Colormap::Magma => &[
+                Color::Rgb(0x00, 0x00, 0x04),
+                Color::Rgb(0x1c, 0x10, 0x44),
+                Color::Rgb(0x4f, 0x12, 0x7b),
+                Color::Rgb(0x81, 0x25, 0x81),
+                Color::Rgb(0xb5, 0x36, 0x7a),
+                Color::Rgb(0xe5, 0x50, 0x64),
+                Color::Rgb(0xfb, 0x87, 0x61),
+                Color::Rgb(0xfe, 0xc2, 0x87),
+                Color::Rgb(0xfc, 0xfd, 0xbf),
+            ],
+            Colormap::Grayscale => &[Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255)],
+        }
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Colormap::Viridis => "Viridis",
+            Colormap::Magma => "Magma",
+            Colormap::Grayscale => "Grayscale",
+        })
+    }
+}
+
+/// The most recent spectra, newest first.
+pub struct Waterfall {
+    rows: VecDeque<Spectrum>,
+    capacity: usize,
+}
+
+impl Waterfall {
+    pub fn new(capacity: usize) -> Waterfall {
+        Waterfall {
+            rows: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Number of spectra the waterfall spans once full.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.rows.truncate(capacity);
+    }
+
+    /// Adds the newest spectrum. Rows with other bands, from before a scale or
+    /// device change, are dropped.
+    pub fn push(&mut self, spectrum: &Spectrum) {
+        if let Some(front) = self.rows.front()
+            && !Arc::ptr_eq(&front.frequencies, &spectrum.frequencies)
+        {
+            self.rows.clear();
+        }
+        if self.rows.len() == self.capacity {
+            self.rows.pop_back();
+        }
+        self.rows.push_front(spectrum.clone());
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// Spectra from newest to oldest.
+    pub fn rows(&self) -> impl Iterator<Item = &Spectrum> {
+        self.rows.iter()
+    }
+}
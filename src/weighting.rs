@@ -0,0 +1,399 @@
+use clap::ValueEnum;
+use realfft::num_complex::Complex64;
+use serde::Deserialize;
+use std::f64::consts::TAU;
+use std::fmt;
+
+/// Frequency weighting applied to the spectrum bands and the level meter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Weighting {
+    /// Flat, unweighted
+    #[default]
+    Z,
+    /// IEC 61672-1 A-weighting, following hearing at low levels
+    A,
+    /// IEC 61672-1 C-weighting, flat apart from the extremes
+    C,
+    /// ITU-R BS.468-4 noise weighting
+    Itu468,
+}
+
+impl Weighting {
+    pub const ALL: [Weighting; 4] = [Weighting::Z, Weighting::A, Weighting::C, Weighting::Itu468];
+
+    /// The weighting after this one in [`Weighting::ALL`], wrapping around.
+    pub fn next(self) -> Weighting {
+        let i = Self::ALL.iter().position(|&w| w == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Gain of the weighting at `freq` Hz in dB, 0 at 1 kHz.
+    pub fn gain_db(self, freq: f32) -> f32 {
+        let prototype = self.prototype();
+        let response = |freq: f64| {
+            let s = Complex64::new(0.0, freq);
+            let poles: f64 = prototype
+                .poles
+                .iter()
+                .flat_map(|pair| pair.poles())
+                .map(|pole| (s - pole).norm())
+                .product();
+            freq.powi(prototype.dc_zeros as i32) / poles
+        };
+        (20.0 * (response(freq as f64) / response(1_000.0)).log10()) as f32
+    }
+
+    fn prototype(self) -> Prototype {
+        match self {
+            Weighting::Z => Prototype {
+                dc_zeros: 0,
+                poles: &[],
+                nyquist_zeros: 0,
+            },
+            Weighting::A => Prototype {
+                dc_zeros: 4,
+                poles: &[
+                    PolePair::Real(IEC_F1, IEC_F1),
+                    PolePair::Real(IEC_F2, IEC_F3),
+                    PolePair::Real(IEC_F4, IEC_F4),
+                ],
+                nyquist_zeros: 1,
+            },
+            Weighting::C => Prototype {
+                dc_zeros: 2,
+                poles: &[
+                    PolePair::Real(IEC_F1, IEC_F1),
+                    PolePair::Real(IEC_F4, IEC_F4),
+                ],
+                nyquist_zeros: 1,
+            },
+            // The roots of the denominator of the BS.468-4 response formula
+            Weighting::Itu468 => Prototype {
+                dc_zeros: 1,
+                poles: &[
+                    PolePair::Real(4_122.702_066_134_758, 9_975.063_123_930_291),
+                    PolePair::Complex(-3_758.529_163_010_729, 5_790.042_336_608_956),
+                    PolePair::Complex(-2_983.159_937_890_675, 9_940.842_645_709_552),
+                ],
+                nyquist_zeros: 2,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Weighting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Weighting::Z => "Z",
+            Weighting::A => "A",
+            Weighting::C => "C",
+            Weighting::Itu468 => "ITU-R 468",
+        })
+    }
+}
+
+/// Pole frequencies of the A- and C-weighting networks, IEC 61672-1 Annex E.
+const IEC_F1: f64 = 20.598_997;
+const IEC_F2: f64 = 107.652_65;
+const IEC_F3: f64 = 737.862_23;
+const IEC_F4: f64 = 12_194.217;
+
+/// The analog network behind a weighting: zeros at DC and poles in Hz.
+struct Prototype {
+    dc_zeros: usize,
+    poles: &'static [PolePair],
+    /// Zeros the digital filter adds at Nyquist. They stand in for the
+    /// network's excess poles, which would otherwise alias and lift the top
+    /// octave.
+    nyquist_zeros: usize,
+}
+
+/// Two poles of an analog prototype, in Hz, realized as one biquad.
+#[derive(Clone, Copy)]
+enum PolePair {
+    /// Real poles at -f1 and -f2.
+    Real(f64, f64),
+    /// A complex-conjugate pair at re ± j·im.
+    Complex(f64, f64),
+}
+
+impl PolePair {
+    fn poles(self) -> [Complex64; 2] {
+        match self {
+            PolePair::Real(f1, f2) => [Complex64::new(-f1, 0.0), Complex64::new(-f2, 0.0)],
+            PolePair::Complex(re, im) => [Complex64::new(re, im), Complex64::new(re, -im)],
+        }
+    }
+}
+
+/// Runs samples through a weighting as a cascade of biquads, for meters that
+/// measure the weighted signal.
+///
+/// Poles are placed by the matched-z transform, so the response follows the
+/// analog network closely up to several kHz at common sample rates; it is
+/// then scaled to 0 dB at 1 kHz.
+///
+/// ```
+/// use selara::weighting::{Weighting, WeightingFilter};
+///
+/// // A-weighting takes about 19 dB off a 100 Hz tone
+/// let mut samples: Vec<f32> = (0..48_000)
+///     .map(|i| (std::f32::consts::TAU * 100.0 * i as f32 / 48_000.0).sin())
+///     .collect();
+/// WeightingFilter::new(Weighting::A, 48_000.0).process(&mut samples);
+/// let peak = samples[24_000..].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+/// assert!((20.0 * peak.log10() + 19.1).abs() < 0.2);
+/// ```
+pub struct WeightingFilter {
+    weighting: Weighting,
+    sections: Vec<Biquad>,
+    gain: f64,
+}
+
+impl WeightingFilter {
+    pub fn new(weighting: Weighting, sample_rate: f32) -> WeightingFilter {
+        let sample_rate = sample_rate as f64;
+        let prototype = weighting.prototype();
+        let mut zeros = std::iter::repeat_n(1.0, prototype.dc_zeros)
+            .chain(std::iter::repeat_n(-1.0, prototype.nyquist_zeros));
+        let sections: Vec<Biquad> = prototype
+            .poles
+            .iter()
+            .map(|pair| {
+                let [p1, p2] = pair.poles().map(|pole| (pole * TAU / sample_rate).exp());
+                let q1 = zeros.next().unwrap_or(0.0);
+                let q2 = zeros.next().unwrap_or(0.0);
+                Biquad::new([1.0, -(q1 + q2), q1 * q2], [-(p1 + p2).re, (p1 * p2).re])
+            })
+            .collect();
+
+        let w = TAU * 1_000.0 / sample_rate;
+        let gain = 1.0 / sections.iter().map(|s| s.magnitude(w)).product::<f64>();
+        WeightingFilter {
+            weighting,
+            sections,
+            gain,
+        }
+    }
+
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+
+    /// Weights a block of samples of one channel in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if self.sections.is_empty() {
+            return;
+        }
+        for sample in samples {
+            let y = self
+                .sections
+                .iter_mut()
+                .fold(*sample as f64, |x, section| section.process(x));
+            *sample = (y * self.gain) as f32;
+        }
+    }
+}
+
+/// A second-order IIR section in transposed direct form II, with a0 = 1.
+pub(crate) struct Biquad {
+    pub(crate) b: [f64; 3],
+    pub(crate) a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    pub(crate) fn new(b: [f64; 3], a: [f64; 2]) -> Biquad {
+        Biquad { b, a, z: [0.0; 2] }
+    }
+
+    pub(crate) fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+
+    /// Magnitude of the response at `w` radians per sample.
+    fn magnitude(&self, w: f64) -> f64 {
+        let z1 = Complex64::from_polar(1.0, -w);
+        let z2 = z1 * z1;
+        let num = self.b[0] + self.b[1] * z1 + self.b[2] * z2;
+        let den = 1.0 + self.a[0] * z1 + self.a[1] * z2;
+        (num / den).norm()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IEC 61672-1 Table 3 frequencies, 10 Hz to 20 kHz in third octaves.
+    fn table_frequencies() -> impl Iterator<Item = f32> {
+        (-20..=13).map(|n| 1_000.0 * 10f32.powf(n as f32 / 10.0))
+    }
+
+    /// IEC 61672-1 Table 3 A-weightings, dB.
+    const A_TABLE: [f32; 34] = [
+        -70.4, -63.4, -56.7, -50.5, -44.7, -39.4, -34.6, -30.2, -26.2, -22.5, -19.1, -16.1, -13.4,
+        -10.9, -8.6, -6.6, -4.8, -3.2, -1.9, -0.8, 0.0, 0.6, 1.0, 1.2, 1.3, 1.2, 1.0, 0.5, -0.1,
+        -1.1, -2.5, -4.3, -6.6, -9.3,
+    ];
+
+    /// IEC 61672-1 Table 3 C-weightings, dB.
+    const C_TABLE: [f32; 34] = [
+        -14.3, -11.2, -8.5, -6.2, -4.4, -3.0, -2.0, -1.3, -0.8, -0.5, -0.3, -0.2, -0.1, 0.0, 0.0,
+        0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.1, -0.2, -0.3, -0.5, -0.8, -1.3, -2.0, -3.0, -4.4,
+        -6.2, -8.5, -11.2,
+    ];
+
+    /// ITU-R BS.468-4 Table 1, (Hz, dB).
+    const ITU_468_TABLE: [(f32, f32); 21] = [
+        (31.5, -29.9),
+        (63.0, -23.9),
+        (100.0, -19.8),
+        (200.0, -13.8),
+        (400.0, -7.8),
+        (800.0, -1.9),
+        (1_000.0, 0.0),
+        (2_000.0, 5.6),
+        (3_150.0, 9.0),
+        (4_000.0, 10.5),
+        (5_000.0, 11.7),
+        (6_300.0, 12.2),
+        (7_100.0, 12.0),
+        (8_000.0, 11.4),
+        (9_000.0, 10.1),
+        (10_000.0, 8.1),
+        (12_500.0, 0.0),
+        (14_000.0, -5.3),
+        (16_000.0, -11.7),
+        (20_000.0, -22.2),
+        (31_500.0, -42.7),
+    ];
+
+    /// IEC 61672-1 class 1 limits above 6.3 kHz, (Hz, +dB, -dB).
+    const CLASS_1_HIGH: [(f32, f32, f32); 5] = [
+        (8_000.0, 1.5, 2.5),
+        (10_000.0, 2.0, 3.0),
+        (12_500.0, 2.0, 5.0),
+        (16_000.0, 3.5, 17.0),
+        (20_000.0, 4.0, f32::INFINITY),
+    ];
+
+    /// Gain of a filter at `freq` Hz, from its coefficients.
+    fn filter_db(filter: &WeightingFilter, freq: f32, sample_rate: f32) -> f32 {
+        let w = TAU * freq as f64 / sample_rate as f64;
+        let magnitude: f64 = filter.sections.iter().map(|s| s.magnitude(w)).product();
+        (20.0 * (magnitude * filter.gain).log10()) as f32
+    }
+
+    fn assert_matches_table(weighting: Weighting, table: &[f32]) {
+        // The table is rounded to 0.1 dB
+        for (freq, &expected) in table_frequencies().zip(table) {
+            let gain = weighting.gain_db(freq);
+            assert!(
+                (gain - expected).abs() < 0.06,
+                "{} at {} Hz: expected {}, got {}",
+                weighting,
+                freq,
+                expected,
+                gain
+            );
+        }
+    }
+
+    #[test]
+    fn a_weighting_matches_iec_61672() {
+        assert_matches_table(Weighting::A, &A_TABLE);
+    }
+
+    #[test]
+    fn c_weighting_matches_iec_61672() {
+        assert_matches_table(Weighting::C, &C_TABLE);
+    }
+
+    #[test]
+    fn itu_468_weighting_matches_its_table() {
+        for (freq, expected) in ITU_468_TABLE {
+            let gain = Weighting::Itu468.gain_db(freq);
+            assert!(
+                (gain - expected).abs() < 0.1,
+                "{} Hz: expected {}, got {}",
+                freq,
+                expected,
+                gain
+            );
+        }
+    }
+
+    #[test]
+    fn filters_meet_class_1_tolerances() {
+        for sample_rate in [44_100.0, 48_000.0] {
+            for (weighting, table) in [(Weighting::A, &A_TABLE), (Weighting::C, &C_TABLE)] {
+                let filter = WeightingFilter::new(weighting, sample_rate);
+                for (freq, &expected) in table_frequencies().zip(table.iter()) {
+                    let error = filter_db(&filter, freq, sample_rate) - expected;
+                    let (plus, minus) = CLASS_1_HIGH
+                        .iter()
+                        .find(|&&(f, _, _)| (f / freq - 1.0).abs() < 0.03)
+                        .map_or((0.5, 0.5), |&(_, plus, minus)| (plus, minus));
+                    assert!(
+                        error <= plus && error >= -minus,
+                        "{} at {} Hz, {} Hz: off by {} dB",
+                        weighting,
+                        sample_rate,
+                        freq,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn itu_468_filter_follows_the_curve_to_10k() {
+        let filter = WeightingFilter::new(Weighting::Itu468, 48_000.0);
+        for (freq, expected) in ITU_468_TABLE.iter().filter(|&&(f, _)| f <= 10_000.0) {
+            let gain = filter_db(&filter, *freq, 48_000.0);
+            assert!(
+                (gain - expected).abs() < 1.0,
+                "{} Hz: expected {}, got {}",
+                freq,
+                expected,
+                gain
+            );
+        }
+    }
+
+    #[test]
+    fn z_weighting_passes_samples_through() {
+        assert_eq!(Weighting::Z.gain_db(20.0), 0.0);
+        let mut samples = [0.5, -0.25, 1.0];
+        WeightingFilter::new(Weighting::Z, 48_000.0).process(&mut samples);
+        assert_eq!(samples, [0.5, -0.25, 1.0]);
+    }
+
+    #[test]
+    fn weighted_sine_reads_the_weighted_level() {
+        let rate = 48_000.0;
+        for freq in [50.0, 1_000.0, 4_000.0] {
+            let mut samples: Vec<f32> = (0..48_000)
+                .map(|i| (std::f32::consts::TAU * freq * i as f32 / rate).sin())
+                .collect();
+            WeightingFilter::new(Weighting::A, rate).process(&mut samples);
+            let settled = &samples[24_000..];
+            let rms = (settled.iter().map(|s| s * s).sum::<f32>() / settled.len() as f32).sqrt();
+            let level = 20.0 * (rms * 2f32.sqrt()).log10();
+            let expected = Weighting::A.gain_db(freq);
+            assert!(
+                (level - expected).abs() < 0.2,
+                "{} Hz: expected {}, got {}",
+                freq,
+                expected,
+                level
+            );
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Window applied to each FFT frame before the transform.
+///
+/// The Kaiser window takes its shape from `AnalyzerConfig::kaiser_beta`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowFunction {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    BlackmanHarris,
+    FlatTop,
+    Kaiser,
+}
+
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 7] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::FlatTop,
+        WindowFunction::Kaiser,
+    ];
+
+    /// The window after this one in [`WindowFunction::ALL`], wrapping around.
+    pub fn next(self) -> WindowFunction {
+        let i = Self::ALL.iter().position(|&w| w == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Display name, including β for the Kaiser window.
+    pub fn label(self, kaiser_beta: f32) -> String {
+        match self {
+            WindowFunction::Kaiser => format!("Kaiser β={}", kaiser_beta),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Periodic window of `size` samples, as used for spectral analysis.
+    pub fn coefficients(self, size: usize, kaiser_beta: f32) -> Vec<f32> {
+        let n = size as f64;
+        // Sum-of-cosines windows: a0 - a1 cos(x) + a2 cos(2x) - ...
+        let cosine_sum = |a: &[f64]| -> Vec<f32> {
+            (0..size)
+                .map(|i| {
+                    let x = 2.0 * PI * i as f64 / n;
+                    a.iter()
+                        .enumerate()
+                        .map(|(k, &ak)| {
+                            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                            sign * ak * (k as f64 * x).cos()
+                        })
+                        .sum::<f64>() as f32
+                })
+                .collect()
+        };
+
+        match self {
+            WindowFunction::Rectangular => vec![1.0; size],
+            WindowFunction::Hann => cosine_sum(&[0.5, 0.5]),
+            WindowFunction::Hamming => cosine_sum(&[0.54, 0.46]),
+            WindowFunction::Blackman => cosine_sum(&[0.42, 0.5, 0.08]),
+            WindowFunction::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168]),
+            WindowFunction::FlatTop => cosine_sum(&[
+                0.21557895,
+                0.41663158,
+                0.277263158,
+                0.083578947,
+                0.006947368,
+            ]),
+            WindowFunction::Kaiser => {
+                let beta = kaiser_beta as f64;
+                let norm = bessel_i0(beta);
+                (0..size)
+                    .map(|i| {
+                        let r = 2.0 * i as f64 / n - 1.0;
+                        (bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / norm) as f32
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            WindowFunction::Rectangular => "Rectangular",
+            WindowFunction::Hann => "Hann",
+            WindowFunction::Hamming => "Hamming",
+            WindowFunction::Blackman => "Blackman",
+            WindowFunction::BlackmanHarris => "Blackman-Harris",
+            WindowFunction::FlatTop => "Flat-top",
+            WindowFunction::Kaiser => "Kaiser",
+        })
+    }
+}
+
+/// Coherent gain: the window's mean, i.e. how much it attenuates a bin-centered sine.
+pub fn coherent_gain(window: &[f32]) -> f32 {
+    window.iter().sum::<f32>() / window.len() as f32
+}
+
+/// Equivalent noise bandwidth in bins.
+pub fn enbw(window: &[f32]) -> f32 {
+    let sum: f32 = window.iter().sum();
+    let sum_sq: f32 = window.iter().map(|w| w * w).sum();
+    window.len() as f32 * sum_sq / (sum * sum)
+}
+
+/// Zeroth-order modified Bessel function of the first kind.
+pub(crate) fn bessel_i0(x: f64) -> f64 {
+    let half = x / 2.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 1..64 {
+        term *= (half / k as f64).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn known_gains() {
+        // Reference values from Heinzel, Rüdiger & Schilling (2002)
+        let cases = [
+            (WindowFunction::Rectangular, 1.0, 1.0),
+            (WindowFunction::Hann, 0.5, 1.5),
+            (WindowFunction::Hamming, 0.54, 1.3628),
+            (WindowFunction::Blackman, 0.42, 1.7268),
+            (WindowFunction::BlackmanHarris, 0.35875, 2.0044),
+            (WindowFunction::FlatTop, 0.2156, 3.7702),
+        ];
+        for (window, cg, bw) in cases {
+            let w = window.coefficients(4096, 0.0);
+            assert_close(coherent_gain(&w), cg, 1e-3);
+            assert_close(enbw(&w), bw, 1e-3);
+        }
+    }
+
+    #[test]
+    fn kaiser_matches_rectangular_at_zero_beta() {
+        let w = WindowFunction::Kaiser.coefficients(256, 0.0);
+        assert!(w.iter().all(|&x| (x - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn kaiser_peaks_at_center() {
+        let w = WindowFunction::Kaiser.coefficients(256, 8.6);
+        assert_close(w[128], 1.0, 1e-6);
+        assert!(w[0] < 2e-3);
+        // β = 8.6 is close to Blackman in bandwidth
+        assert_close(enbw(&w), 1.72, 0.02);
+    }
+
+    #[test]
+    fn next_cycles_through_all() {
+        let mut window = WindowFunction::Rectangular;
+        for _ in 0..WindowFunction::ALL.len() {
+            window = window.next();
+        }
+        assert_eq!(window, WindowFunction::Rectangular);
+    }
+}